@@ -0,0 +1,2 @@
+-- Allow jobs to only notify on openings without attempting enrollment
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS enroll BOOLEAN NOT NULL DEFAULT true;
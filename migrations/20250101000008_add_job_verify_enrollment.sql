@@ -0,0 +1,2 @@
+-- Confirm reported enrollments against the user's schedule before counting them
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS verify_enrollment BOOLEAN NOT NULL DEFAULT true;
@@ -0,0 +1,2 @@
+-- Stop the whole job once any monitored section is enrolled
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS stop_on_first_success BOOLEAN NOT NULL DEFAULT false;
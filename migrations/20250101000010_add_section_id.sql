@@ -0,0 +1,2 @@
+-- Optional WebReg section id, matched instead of the lecture code when set
+ALTER TABLE sections ADD COLUMN IF NOT EXISTS section_id TEXT;
@@ -0,0 +1,2 @@
+-- Automatically stop a job after running this many hours (NULL = unlimited)
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS max_run_duration_hours INTEGER;
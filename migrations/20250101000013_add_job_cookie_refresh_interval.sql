@@ -0,0 +1,2 @@
+-- Seconds between WebReg session checks for a job
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS cookie_refresh_interval INTEGER NOT NULL DEFAULT 480;
@@ -0,0 +1,2 @@
+-- Optional URL that receives a JSON POST whenever a job enrolls successfully
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS success_webhook_url TEXT;
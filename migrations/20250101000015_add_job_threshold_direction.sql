@@ -0,0 +1,2 @@
+-- Whether seat_threshold is a ceiling (at_most) or a floor (at_least)
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS threshold_direction TEXT NOT NULL DEFAULT 'at_most';
@@ -0,0 +1,12 @@
+-- Every add_section call a job makes, with WebReg's result or error
+CREATE TABLE IF NOT EXISTS enrollment_attempts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    job_id UUID NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    section VARCHAR(50) NOT NULL,
+    section_id VARCHAR(50) NOT NULL,
+    success BOOLEAN NOT NULL,
+    result TEXT NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_enrollment_attempts_job_id ON enrollment_attempts(job_id, attempted_at DESC);
@@ -0,0 +1,3 @@
+-- Optional destinations for connectivity alerts (cookie expiry), kept apart from opening alerts
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS alert_webhook_url TEXT;
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS alert_email_recipients JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,7 @@
+-- WebReg requests each user made per day, for the daily request quota
+CREATE TABLE IF NOT EXISTS user_request_counts (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    request_count BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, day)
+);
@@ -0,0 +1,3 @@
+-- Optional second cookie a job fails over to when the primary expires
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS backup_cookie_encrypted TEXT;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS backup_encryption_nonce TEXT;
@@ -0,0 +1,4 @@
+-- How many enrollment attempts a job runs at once when several sections open in one cycle
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS max_concurrent_enrolls INTEGER NOT NULL DEFAULT 1;
+-- Skip the cycle's remaining candidates once one enrollment succeeds
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS stop_cycle_on_success BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Per-section enroll/waitlist preference; NULL follows the job's monitoring mode
+ALTER TABLE sections ADD COLUMN IF NOT EXISTS enroll_target TEXT;
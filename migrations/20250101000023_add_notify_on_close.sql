@@ -0,0 +1,2 @@
+-- Opt-in alert when a section that was reported open closes again
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS notify_on_close BOOLEAN NOT NULL DEFAULT FALSE;
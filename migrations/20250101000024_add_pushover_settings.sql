@@ -0,0 +1,3 @@
+-- Pushover delivery; both are needed to send
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS pushover_user_key TEXT;
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS pushover_api_token TEXT;
@@ -0,0 +1,3 @@
+-- Re-check enrolled sections and re-enroll when WebReg drops them
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS watch_enrolled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS held_sections JSONB NOT NULL DEFAULT '[]'::jsonb;
@@ -0,0 +1,2 @@
+-- Whether a job polls right at start or after a staggered delay
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS poll_immediately BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,2 @@
+-- Lecture and discussions enrolled together, rolled back if any add fails
+ALTER TABLE sections ADD COLUMN IF NOT EXISTS atomic BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Alert when a job's WebReg session works again after the cookie expired
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS notify_on_recovery BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,2 @@
+-- At most one of the group's discussions is enrolled; the rest stop once one is taken
+ALTER TABLE sections ADD COLUMN IF NOT EXISTS one_discussion BOOLEAN NOT NULL DEFAULT FALSE;
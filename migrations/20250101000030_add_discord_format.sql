@@ -0,0 +1,2 @@
+-- "plain" posts the notification text; "embed" posts a color-coded embed with course fields
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS discord_format TEXT NOT NULL DEFAULT 'plain';
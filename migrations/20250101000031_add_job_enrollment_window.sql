@@ -0,0 +1,3 @@
+-- The user's enrollment appointment; the job only monitors until then unless force_enroll is set
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS enrollment_opens_at TIMESTAMPTZ;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS force_enroll BOOLEAN NOT NULL DEFAULT FALSE;
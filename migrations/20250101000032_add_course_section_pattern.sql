@@ -0,0 +1,2 @@
+-- Sections matching this pattern (e.g. "A*") are found in WebReg's listing and monitored too
+ALTER TABLE courses ADD COLUMN IF NOT EXISTS section_pattern TEXT;
@@ -0,0 +1,2 @@
+-- The lecture is never enrolled on its own, only through one of the group's discussions
+ALTER TABLE sections ADD COLUMN IF NOT EXISTS discussion_required BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- "primary" or "backup"; enrolling in a primary course stops monitoring the job's backups
+ALTER TABLE courses ADD COLUMN IF NOT EXISTS role TEXT;
@@ -0,0 +1,8 @@
+-- Defaults a user's new jobs take for the fields their create request leaves out
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    polling_interval INTEGER,
+    seat_threshold INTEGER,
+    monitoring_mode VARCHAR(50),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
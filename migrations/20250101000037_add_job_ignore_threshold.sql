@@ -0,0 +1,2 @@
+-- Attempt enrollment on any open seat, regardless of the seat threshold
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS ignore_threshold BOOLEAN NOT NULL DEFAULT FALSE;
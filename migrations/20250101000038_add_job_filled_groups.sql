@@ -0,0 +1,2 @@
+-- One-discussion groups whose discussion the job already took, so a restart leaves them alone
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS filled_groups JSONB NOT NULL DEFAULT '[]'::jsonb;
@@ -0,0 +1,4 @@
+-- The Pushover token is a secret like the gmail password, so it's stored encrypted too.
+-- A token saved in the clear before this is still read until the user saves a new one.
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS pushover_api_token_encrypted TEXT;
+ALTER TABLE notification_settings ADD COLUMN IF NOT EXISTS pushover_encryption_nonce TEXT;
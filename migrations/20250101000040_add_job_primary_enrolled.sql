@@ -0,0 +1,2 @@
+-- Set once a primary course is enrolled or waitlisted; the job's backup courses stay stopped after a restart
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS primary_enrolled BOOLEAN NOT NULL DEFAULT FALSE;
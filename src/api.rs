@@ -6,10 +6,12 @@ use axum::{
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::config::SectionGroup;
+use crate::config::{DiscordFormat, MonitoringMode, SectionGroup, ThresholdDirection};
+use crate::stats::SectionFailures;
 
 // API Types
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,16 +27,13 @@ pub struct JobConfig {
     pub term: String,
     pub polling_interval: u64,
     pub cookie: String,
+    #[serde(default)]
+    pub backup_cookie: Option<String>,
     pub courses: Vec<CourseRequest>,
     pub seat_threshold: i64,
     pub monitoring_mode: MonitoringMode,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "lowercase")]
-pub enum MonitoringMode {
-    Include,  // Only enroll when seats are available (seat_threshold = 0)
-    Exclude,  // Only enroll when seats are limited (seat_threshold > 0)
+    #[serde(default)]
+    pub threshold_direction: ThresholdDirection,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,6 +50,8 @@ pub struct StatusResponse {
     pub last_check_time: String,
     pub stats: StatsResponse,
     pub health: String,
+    pub avg_latency_ms: Option<f64>,
+    pub p95_latency_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,6 +59,7 @@ pub struct StatsResponse {
     pub enrollment_attempts: u64,
     pub successful_enrollments: u64,
     pub errors: u64,
+    pub section_failures: HashMap<String, SectionFailures>,  // Failed enrollments per section today
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,6 +68,24 @@ pub struct NotificationConfig {
     pub gmail_app_password: String,
     pub email_recipients: Vec<String>,
     pub discord_webhook_url: String,
+    #[serde(default)]
+    pub alert_webhook_url: String,
+    #[serde(default)]
+    pub alert_email_recipients: Vec<String>,
+    #[serde(default)]
+    pub notify_on_close: bool,
+    #[serde(default = "default_notify_on_recovery")]
+    pub notify_on_recovery: bool,
+    #[serde(default)]
+    pub discord_format: DiscordFormat,
+    #[serde(default)]
+    pub pushover_user_key: String,
+    #[serde(default)]
+    pub pushover_api_token: String,
+}
+
+fn default_notify_on_recovery() -> bool {
+    true
 }
 
 use crate::job_manager::JobManager;
@@ -96,7 +116,10 @@ async fn get_status(State(state): State<Arc<ApiState>>) -> Result<Json<StatusRes
             enrollment_attempts: app_state.stats.enrollment_attempts,
             successful_enrollments: app_state.stats.successful_enrollments,
             errors: app_state.stats.errors,
+            section_failures: app_state.stats.section_failures.clone(),
         },
+        avg_latency_ms: health.avg_latency_ms,
+        p95_latency_ms: health.p95_latency_ms,
         health: format!("{:?}", health),
     }))
 }
@@ -111,12 +134,15 @@ async fn create_job(
     app_state.config.webreg.term = config.term.clone();
     app_state.config.webreg.polling_interval = config.polling_interval;
     app_state.config.webreg.cookie = config.cookie.clone();
+    app_state.config.webreg.backup_cookie = config.backup_cookie.clone().filter(|c| !c.trim().is_empty());
 
     // Set seat threshold based on monitoring mode
     app_state.config.monitoring.seat_threshold = match config.monitoring_mode {
-        MonitoringMode::Include => 0,  // Any availability
-        MonitoringMode::Exclude => config.seat_threshold,  // Custom threshold
+        MonitoringMode::Available => 0,  // Any availability
+        MonitoringMode::LowSeats | MonitoringMode::WaitlistOnly => config.seat_threshold,  // Custom threshold
     };
+    app_state.config.monitoring.mode = Some(config.monitoring_mode);
+    app_state.config.monitoring.threshold_direction = config.threshold_direction;
 
     let job_id = Uuid::new_v4().to_string();
 
@@ -164,7 +190,8 @@ async fn get_config(State(state): State<Arc<ApiState>>) -> Result<Json<serde_jso
         "term": app_state.config.webreg.term,
         "polling_interval": app_state.config.webreg.polling_interval,
         "seat_threshold": app_state.config.monitoring.seat_threshold,
-        "monitoring_mode": if app_state.config.monitoring.seat_threshold == 0 { "include" } else { "exclude" }
+        "monitoring_mode": app_state.config.monitoring.effective_mode(),
+        "threshold_direction": app_state.config.monitoring.threshold_direction
     })))
 }
 
@@ -178,6 +205,13 @@ async fn update_notifications(
     app_state.config.notifications.gmail_app_password = config.gmail_app_password;
     app_state.config.notifications.email_recipients = config.email_recipients;
     app_state.config.notifications.discord_webhook_url = config.discord_webhook_url;
+    app_state.config.notifications.alert_webhook_url = config.alert_webhook_url;
+    app_state.config.notifications.alert_email_recipients = config.alert_email_recipients;
+    app_state.config.notifications.notify_on_close = config.notify_on_close;
+    app_state.config.notifications.notify_on_recovery = config.notify_on_recovery;
+    app_state.config.notifications.discord_format = config.discord_format;
+    app_state.config.notifications.pushover_user_key = config.pushover_user_key;
+    app_state.config.notifications.pushover_api_token = config.pushover_api_token;
 
     Ok(Json(JobResponse {
         job_id: "".to_string(),
@@ -186,12 +220,37 @@ async fn update_notifications(
     }))
 }
 
+async fn reload_config(State(state): State<Arc<ApiState>>) -> Result<Json<JobResponse>, StatusCode> {
+    let mut app_state = state.job_manager.state.lock().await;
+
+    match app_state.reload_config().await {
+        Ok(changes) => {
+            let message = if changes.is_empty() {
+                "Config reloaded, nothing changed".to_string()
+            } else {
+                format!("Config reloaded, applied changes to: {}", changes.join(", "))
+            };
+            Ok(Json(JobResponse {
+                job_id: "".to_string(),
+                status: "reloaded".to_string(),
+                message,
+            }))
+        }
+        Err(e) => Ok(Json(JobResponse {
+            job_id: "".to_string(),
+            status: "error".to_string(),
+            message: e.to_string(),
+        })),
+    }
+}
+
 // Create router
 pub fn create_router(api_state: Arc<ApiState>) -> Router {
     Router::new()
         .route("/api/health", get(health_check))
         .route("/api/status", get(get_status))
         .route("/api/config", get(get_config))
+        .route("/api/config/reload", post(reload_config))
         .route("/api/jobs", post(create_job))
         .route("/api/jobs/start", post(start_monitoring))
         .route("/api/jobs/stop", post(stop_monitoring))
@@ -1,11 +1,45 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use chrono_tz::Tz;
+use crate::utils::{GraceRetry, RetryPolicy};
 use serde::{Deserialize, Serialize};
 
 // Constants
 pub const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
 pub const DEFAULT_RETRY_DELAY: u64 = 1000;
 pub const CONFIG_PATH: &str = "config.toml";
+pub const LOG_FILE: &str = "webreg_monitor.log";
+pub const SECTION_DETAILS_LOG: &str = "section_details.log";
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 10;
+pub const DEFAULT_DB_MAX_CONNECTIONS: u32 = 5;
+pub const DEFAULT_DB_MIN_CONNECTIONS: u32 = 0;
+pub const DEFAULT_DB_ACQUIRE_TIMEOUT: u64 = 30;
+pub const DEFAULT_STATS_FLUSH_INTERVAL: u64 = 30;
+pub const DEFAULT_FAILURE_NOTIFICATION_CAP: u64 = 3;
+pub const DEFAULT_MIN_POLLING_INTERVAL: u64 = 3;
+pub const DEFAULT_MAX_COURSES_PER_JOB: usize = 10;
+pub const DEFAULT_MAX_SECTIONS_PER_COURSE: usize = 25;
+pub const DEFAULT_GRACE_RETRIES: u32 = 2;
+pub const DEFAULT_GRACE_RETRY_DELAY_MS: u64 = 150;
+pub const DEFAULT_CYCLE_RETRY_BUDGET: u32 = 10;
+pub const DEFAULT_WATCHDOG_STALL_INTERVALS: u32 = 5;
+pub const DEFAULT_POLL_BACKOFF_MAX_SKIP: u32 = 4;
+pub const DEFAULT_WEBREG_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_SECTION_TIMEOUT_SECS: u64 = 120;
+pub const DEFAULT_NOTIFICATION_MAX_ATTEMPTS: u32 = 10;
+pub const DEFAULT_NOTIFICATION_RETRY_DELAY_SECS: u64 = 30;
+pub const DEFAULT_NOTIFICATION_RETENTION_DAYS: u32 = 7;
+pub const DEFAULT_RESUME_CONCURRENCY: usize = 8;
+pub const DEFAULT_PLAN_UNIT_COUNT: u8 = 4;
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::America::Los_Angeles;  // UCSD runs on Pacific time
 
-#[derive(Debug, Deserialize, Clone)]
+// Environment variables that override sensitive config.toml values
+pub const ENV_WEBREG_COOKIE: &str = "WEBREG_COOKIE";
+pub const ENV_GMAIL_APP_PASSWORD: &str = "GMAIL_APP_PASSWORD";
+pub const ENV_DISCORD_WEBHOOK_URL: &str = "DISCORD_WEBHOOK_URL";
+pub const ENV_PUSHOVER_API_TOKEN: &str = "PUSHOVER_API_TOKEN";
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct AppConfig {
     pub webreg: WebRegConfig,
     pub notifications: NotificationConfig,
@@ -13,47 +47,194 @@ pub struct AppConfig {
     pub monitoring: MonitoringConfig,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl AppConfig {
+    /// Replace sensitive values with environment variables when they are set.
+    /// The environment always wins over the file, so config.toml can hold placeholders.
+    pub fn apply_env_overrides(&mut self) {
+        if let Some(cookie) = non_empty_env(ENV_WEBREG_COOKIE) {
+            self.webreg.cookie = cookie;
+        }
+        if let Some(password) = non_empty_env(ENV_GMAIL_APP_PASSWORD) {
+            self.notifications.gmail_app_password = password;
+        }
+        if let Some(webhook) = non_empty_env(ENV_DISCORD_WEBHOOK_URL) {
+            self.notifications.discord_webhook_url = webhook;
+        }
+        if let Some(token) = non_empty_env(ENV_PUSHOVER_API_TOKEN) {
+            self.notifications.pushover_api_token = token;
+        }
+    }
+
+    /// Every term the courses are polled in, [webreg] term first, without repeats
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = vec![self.webreg.term.clone()];
+        for term in [self.courses.chem.term(), self.courses.bild.term.as_deref()].into_iter().flatten() {
+            if !terms.iter().any(|known| known == term) {
+                terms.push(term.to_string());
+            }
+        }
+        terms
+    }
+
+    /// Canonicalize course identifiers so "chem" / " 6a " match WebReg's "CHEM" / "6A"
+    pub fn normalize_courses(&mut self) {
+        match &mut self.courses.chem {
+            CourseDetails::New(details) => {
+                details.department = normalize_course_field(&details.department);
+                details.course_code = normalize_course_field(&details.course_code);
+            }
+            CourseDetails::Legacy(details) => {
+                details.department = normalize_course_field(&details.department);
+                details.course_code = normalize_course_field(&details.course_code);
+            }
+        }
+        self.courses.bild.department = normalize_course_field(&self.courses.bild.department);
+        self.courses.bild.course_code = normalize_course_field(&self.courses.bild.course_code);
+    }
+
+    /// Drop sections listed more than once so each is monitored once.
+    /// Returns the removed repeats, e.g. "CHEM 6A A01".
+    pub fn dedupe_sections(&mut self) -> Vec<String> {
+        let chem = &mut self.courses.chem;
+        let chem_label = format!("{} {}", chem.department(), chem.course_code());
+        let chem_repeats = match chem {
+            CourseDetails::New(details) => dedupe_section_groups(&mut details.sections),
+            CourseDetails::Legacy(details) => dedupe_discussions(&mut details.discussion_sections),
+        };
+
+        let bild = &mut self.courses.bild;
+        let bild_label = format!("{} {}", bild.department, bild.course_code);
+        let bild_repeats = dedupe_discussions(&mut bild.discussion_sections);
+
+        chem_repeats.into_iter().map(|section| format!("{} {}", chem_label, section))
+            .chain(bild_repeats.into_iter().map(|section| format!("{} {}", bild_label, section)))
+            .collect()
+    }
+}
+
+/// config.toml's text with `cookie` as the [webreg] cookie and the used backup_cookie line
+/// dropped, or None when the file has no [webreg] cookie line to replace
+pub fn with_promoted_cookie(content: &str, cookie: &str) -> Option<String> {
+    let mut in_webreg = false;
+    let mut replaced = false;
+    let mut lines = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            in_webreg = trimmed.starts_with("[webreg]");
+        } else if in_webreg && trimmed.contains('=') {
+            match trimmed.split('=').next().unwrap_or_default().trim() {
+                "backup_cookie" => continue,
+                "cookie" => {
+                    lines.push(format!("cookie = {}", toml::Value::String(cookie.to_string())));
+                    replaced = true;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        lines.push(line.to_string());
+    }
+
+    replaced.then(|| lines.join("\n") + "\n")
+}
+
+pub fn non_empty_env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.trim().is_empty())
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct WebRegConfig {
     pub term: String,
     pub polling_interval: u64,
     pub cookie: String,
+    #[serde(default)]
+    pub backup_cookie: Option<String>,  // Used once the primary cookie stops working
+    #[serde(default)]
+    pub user_agent: Option<String>,  // Overrides webweg's default User-Agent
+    #[serde(default)]
+    pub headers: HashMap<String, String>,  // Extra headers sent with every WebReg request
+    #[serde(default)]
+    pub proxy: Option<String>,  // http://, https:// or socks5:// proxy for WebReg and webhook traffic
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,  // A WebReg request running longer fails instead of stalling the cycle (0 = no limit)
+}
+
+impl WebRegConfig {
+    /// Whether two configs would produce the same WebReg session
+    pub fn same_session(&self, other: &WebRegConfig) -> bool {
+        self.cookie == other.cookie
+            && self.term == other.term
+            && self.user_agent == other.user_agent
+            && self.headers == other.headers
+            && self.proxy == other.proxy
+            && self.request_timeout_secs == other.request_timeout_secs
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
 pub struct NotificationConfig {
     pub gmail_address: String,
     pub gmail_app_password: String,
     pub email_recipients: Vec<String>,
     pub discord_webhook_url: String,
+    #[serde(default)]
+    pub success_webhook_url: String,  // Receives a JSON POST on each successful enrollment
+    #[serde(default)]
+    pub alert_webhook_url: String,  // Discord webhook for connectivity alerts (cookie expiry)
+    #[serde(default)]
+    pub alert_email_recipients: Vec<String>,  // Email recipients for connectivity alerts
+    #[serde(default)]
+    pub notify_on_close: bool,  // Also notify when a reported opening closes again
+    #[serde(default = "default_notify_on_recovery")]
+    pub notify_on_recovery: bool,  // Alert when WebReg works again after the cookie expired
+    #[serde(default)]
+    pub discord_format: DiscordFormat,  // Plain text, or color-coded embeds with course fields
+    #[serde(default)]
+    pub pushover_user_key: String,  // Pushover delivery needs both the user key and an app token
+    #[serde(default)]
+    pub pushover_api_token: String,
+    // Minimum seconds between sends per channel (0 = unlimited); notifications inside
+    // the window are held until it closes, except high priority ones
+    #[serde(default)]
+    pub email_min_interval_secs: u64,
+    #[serde(default)]
+    pub discord_min_interval_secs: u64,
+    #[serde(default)]
+    pub pushover_min_interval_secs: u64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct CourseConfig {
     pub chem: CourseDetails,
     pub bild: LegacyCourseDetails,  // Use old format for BILD
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]  // This allows serde to try both formats
 pub enum CourseDetails {
     New(NewCourseDetails),
     Legacy(LegacyCourseDetails),
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct NewCourseDetails {
     pub department: String,
     pub course_code: String,
     pub sections: Vec<SectionGroup>,
+    #[serde(default)]
+    pub term: Option<String>,  // Overrides [webreg] term, e.g. for a summer session course
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct LegacyCourseDetails {
     pub department: String,
     pub course_code: String,
     pub lecture_section: String,
     pub discussion_sections: Vec<String>,
+    #[serde(default)]
+    pub term: Option<String>,  // Overrides [webreg] term, e.g. for a summer session course
 }
 
 impl CourseDetails {
@@ -70,32 +251,616 @@ impl CourseDetails {
             CourseDetails::Legacy(details) => &details.course_code,
         }
     }
+
+    pub fn term(&self) -> Option<&str> {
+        match self {
+            CourseDetails::New(details) => details.term.as_deref(),
+            CourseDetails::Legacy(details) => details.term.as_deref(),
+        }
+    }
+}
+
+/// Uppercase, trim and collapse inner whitespace, the form WebReg's course lookup expects
+pub fn normalize_course_field(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase()
+}
+
+/// A lecture and its discussions, whether from config.toml or a job request
+pub trait SectionList {
+    fn lecture_key(&self) -> &str;
+    fn discussions_mut(&mut self) -> &mut Vec<String>;
+}
+
+/// Remove discussions already listed in the course, and fold a group that repeats an
+/// earlier group's lecture into that group. Returns the removed sections.
+pub fn dedupe_section_groups<G: SectionList>(groups: &mut Vec<G>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut removed = Vec::new();
+    let mut kept: Vec<G> = Vec::new();
+
+    for mut group in std::mem::take(groups) {
+        let mut discussions = std::mem::take(group.discussions_mut());
+        discussions.retain(|d| seen.insert(d.clone()) || { removed.push(d.clone()); false });
+
+        match kept.iter_mut().find(|earlier| earlier.lecture_key() == group.lecture_key()) {
+            Some(earlier) => {
+                removed.push(group.lecture_key().to_string());
+                earlier.discussions_mut().extend(discussions);
+            }
+            None => {
+                *group.discussions_mut() = discussions;
+                kept.push(group);
+            }
+        }
+    }
+
+    *groups = kept;
+    removed
+}
+
+fn dedupe_discussions(discussions: &mut Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut removed = Vec::new();
+    discussions.retain(|d| seen.insert(d.clone()) || { removed.push(d.clone()); false });
+    removed
 }
 
 pub fn to_section_groups(course: &LegacyCourseDetails) -> Vec<SectionGroup> {
     vec![SectionGroup {
         lecture: course.lecture_section.clone(),
         discussions: course.discussion_sections.clone(),
+        section_id: None,
+        enroll_target: None,
+        discussion_required: false,
     }]
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct SectionGroup {
     pub lecture: String,
     pub discussions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section_id: Option<String>,  // WebReg's numeric id for the lecture; matched instead of the code when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enroll_target: Option<EnrollTarget>,  // Unset follows the monitoring mode
+    #[serde(default)]
+    pub discussion_required: bool,  // Never enroll the lecture alone, only through a discussion
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl SectionGroup {
+    /// What to match the lecture on: its section id when given, otherwise its code
+    pub fn lecture_key(&self) -> &str {
+        self.section_id.as_deref().unwrap_or(&self.lecture)
+    }
+
+    pub fn enroll_target(&self, mode: MonitoringMode) -> EnrollTarget {
+        self.enroll_target.unwrap_or(mode.default_target())
+    }
+
+    /// Whether an open lecture may be enrolled without one of its discussions
+    pub fn enrolls_lecture_alone(&self) -> bool {
+        enrolls_lecture_alone(self.discussion_required, &self.discussions)
+    }
+}
+
+/// Whether a group's open lecture may be enrolled on its own. A required discussion
+/// keeps it from being, unless the group lists none.
+pub fn enrolls_lecture_alone(discussion_required: bool, discussions: &[String]) -> bool {
+    !discussion_required || discussions.is_empty()
+}
+
+impl SectionList for SectionGroup {
+    fn lecture_key(&self) -> &str {
+        SectionGroup::lecture_key(self)
+    }
+
+    fn discussions_mut(&mut self) -> &mut Vec<String> {
+        &mut self.discussions
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct MonitoringConfig {
     pub log_file: String,
-    pub stats_file: String,
+    pub stats_file: String,  // Relative to data_dir when one is set
+    #[serde(default)]
+    pub data_dir: Option<String>,  // Directory all generated files go under, created if missing (default: working directory)
     pub cookie_refresh_interval: u64,
     pub max_retries: u32,
     pub retry_delay: u64,
     #[serde(default = "default_seat_threshold")]
     pub seat_threshold: i64,  // Threshold for available seats (0 = any availability, 3 = fewer than 3 seats)
+    #[serde(default = "default_verify_enrollment")]
+    pub verify_enrollment: bool,  // Confirm a reported enrollment against the schedule before counting it
+    #[serde(default)]
+    pub recheck_freshness_ms: u64,  // Skip the confirming fetch when the first is younger than this (0 = always recheck)
+    #[serde(default)]
+    pub two_phase_enroll: bool,  // Experimental: plan the section before enrolling
+    #[serde(default = "default_plan_unit_count")]
+    pub plan_unit_count: u8,  // Units sent with the plan request; WebReg recalculates them on enrollment
+    #[serde(default)]
+    pub mode: Option<MonitoringMode>,  // Derived from seat_threshold when omitted
+    #[serde(default)]
+    pub threshold_direction: ThresholdDirection,  // at_most = few seats left, at_least = comfortably many
+    #[serde(default = "default_failure_notification_cap")]
+    pub failure_notification_cap: u64,  // Failure notifications per section per day (0 = unlimited)
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,  // IANA name used for timestamps and the daily failure reset
+    #[serde(default = "default_consecutive_polls")]
+    pub consecutive_polls: u32,  // Polls in a row a section must look open before enrolling (1 = act at once)
+    #[serde(default = "default_grace_retries")]
+    pub grace_retries: u32,  // Quick re-adds right after a failed enroll, before the normal sleep (0 = none)
+    #[serde(default = "default_grace_retry_delay_ms")]
+    pub grace_retry_delay_ms: u64,  // Milliseconds between those re-adds
+    #[serde(default = "default_cycle_retry_budget")]
+    pub cycle_retry_budget: u32,  // Retries shared by every section in one poll cycle (0 = unlimited)
+    #[serde(default)]
+    pub session_check_cycles: u32,  // Also check the cookie every this many poll cycles (0 = only on the refresh timer)
+    #[serde(default = "default_section_timeout_secs")]
+    pub section_timeout_secs: u64,  // One section's check, retries included, is abandoned after this; enrolls always finish (0 = no limit)
+}
+
+impl MonitoringConfig {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+        }
+    }
+
+    pub fn grace_retry(&self) -> GraceRetry {
+        GraceRetry {
+            retries: self.grace_retries,
+            delay_ms: self.grace_retry_delay_ms,
+        }
+    }
+
+    /// The configured mode, or the one implied by seat_threshold for older configs
+    pub fn effective_mode(&self) -> MonitoringMode {
+        self.mode.unwrap_or(if self.seat_threshold == 0 {
+            MonitoringMode::Available
+        } else {
+            MonitoringMode::LowSeats
+        })
+    }
+}
+
+/// When an opening is worth acting on. Deserializes through `FromStr`, so unknown
+/// modes are rejected with its error.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", try_from = "String")]
+pub enum MonitoringMode {
+    /// Any open seat
+    #[default]
+    Available,
+    /// Open seats on the threshold_direction side of seat_threshold
+    LowSeats,
+    /// Only when the section is full and the waitlist is the way in
+    WaitlistOnly,
+}
+
+impl std::str::FromStr for MonitoringMode {
+    type Err = String;
+
+    /// Accepts the current names and the older Include/Exclude, ignoring case
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "available" | "include" => Ok(MonitoringMode::Available),
+            "low_seats" | "lowseats" | "exclude" => Ok(MonitoringMode::LowSeats),
+            "waitlist_only" | "waitlistonly" => Ok(MonitoringMode::WaitlistOnly),
+            _ => Err(format!(
+                "Unknown monitoring mode '{}' (expected available, low_seats or waitlist_only)",
+                value
+            )),
+        }
+    }
+}
+
+impl MonitoringMode {
+    /// A note when `direction` has no effect: only low-seats mode compares against the
+    /// threshold, so at_least is ignored in the other modes
+    pub fn ignored_direction(self, direction: ThresholdDirection) -> Option<String> {
+        (self != MonitoringMode::LowSeats && direction == ThresholdDirection::AtLeast).then(|| format!(
+            "threshold_direction = at_least only applies in low_seats mode and is ignored in {:?} mode",
+            self
+        ))
+    }
+}
+
+impl TryFrom<String> for MonitoringMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::fmt::Display for MonitoringMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MonitoringMode::Available => "available",
+            MonitoringMode::LowSeats => "low_seats",
+            MonitoringMode::WaitlistOnly => "waitlist_only",
+        })
+    }
+}
+
+impl MonitoringMode {
+    /// The target for sections that don't set their own
+    pub fn default_target(self) -> EnrollTarget {
+        match self {
+            MonitoringMode::WaitlistOnly => EnrollTarget::WaitlistOnly,
+            _ => EnrollTarget::EnrollOnly,
+        }
+    }
+
+    /// The mode that decides when seats count as open; waitlisting is up to the target
+    pub fn seat_mode(self) -> MonitoringMode {
+        match self {
+            MonitoringMode::WaitlistOnly => MonitoringMode::Available,
+            mode => mode,
+        }
+    }
+}
+
+/// How a section may be joined once it qualifies
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrollTarget {
+    /// Enroll when seats open; a full section is left alone
+    #[default]
+    EnrollOnly,
+    /// Only ever join the waitlist, never taking an open seat
+    WaitlistOnly,
+    /// Enroll if possible, otherwise join the waitlist
+    EnrollOrWaitlist,
+}
+
+impl std::str::FromStr for EnrollTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "enroll_only" | "enroll" => Ok(EnrollTarget::EnrollOnly),
+            "waitlist_only" | "waitlist" => Ok(EnrollTarget::WaitlistOnly),
+            "enroll_or_waitlist" => Ok(EnrollTarget::EnrollOrWaitlist),
+            _ => Err(format!("Unknown enroll target '{}'", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for EnrollTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EnrollTarget::EnrollOnly => "enroll_only",
+            EnrollTarget::WaitlistOnly => "waitlist_only",
+            EnrollTarget::EnrollOrWaitlist => "enroll_or_waitlist",
+        })
+    }
+}
+
+impl EnrollTarget {
+    /// The target for a section showing `available_seats`: a full section goes straight to its waitlist
+    pub fn for_seats(self, available_seats: i64) -> Self {
+        match self {
+            EnrollTarget::EnrollOrWaitlist if available_seats <= 0 => EnrollTarget::WaitlistOnly,
+            target => target,
+        }
+    }
+}
+
+/// A course's place in a job's plan: once a primary course is enrolled, the backups stop
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseRole {
+    Primary,
+    Backup,
+}
+
+impl std::str::FromStr for CourseRole {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "primary" => Ok(CourseRole::Primary),
+            "backup" => Ok(CourseRole::Backup),
+            _ => Err(format!("Unknown course role '{}'", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for CourseRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CourseRole::Primary => "primary",
+            CourseRole::Backup => "backup",
+        })
+    }
+}
+
+/// How Discord messages are posted
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscordFormat {
+    /// The notification text as the message content
+    #[default]
+    Plain,
+    /// A rich embed colored by what happened, with course, section and seat fields
+    Embed,
+}
+
+impl std::str::FromStr for DiscordFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "plain" => Ok(DiscordFormat::Plain),
+            "embed" => Ok(DiscordFormat::Embed),
+            _ => Err(format!("Unknown Discord format '{}'", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for DiscordFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DiscordFormat::Plain => "plain",
+            DiscordFormat::Embed => "embed",
+        })
+    }
+}
+
+/// Which side of seat_threshold counts as a match
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdDirection {
+    /// available_seats <= seat_threshold
+    #[default]
+    AtMost,
+    /// available_seats >= seat_threshold
+    AtLeast,
+}
+
+impl std::str::FromStr for ThresholdDirection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "at_most" | "atmost" => Ok(ThresholdDirection::AtMost),
+            "at_least" | "atleast" => Ok(ThresholdDirection::AtLeast),
+            _ => Err(format!("Unknown threshold direction '{}'", value)),
+        }
+    }
 }
 
 fn default_seat_threshold() -> i64 {
     0  // Default to aggressive mode (any seat availability)
 }
+
+fn default_timezone() -> Tz {
+    DEFAULT_TIMEZONE
+}
+
+fn default_failure_notification_cap() -> u64 {
+    DEFAULT_FAILURE_NOTIFICATION_CAP
+}
+
+fn default_verify_enrollment() -> bool {
+    true
+}
+
+fn default_notify_on_recovery() -> bool {
+    true
+}
+
+fn default_consecutive_polls() -> u32 {
+    1
+}
+
+fn default_grace_retries() -> u32 {
+    DEFAULT_GRACE_RETRIES
+}
+
+fn default_grace_retry_delay_ms() -> u64 {
+    DEFAULT_GRACE_RETRY_DELAY_MS
+}
+
+fn default_section_timeout_secs() -> u64 {
+    DEFAULT_SECTION_TIMEOUT_SECS
+}
+
+fn default_request_timeout_secs() -> u64 {
+    DEFAULT_WEBREG_TIMEOUT_SECS
+}
+
+fn default_cycle_retry_budget() -> u32 {
+    DEFAULT_CYCLE_RETRY_BUDGET
+}
+
+fn default_plan_unit_count() -> u8 {
+    DEFAULT_PLAN_UNIT_COUNT
+}
+
+/// When a section is far enough past capacity to be polled less often. Demand is
+/// (enrolled + waitlisted) / seats; anything at or under 1.0 is polled every cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BackoffThresholds {
+    pub slowest_demand: f64,  // Demand at which the longest wait applies (at or below 1.0 disables backoff)
+    pub max_skip: u32,        // Cycles skipped between polls at that demand
+}
+
+impl BackoffThresholds {
+    /// Cycles to sit out, scaled linearly from 0 at capacity up to `max_skip`
+    pub fn skip_for(&self, demand: f64) -> u32 {
+        if self.slowest_demand <= 1.0 || self.max_skip == 0 || demand <= 1.0 {
+            return 0;
+        }
+        let scale = ((demand - 1.0) / (self.slowest_demand - 1.0)).min(1.0);
+        (scale * self.max_skip as f64).round() as u32
+    }
+}
+
+/// Operator settings for the multi-user server, read from environment variables
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub max_concurrent_requests: usize,  // In-flight WebReg requests allowed across all jobs
+    pub max_jobs_per_user: usize,        // 0 means unlimited
+    pub max_courses_per_job: usize,      // 0 means unlimited
+    pub max_sections_per_course: usize,  // Lectures plus discussions; 0 means unlimited
+    pub db_max_connections: u32,
+    pub db_min_connections: u32,
+    pub db_acquire_timeout: u64,         // Seconds to wait for a pooled connection
+    pub stats_flush_interval: u64,       // Minimum seconds between job stats writes (0 = every cycle)
+    pub recheck_freshness_ms: u64,       // Skip the confirming fetch when the first is younger than this
+    pub two_phase_enroll: bool,          // Experimental: plan sections before enrolling
+    pub plan_unit_count: u8,             // Units sent with those plan requests
+    pub timezone: Tz,                    // IANA name used for timestamps and the daily failure reset
+    pub daily_request_quota: u64,        // WebReg requests per user per day (0 = unlimited)
+    pub min_polling_interval: u64,       // Seconds; lower job intervals are raised to this
+    pub consecutive_polls: u32,          // Polls in a row a section must look open before enrolling
+    pub grace_retry: GraceRetry,         // Quick re-adds right after a failed enroll
+    pub cycle_retry_budget: u32,         // Retries shared by every section in one job's poll cycle (0 = unlimited)
+    pub email_min_interval_secs: u64,    // Per-channel notification spacing for every job (0 = unlimited)
+    pub discord_min_interval_secs: u64,
+    pub pushover_min_interval_secs: u64,
+    pub data_dir: Option<String>,        // Directory the logs are written under (default: working directory)
+    pub watchdog_stall_intervals: u32,   // Restart a job's loop after this many intervals without a cycle (0 = never)
+    pub cookie_refresh_jitter: bool,     // Start each job's cookie checks at a random point in the interval
+    pub proxy_url: Option<String>,       // Proxy for WebReg requests and notification webhooks
+    pub session_check_cycles: u32,       // Also check each job's cookie every this many poll cycles (0 = only on the refresh timer)
+    pub poll_backoff: BackoffThresholds, // Poll sections far past capacity less often (disabled by default)
+    pub webreg_timeout: Duration,        // A WebReg request running longer fails instead of stalling the cycle (zero = no limit)
+    pub section_timeout: Duration,       // One section's check, retries included, is abandoned after this (zero = no limit)
+    pub notification_max_attempts: u32,  // Deliveries tried per outbox notification before it stays failed
+    pub notification_retry_delay: Duration, // Wait before retrying a failed notification, multiplied by its attempts so far
+    pub notification_retention_days: u32, // Delivered outbox notifications are deleted after this many days (0 = kept)
+    pub resume_concurrency: usize,       // Active jobs set up at once when the server starts
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        Self {
+            max_concurrent_requests: env_or("MAX_CONCURRENT_WEBREG_REQUESTS", DEFAULT_MAX_CONCURRENT_REQUESTS).max(1),
+            max_jobs_per_user: env_or("MAX_JOBS_PER_USER", 0),
+            max_courses_per_job: env_or("MAX_COURSES_PER_JOB", DEFAULT_MAX_COURSES_PER_JOB),
+            max_sections_per_course: env_or("MAX_SECTIONS_PER_COURSE", DEFAULT_MAX_SECTIONS_PER_COURSE),
+            db_max_connections: env_or("DB_MAX_CONNECTIONS", DEFAULT_DB_MAX_CONNECTIONS).max(1),
+            db_min_connections: env_or("DB_MIN_CONNECTIONS", DEFAULT_DB_MIN_CONNECTIONS),
+            db_acquire_timeout: env_or("DB_ACQUIRE_TIMEOUT", DEFAULT_DB_ACQUIRE_TIMEOUT).max(1),
+            stats_flush_interval: env_or("STATS_FLUSH_INTERVAL", DEFAULT_STATS_FLUSH_INTERVAL),
+            recheck_freshness_ms: env_or("RECHECK_FRESHNESS_MS", 0),
+            two_phase_enroll: env_or("TWO_PHASE_ENROLL", false),
+            plan_unit_count: env_or("PLAN_UNIT_COUNT", DEFAULT_PLAN_UNIT_COUNT),
+            timezone: env_or("TIMEZONE", DEFAULT_TIMEZONE),
+            daily_request_quota: env_or("DAILY_REQUEST_QUOTA", 0),
+            min_polling_interval: env_or("MIN_POLLING_INTERVAL", DEFAULT_MIN_POLLING_INTERVAL).max(1),
+            consecutive_polls: env_or("CONSECUTIVE_POLLS", 1).max(1),
+            grace_retry: GraceRetry {
+                retries: env_or("GRACE_RETRIES", DEFAULT_GRACE_RETRIES),
+                delay_ms: env_or("GRACE_RETRY_DELAY_MS", DEFAULT_GRACE_RETRY_DELAY_MS),
+            },
+            cycle_retry_budget: env_or("CYCLE_RETRY_BUDGET", DEFAULT_CYCLE_RETRY_BUDGET),
+            email_min_interval_secs: env_or("EMAIL_MIN_INTERVAL_SECS", 0),
+            discord_min_interval_secs: env_or("DISCORD_MIN_INTERVAL_SECS", 0),
+            pushover_min_interval_secs: env_or("PUSHOVER_MIN_INTERVAL_SECS", 0),
+            data_dir: std::env::var("DATA_DIR").ok().filter(|dir| !dir.is_empty()),
+            watchdog_stall_intervals: env_or("WATCHDOG_STALL_INTERVALS", DEFAULT_WATCHDOG_STALL_INTERVALS),
+            cookie_refresh_jitter: env_or("COOKIE_REFRESH_JITTER", true),
+            proxy_url: std::env::var("WEBREG_PROXY").ok().filter(|url| !url.is_empty()),
+            session_check_cycles: env_or("SESSION_CHECK_CYCLES", 0),
+            poll_backoff: BackoffThresholds {
+                slowest_demand: env_or("POLL_BACKOFF_DEMAND", 0.0),
+                max_skip: env_or("POLL_BACKOFF_MAX_SKIP", DEFAULT_POLL_BACKOFF_MAX_SKIP),
+            },
+            webreg_timeout: Duration::from_secs(env_or("WEBREG_TIMEOUT_SECS", DEFAULT_WEBREG_TIMEOUT_SECS)),
+            section_timeout: Duration::from_secs(env_or("SECTION_TIMEOUT_SECS", DEFAULT_SECTION_TIMEOUT_SECS)),
+            notification_max_attempts: env_or("NOTIFICATION_MAX_ATTEMPTS", DEFAULT_NOTIFICATION_MAX_ATTEMPTS).max(1),
+            notification_retry_delay: Duration::from_secs(env_or("NOTIFICATION_RETRY_DELAY_SECS", DEFAULT_NOTIFICATION_RETRY_DELAY_SECS)),
+            notification_retention_days: env_or("NOTIFICATION_RETENTION_DAYS", DEFAULT_NOTIFICATION_RETENTION_DAYS),
+            resume_concurrency: env_or("RESUME_CONCURRENCY", DEFAULT_RESUME_CONCURRENCY).max(1),
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_terms(chem_term: &str, bild_term: &str) -> AppConfig {
+        toml::from_str(&format!(r#"
+            [webreg]
+            term = "FA25"
+            polling_interval = 30
+            cookie = "cookie"
+
+            [notifications]
+            gmail_address = ""
+            gmail_app_password = ""
+            email_recipients = []
+            discord_webhook_url = ""
+
+            [courses.chem]
+            department = "CHEM"
+            course_code = "6B"
+            sections = [{{ lecture = "A00", discussions = [] }}]
+            {}
+
+            [courses.bild]
+            department = "BILD"
+            course_code = "1"
+            lecture_section = "A00"
+            discussion_sections = []
+            {}
+
+            [monitoring]
+            log_file = "webreg_monitor.log"
+            stats_file = "enrollment_stats.json"
+            cookie_refresh_interval = 480
+            max_retries = 3
+            retry_delay = 1000
+        "#, chem_term, bild_term)).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_course_field() {
+        assert_eq!(normalize_course_field(" chem "), "CHEM");
+        assert_eq!(normalize_course_field("6a"), "6A");
+        assert_eq!(normalize_course_field("  cse \t 100  "), "CSE 100");
+        assert_eq!(normalize_course_field(""), "");
+    }
+
+    #[test]
+    fn test_full_section_goes_straight_to_the_waitlist() {
+        assert_eq!(EnrollTarget::EnrollOrWaitlist.for_seats(0), EnrollTarget::WaitlistOnly);
+        assert_eq!(EnrollTarget::EnrollOrWaitlist.for_seats(-1), EnrollTarget::WaitlistOnly);
+        assert_eq!(EnrollTarget::EnrollOrWaitlist.for_seats(2), EnrollTarget::EnrollOrWaitlist);
+        assert_eq!(EnrollTarget::EnrollOnly.for_seats(0), EnrollTarget::EnrollOnly);
+        assert_eq!(EnrollTarget::WaitlistOnly.for_seats(3), EnrollTarget::WaitlistOnly);
+    }
+
+    #[test]
+    fn test_promoted_cookie_replaces_only_the_webreg_cookie() {
+        let content = "[webreg]\nterm = \"FA25\"\ncookie = \"dead\"\nbackup_cookie = \"spare\"\n\n[other]\ncookie = \"untouched\"\n";
+        assert_eq!(
+            with_promoted_cookie(content, "spare").unwrap(),
+            "[webreg]\nterm = \"FA25\"\ncookie = \"spare\"\n\n[other]\ncookie = \"untouched\"\n",
+        );
+        assert_eq!(with_promoted_cookie("[webreg]\nterm = \"FA25\"\n", "spare"), None);
+    }
+
+    #[test]
+    fn test_terms_lists_each_term_once_webreg_first() {
+        assert_eq!(config_with_terms("", "").terms(), vec!["FA25"]);
+        assert_eq!(config_with_terms(r#"term = "S125""#, "").terms(), vec!["FA25", "S125"]);
+        // A course in the [webreg] term and two courses sharing a term add nothing new
+        assert_eq!(config_with_terms(r#"term = "FA25""#, r#"term = "S225""#).terms(), vec!["FA25", "S225"]);
+        assert_eq!(config_with_terms(r#"term = "S225""#, r#"term = "S225""#).terms(), vec!["FA25", "S225"]);
+    }
+}
@@ -1,26 +1,85 @@
-use sqlx::postgres::PgPoolOptions;
-use sqlx::{Pool, Postgres};
+use chrono::NaiveDate;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnection, PgPoolOptions};
+use sqlx::{Pool, Postgres, QueryBuilder};
+use std::collections::HashSet;
 use std::error::Error as StdError;
+use std::future::Future;
+use std::time::Duration;
 use uuid::Uuid;
+use crate::config::ServerConfig;
+use crate::enroll::EnrollAttempt;
 use crate::models::*;
+use crate::utils::{get_retry_strategy, RetryPolicy};
 
 pub type DbPool = Pool<Postgres>;
 
+/// How long an idempotency key replays its original job
+const IDEMPOTENCY_WINDOW_HOURS: i32 = 24;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Retry a write with the usual backoff so a brief database outage doesn't drop it
+pub async fn retry_write<T, F, Fut>(write: F) -> Result<T, Box<dyn StdError + Send + Sync>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn StdError + Send + Sync>>>,
+{
+    let policy = RetryPolicy::default();
+    tokio_retry::Retry::spawn(get_retry_strategy(policy.max_retries, policy.retry_delay), write).await
+}
+
 /// Initialize database connection pool
-pub async fn init_pool(database_url: &str) -> Result<DbPool, Box<dyn StdError + Send + Sync>> {
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
-        .await?;
+pub async fn init_pool(
+    database_url: &str,
+    settings: &ServerConfig,
+) -> Result<DbPool, Box<dyn StdError + Send + Sync>> {
+    let pool = connect_pool(database_url, settings).await?;
 
     // Run migrations
-    sqlx::migrate!("./migrations")
-        .run(&pool)
+    MIGRATOR.run(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Connect without running migrations
+pub async fn connect_pool(
+    database_url: &str,
+    settings: &ServerConfig,
+) -> Result<DbPool, Box<dyn StdError + Send + Sync>> {
+    let pool = PgPoolOptions::new()
+        .max_connections(settings.db_max_connections)
+        .min_connections(settings.db_min_connections.min(settings.db_max_connections))
+        .acquire_timeout(Duration::from_secs(settings.db_acquire_timeout))
+        .connect(database_url)
         .await?;
 
     Ok(pool)
 }
 
+/// Versions of bundled migrations that have not been applied to the database
+pub async fn pending_migrations(pool: &DbPool) -> Result<Vec<i64>, Box<dyn StdError + Send + Sync>> {
+    let table_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = '_sqlx_migrations')"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let applied: Vec<i64> = if table_exists {
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
 // ============================================================================
 // User queries
 // ============================================================================
@@ -76,18 +135,23 @@ pub async fn get_user_by_id(
 
 /// Create a new monitoring job
 pub async fn create_job(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     user_id: Uuid,
     request: &CreateJobRequest,
     cookie_encrypted: &str,
     encryption_nonce: &str,
+    backup_cookie: Option<(&str, &str)>,
 ) -> Result<Job, Box<dyn StdError + Send + Sync>> {
     let job = sqlx::query_as::<_, Job>(
         r#"
         INSERT INTO jobs (
             user_id, term, polling_interval, cookie_encrypted, encryption_nonce,
-            seat_threshold, monitoring_mode
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            seat_threshold, monitoring_mode, enroll, verify_enrollment,
+            stop_on_first_success, max_run_duration_hours, cookie_refresh_interval,
+            threshold_direction, backup_cookie_encrypted, backup_encryption_nonce,
+            max_concurrent_enrolls, stop_cycle_on_success, watch_enrolled, poll_immediately,
+            enrollment_opens_at, force_enroll, ignore_threshold
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
         RETURNING *
         "#
     )
@@ -97,25 +161,125 @@ pub async fn create_job(
     .bind(cookie_encrypted)
     .bind(encryption_nonce)
     .bind(request.seat_threshold)
-    .bind(&request.monitoring_mode)
-    .fetch_one(pool)
+    .bind(request.monitoring_mode.to_string())
+    .bind(request.enroll)
+    .bind(request.verify_enrollment)
+    .bind(request.stop_on_first_success)
+    .bind(request.max_run_duration_hours)
+    .bind(request.cookie_refresh_interval)
+    .bind(&request.threshold_direction)
+    .bind(backup_cookie.map(|(encrypted, _)| encrypted))
+    .bind(backup_cookie.map(|(_, nonce)| nonce))
+    .bind(request.max_concurrent_enrolls)
+    .bind(request.stop_cycle_on_success)
+    .bind(request.watch_enrolled)
+    .bind(request.poll_immediately)
+    .bind(request.enrollment_opens_at)
+    .bind(request.force_enroll)
+    .bind(request.ignore_threshold)
+    .fetch_one(conn)
     .await?;
 
     Ok(job)
 }
 
-/// Get all jobs for a user
-pub async fn get_user_jobs(
+/// Get the job created for an idempotency key within the replay window
+pub async fn get_idempotent_job(
     pool: &DbPool,
     user_id: Uuid,
-) -> Result<Vec<Job>, Box<dyn StdError + Send + Sync>> {
-    let jobs = sqlx::query_as::<_, Job>(
-        "SELECT * FROM jobs WHERE user_id = $1 ORDER BY created_at DESC"
+    idempotency_key: &str,
+) -> Result<Option<Uuid>, Box<dyn StdError + Send + Sync>> {
+    let job_id = sqlx::query_scalar(
+        r#"
+        SELECT job_id FROM idempotency_keys
+        WHERE user_id = $1 AND idempotency_key = $2
+          AND created_at > NOW() - make_interval(hours => $3)
+        "#
     )
     .bind(user_id)
-    .fetch_all(pool)
+    .bind(idempotency_key)
+    .bind(IDEMPOTENCY_WINDOW_HOURS)
+    .fetch_optional(pool)
     .await?;
 
+    Ok(job_id)
+}
+
+/// Record the job created for an idempotency key, replacing an expired entry.
+/// Returns false if the key is still held by another job.
+pub async fn claim_idempotency_key(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    idempotency_key: &str,
+    job_id: Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let claimed: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO idempotency_keys (user_id, idempotency_key, job_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, idempotency_key) DO UPDATE
+            SET job_id = EXCLUDED.job_id, created_at = NOW()
+            WHERE idempotency_keys.created_at <= NOW() - make_interval(hours => $4)
+        RETURNING job_id
+        "#
+    )
+    .bind(user_id)
+    .bind(idempotency_key)
+    .bind(job_id)
+    .bind(IDEMPOTENCY_WINDOW_HOURS)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(claimed.is_some())
+}
+
+/// Count the jobs a user already has, locking the user's row until the
+/// transaction ends so concurrent creates can't both pass the limit
+pub async fn count_user_jobs_for_update(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM jobs WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(count)
+}
+
+/// Get a user's jobs, filtered and ordered by `query`
+pub async fn get_user_jobs(
+    pool: &DbPool,
+    user_id: Uuid,
+    query: &JobListQuery,
+) -> Result<Vec<Job>, Box<dyn StdError + Send + Sync>> {
+    let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM jobs WHERE user_id = ");
+    builder.push_bind(user_id);
+
+    if let Some(is_active) = query.is_active {
+        builder.push(" AND is_active = ").push_bind(is_active);
+    }
+    if let Some(is_connected) = query.is_connected {
+        builder.push(" AND is_connected = ").push_bind(is_connected);
+    }
+    if let Some(term) = &query.term {
+        builder.push(" AND UPPER(term) = UPPER(").push_bind(term.clone()).push(")");
+    }
+
+    // Column and direction come from enums, never from the raw query string
+    builder.push(format!(" ORDER BY {} {} NULLS LAST", query.sort.column(), query.order.keyword()));
+
+    let jobs = builder.build_query_as::<Job>()
+        .fetch_all(pool)
+        .await?;
+
     Ok(jobs)
 }
 
@@ -155,6 +319,91 @@ pub async fn update_job_status(
     Ok(())
 }
 
+/// Turn a job's threshold override on or off, returning whether the user has such a job
+pub async fn set_ignore_threshold(
+    pool: &DbPool,
+    job_id: Uuid,
+    user_id: Uuid,
+    ignore_threshold: bool,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let result = sqlx::query(
+        "UPDATE jobs SET ignore_threshold = $1, updated_at = NOW() WHERE id = $2 AND user_id = $3"
+    )
+    .bind(ignore_threshold)
+    .bind(job_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Replace the sections a job is recorded as holding
+pub async fn set_held_sections(
+    pool: &DbPool,
+    job_id: Uuid,
+    held_sections: &[HeldSection],
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query("UPDATE jobs SET held_sections = $1, updated_at = NOW() WHERE id = $2")
+        .bind(serde_json::to_value(held_sections)?)
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Replace the one-discussion groups a job is recorded as having filled
+pub async fn set_filled_groups(
+    pool: &DbPool,
+    job_id: Uuid,
+    filled_groups: &HashSet<String>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query("UPDATE jobs SET filled_groups = $1, updated_at = NOW() WHERE id = $2")
+        .bind(serde_json::to_value(filled_groups)?)
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Record that a job enrolled in one of its primary courses, so its backups stay stopped
+pub async fn set_primary_enrolled(
+    pool: &DbPool,
+    job_id: Uuid,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query("UPDATE jobs SET primary_enrolled = TRUE, updated_at = NOW() WHERE id = $1")
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Make the backup cookie the job's primary once the primary has expired
+pub async fn promote_backup_cookie(
+    pool: &DbPool,
+    job_id: Uuid,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query(
+        r#"
+        UPDATE jobs SET
+            cookie_encrypted = backup_cookie_encrypted,
+            encryption_nonce = backup_encryption_nonce,
+            backup_cookie_encrypted = NULL,
+            backup_encryption_nonce = NULL,
+            updated_at = NOW()
+        WHERE id = $1 AND backup_cookie_encrypted IS NOT NULL
+        "#
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// Update job last check time
 pub async fn update_job_last_check(
     pool: &DbPool,
@@ -200,13 +449,60 @@ pub async fn get_all_active_jobs(
     Ok(jobs)
 }
 
+pub async fn count_active_jobs(
+    pool: &DbPool,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM jobs WHERE is_active = true"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Get every active job with its owner's email (admin view)
+pub async fn get_active_jobs_with_owner(
+    pool: &DbPool,
+) -> Result<Vec<AdminJobSummary>, Box<dyn StdError + Send + Sync>> {
+    let jobs = sqlx::query_as::<_, AdminJobSummary>(
+        r#"
+        SELECT j.id, j.user_id, u.email AS owner_email, j.term, j.polling_interval,
+               j.is_active, j.is_connected, j.last_check_time
+        FROM jobs j
+        JOIN users u ON u.id = j.user_id
+        WHERE j.is_active = true
+        ORDER BY j.created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(jobs)
+}
+
+/// Mark a job stopped regardless of owner. Returns false when the job doesn't exist.
+pub async fn deactivate_job(
+    pool: &DbPool,
+    job_id: Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let result = sqlx::query(
+        "UPDATE jobs SET is_active = false, is_connected = false, updated_at = NOW() WHERE id = $1"
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
 // ============================================================================
 // Course queries
 // ============================================================================
 
 /// Create courses for a job
 pub async fn create_courses(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     job_id: Uuid,
     courses: &[CourseRequest],
 ) -> Result<Vec<Course>, Box<dyn StdError + Send + Sync>> {
@@ -214,12 +510,14 @@ pub async fn create_courses(
 
     for course_req in courses {
         let course = sqlx::query_as::<_, Course>(
-            "INSERT INTO courses (job_id, department, course_code) VALUES ($1, $2, $3) RETURNING *"
+            "INSERT INTO courses (job_id, department, course_code, section_pattern, role) VALUES ($1, $2, $3, $4, $5) RETURNING *"
         )
         .bind(job_id)
         .bind(&course_req.department)
         .bind(&course_req.course_code)
-        .fetch_one(pool)
+        .bind(&course_req.section_pattern)
+        .bind(course_req.role.map(|role| role.to_string()))
+        .fetch_one(&mut *conn)
         .await?;
 
         created_courses.push(course);
@@ -249,7 +547,7 @@ pub async fn get_job_courses(
 
 /// Create sections for a course
 pub async fn create_sections(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     course_id: Uuid,
     sections: &[SectionRequest],
 ) -> Result<Vec<Section>, Box<dyn StdError + Send + Sync>> {
@@ -259,12 +557,17 @@ pub async fn create_sections(
         let discussions_json = serde_json::to_value(&section_req.discussions)?;
 
         let section = sqlx::query_as::<_, Section>(
-            "INSERT INTO sections (course_id, lecture, discussions) VALUES ($1, $2, $3) RETURNING *"
+            "INSERT INTO sections (course_id, lecture, discussions, section_id, enroll_target, atomic, one_discussion, discussion_required) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *"
         )
         .bind(course_id)
         .bind(&section_req.lecture)
         .bind(discussions_json)
-        .fetch_one(pool)
+        .bind(&section_req.section_id)
+        .bind(section_req.enroll_target.map(|target| target.to_string()))
+        .bind(section_req.atomic)
+        .bind(section_req.one_discussion)
+        .bind(section_req.discussion_required)
+        .fetch_one(&mut *conn)
         .await?;
 
         created_sections.push(section);
@@ -294,14 +597,14 @@ pub async fn get_course_sections(
 
 /// Initialize stats for a job
 pub async fn init_job_stats(
-    pool: &DbPool,
+    conn: &mut PgConnection,
     job_id: Uuid,
 ) -> Result<EnrollmentStatsDb, Box<dyn StdError + Send + Sync>> {
     let stats = sqlx::query_as::<_, EnrollmentStatsDb>(
         "INSERT INTO enrollment_stats (job_id) VALUES ($1) RETURNING *"
     )
     .bind(job_id)
-    .fetch_one(pool)
+    .fetch_one(conn)
     .await?;
 
     Ok(stats)
@@ -359,6 +662,241 @@ pub async fn update_job_stats(
     Ok(())
 }
 
+// ============================================================================
+// Enrollment attempt queries
+// ============================================================================
+
+/// Record the add_section calls made for one opening
+pub async fn record_enrollment_attempts(
+    pool: &DbPool,
+    job_id: Uuid,
+    attempts: &[EnrollAttempt],
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    if attempts.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "INSERT INTO enrollment_attempts (job_id, section, section_id, success, result, attempted_at) "
+    );
+    builder.push_values(attempts, |mut row, attempt| {
+        row.push_bind(job_id)
+            .push_bind(&attempt.section)
+            .push_bind(&attempt.section_id)
+            .push_bind(attempt.success)
+            .push_bind(&attempt.result)
+            .push_bind(attempt.attempted_at);
+    });
+    builder.build().execute(pool).await?;
+
+    Ok(())
+}
+
+/// Most recent attempts for a job, newest first
+pub async fn get_enrollment_attempts(
+    pool: &DbPool,
+    job_id: Uuid,
+    limit: i64,
+) -> Result<Vec<EnrollmentAttempt>, Box<dyn StdError + Send + Sync>> {
+    let attempts = sqlx::query_as::<_, EnrollmentAttempt>(
+        "SELECT * FROM enrollment_attempts WHERE job_id = $1 ORDER BY attempted_at DESC LIMIT $2"
+    )
+    .bind(job_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attempts)
+}
+
+// ============================================================================
+// Notification outbox queries
+// ============================================================================
+
+/// Store a notification as pending, before anything tries to deliver it
+pub async fn enqueue_notification(
+    pool: &DbPool,
+    user_id: Uuid,
+    job_id: Option<Uuid>,
+    channel: &str,
+    payload: &serde_json::Value,
+) -> Result<Uuid, Box<dyn StdError + Send + Sync>> {
+    let id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO notifications (user_id, job_id, channel, payload) VALUES ($1, $2, $3, $4) RETURNING id"
+    )
+    .bind(user_id)
+    .bind(job_id)
+    .bind(channel)
+    .bind(payload)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Oldest notifications still to deliver: pending ones, and failed ones whose retry delay
+/// (`retry_delay_secs` times their attempts so far) has passed and that have attempts left
+pub async fn get_undelivered_notifications(
+    pool: &DbPool,
+    max_attempts: i32,
+    retry_delay_secs: f64,
+    limit: i64,
+) -> Result<Vec<QueuedNotification>, Box<dyn StdError + Send + Sync>> {
+    let notifications = sqlx::query_as::<_, QueuedNotification>(
+        r#"
+        SELECT * FROM notifications
+        WHERE status = 'pending'
+           OR (status = 'failed'
+               AND attempts < $1
+               AND last_attempt_at < NOW() - make_interval(secs => $2 * attempts))
+        ORDER BY created_at
+        LIMIT $3
+        "#
+    )
+    .bind(max_attempts)
+    .bind(retry_delay_secs)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notifications)
+}
+
+pub async fn mark_notification_sent(
+    pool: &DbPool,
+    id: Uuid,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query(
+        "UPDATE notifications SET status = 'sent', attempts = attempts + 1, last_attempt_at = NOW(), sent_at = NOW(), last_error = NULL WHERE id = $1"
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Delete notifications delivered more than `retention_days` ago, returning how many went
+pub async fn delete_sent_notifications(
+    pool: &DbPool,
+    retention_days: i32,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let result = sqlx::query(
+        "DELETE FROM notifications WHERE status = 'sent' AND sent_at < NOW() - make_interval(days => $1)"
+    )
+    .bind(retention_days)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn mark_notification_failed(
+    pool: &DbPool,
+    id: Uuid,
+    error: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query(
+        "UPDATE notifications SET status = 'failed', attempts = attempts + 1, last_attempt_at = NOW(), last_error = $2 WHERE id = $1"
+    )
+    .bind(id)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Request quota queries
+// ============================================================================
+
+/// WebReg requests a user has made on `day`
+pub async fn get_request_count(
+    pool: &DbPool,
+    user_id: Uuid,
+    day: NaiveDate,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT request_count FROM user_request_counts WHERE user_id = $1 AND day = $2"
+    )
+    .bind(user_id)
+    .bind(day)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(count.unwrap_or(0))
+}
+
+/// Add to a user's request count for `day`
+pub async fn add_request_count(
+    pool: &DbPool,
+    user_id: Uuid,
+    day: NaiveDate,
+    requests: i64,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_request_counts (user_id, day, request_count)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, day)
+        DO UPDATE SET request_count = user_request_counts.request_count + EXCLUDED.request_count
+        "#
+    )
+    .bind(user_id)
+    .bind(day)
+    .bind(requests)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// ============================================================================
+// User preference queries
+// ============================================================================
+
+pub async fn get_user_preferences(
+    pool: &DbPool,
+    user_id: Uuid,
+) -> Result<Option<UserPreferences>, Box<dyn StdError + Send + Sync>> {
+    let preferences = sqlx::query_as::<_, UserPreferences>(
+        "SELECT * FROM user_preferences WHERE user_id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(preferences)
+}
+
+/// Replace a user's job defaults
+pub async fn set_user_preferences(
+    pool: &DbPool,
+    user_id: Uuid,
+    defaults: &JobDefaults,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_preferences (user_id, polling_interval, seat_threshold, monitoring_mode)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE SET
+            polling_interval = EXCLUDED.polling_interval,
+            seat_threshold = EXCLUDED.seat_threshold,
+            monitoring_mode = EXCLUDED.monitoring_mode,
+            updated_at = NOW()
+        "#
+    )
+    .bind(user_id)
+    .bind(defaults.polling_interval)
+    .bind(defaults.seat_threshold)
+    .bind(defaults.monitoring_mode.map(|mode| mode.to_string()))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 // ============================================================================
 // Notification queries
 // ============================================================================
@@ -400,19 +938,39 @@ pub async fn update_notification_settings(
     gmail_nonce: Option<&str>,
     email_recipients: &[String],
     discord_webhook: Option<&str>,
+    success_webhook: Option<&str>,
+    alert_webhook: Option<&str>,
+    alert_email_recipients: &[String],
+    notify_on_close: bool,
+    pushover_user_key: Option<&str>,
+    pushover_token_encrypted: Option<&str>,
+    pushover_token_nonce: Option<&str>,
+    notify_on_recovery: bool,
+    discord_format: Option<&str>,
 ) -> Result<(), Box<dyn StdError + Send + Sync>> {
     let recipients_json = serde_json::to_value(email_recipients)?;
+    let alert_recipients_json = serde_json::to_value(alert_email_recipients)?;
 
     sqlx::query(
         r#"
         UPDATE notification_settings SET
             gmail_address = $1,
-            gmail_app_password_encrypted = $2,
-            gmail_encryption_nonce = $3,
+            gmail_app_password_encrypted = CASE WHEN $2::text IS NULL THEN gmail_app_password_encrypted ELSE NULLIF($2, '') END,
+            gmail_encryption_nonce = CASE WHEN $2::text IS NULL THEN gmail_encryption_nonce ELSE NULLIF($3, '') END,
             email_recipients = $4,
             discord_webhook_url = $5,
+            success_webhook_url = $6,
+            alert_webhook_url = $7,
+            alert_email_recipients = $8,
+            notify_on_close = $9,
+            pushover_user_key = CASE WHEN $10::text IS NULL THEN pushover_user_key ELSE NULLIF($10, '') END,
+            pushover_api_token_encrypted = CASE WHEN $11::text IS NULL THEN pushover_api_token_encrypted ELSE NULLIF($11, '') END,
+            pushover_encryption_nonce = CASE WHEN $11::text IS NULL THEN pushover_encryption_nonce ELSE NULLIF($12, '') END,
+            pushover_api_token = CASE WHEN $11::text IS NULL THEN pushover_api_token END,
+            notify_on_recovery = $13,
+            discord_format = COALESCE($14, discord_format),
             updated_at = NOW()
-        WHERE user_id = $6
+        WHERE user_id = $15
         "#
     )
     .bind(gmail_address)
@@ -420,6 +978,15 @@ pub async fn update_notification_settings(
     .bind(gmail_nonce)
     .bind(recipients_json)
     .bind(discord_webhook)
+    .bind(success_webhook)
+    .bind(alert_webhook)
+    .bind(alert_recipients_json)
+    .bind(notify_on_close)
+    .bind(pushover_user_key)
+    .bind(pushover_token_encrypted)
+    .bind(pushover_token_nonce)
+    .bind(notify_on_recovery)
+    .bind(discord_format)
     .bind(user_id)
     .execute(pool)
     .await?;
@@ -1,78 +1,716 @@
 use std::error::Error as StdError;
-use webweg::wrapper::{WebRegWrapper, input_types::{AddType, EnrollWaitAdd, GradeOption}};
-use chrono::Local;
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use webweg::wrapper::{WebRegWrapper, input_types::{AddType, EnrollWaitAdd, ExplicitAddType, GradeOption}};
+use chrono::{DateTime, Utc};
+use futures::{future, stream, StreamExt};
 use log::{info, warn, error};
-use crate::notifier::Notifier;
-use crate::stats::EnrollmentStats;
-use crate::utils::get_retry_strategy;
+use serde::Serialize;
+use crate::config::EnrollTarget;
+use crate::notifier::{CourseNotice, NoticeKind, Notifier, Priority};
+use crate::stats::{EnrollmentStats, LatencyTracker};
+use crate::utils::{budgeted_retry_strategy, local_now, GraceRetry, RetryBudget, RetryPolicy};
+use crate::webreg::{back_off_if_throttled, is_throttled, ThrottleBackoff, WebRegClient};
+
+/// How an enrollment attempt is made and confirmed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnrollOptions {
+    pub verify: bool,     // Confirm against the schedule before counting success
+    pub two_phase: bool,  // Experimental plan-then-enroll
+    pub plan_unit_count: u8,  // Units sent with the plan request
+    pub target: EnrollTarget,  // Enroll, waitlist, or enroll falling back to the waitlist
+    pub failure_notification_cap: u64,  // Failure notifications per section per day (0 = unlimited)
+    pub retry: RetryPolicy,
+    pub grace: GraceRetry,  // Quick re-adds after a failed attempt, before the normal sleep
+}
+
+impl EnrollOptions {
+    /// The same options for a section with its own target
+    pub fn with_target(self, target: EnrollTarget) -> Self {
+        Self { target, ..self }
+    }
+}
+
+/// What a finished enrollment attempt got the student
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnrollOutcome {
+    Enrolled,
+    Waitlisted,
+    Rejected,
+}
+
+impl EnrollOutcome {
+    pub fn succeeded(self) -> bool {
+        self != EnrollOutcome::Rejected
+    }
+}
+
+/// One `add_section` call and what WebReg said, kept for the attempt history
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollAttempt {
+    pub section: String,
+    pub section_id: String,
+    pub success: bool,
+    pub result: String,
+    pub attempted_at: DateTime<Utc>,
+}
 
 pub async fn try_enroll(
-    wrapper: &WebRegWrapper,
+    client: &impl WebRegClient,
     term: &str,
     section_id: &str,
+    waitlist: bool,
+    latency: &LatencyTracker,
 ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let started = Instant::now();
+    let result = client.add_section(term, section_id, waitlist).await
+        .map_err(|e| {
+            error!("Enrollment error: {:?}", e);
+            e
+        });
+    latency.record(started.elapsed());
+    let result = result?;
+
+    info!("Enrollment attempt result: {:?}", result);
+    Ok(result)
+}
+
+/// Outcome of a validate-only enrollment check for a single section
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollEligibility {
+    pub department: String,
+    pub course_code: String,
+    pub section: String,
+    pub can_enroll: bool,
+    pub reason: Option<String>,
+}
+
+/// Ask WebReg to validate an enrollment without actually adding the section.
+/// Returns `None` when the add would be accepted, or WebReg's reason otherwise
+/// (e.g. missing prerequisite, time conflict, hold on account).
+pub async fn can_enroll(
+    wrapper: &WebRegWrapper,
+    term: &str,
+    section_id: &str,
+) -> Result<Option<String>, Box<dyn StdError + Send + Sync>> {
     let enroll_request = EnrollWaitAdd::builder()
         .with_section_id(section_id)
         .with_grading_option(GradeOption::L)
         .try_build()
         .ok_or("Failed to build enrollment request")?;
 
-    let result = wrapper.req(term).parsed().add_section(AddType::Enroll, enroll_request, true).await
+    match wrapper.req(term).parsed().validate_add_section(AddType::Enroll, &enroll_request).await {
+        Ok(true) => Ok(None),
+        Ok(false) => Ok(Some("WebReg rejected the enrollment validation".to_string())),
+        Err(e) => Ok(Some(e.to_string())),
+    }
+}
+
+/// Run `can_enroll` for every configured section of a course
+pub async fn check_course_eligibility(
+    wrapper: &WebRegWrapper,
+    term: &str,
+    department: &str,
+    course_code: &str,
+    sections: &[String],
+) -> Vec<EnrollEligibility> {
+    let course_info = match wrapper.req(term).parsed().get_course_info(department, course_code).await {
+        Ok(info) => info,
+        Err(e) => {
+            warn!("Eligibility check could not fetch {} {}: {:?}", department, course_code, e);
+            return sections.iter().map(|section| EnrollEligibility {
+                department: department.to_string(),
+                course_code: course_code.to_string(),
+                section: section.clone(),
+                can_enroll: false,
+                reason: Some(format!("Failed to fetch course info: {}", e)),
+            }).collect();
+        }
+    };
+
+    let mut results = Vec::new();
+    for section in sections {
+        let reason = match course_info.iter().find(|s| &s.section_code == section || &s.section_id == section) {
+            Some(section_info) => match can_enroll(wrapper, term, &section_info.section_id).await {
+                Ok(reason) => reason,
+                Err(e) => Some(e.to_string()),
+            },
+            None => Some("Section not found in WebReg course listing".to_string()),
+        };
+
+        if let Some(reason) = &reason {
+            warn!("Cannot enroll in {} {} section {}: {}", department, course_code, section, reason);
+        }
+
+        results.push(EnrollEligibility {
+            department: department.to_string(),
+            course_code: course_code.to_string(),
+            section: section.clone(),
+            can_enroll: reason.is_none(),
+            reason,
+        });
+    }
+
+    results
+}
+
+pub async fn try_drop(
+    wrapper: &WebRegWrapper,
+    term: &str,
+    section_id: &str,
+    waitlisted: bool,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let drop_type = if waitlisted {
+        ExplicitAddType::Waitlist
+    } else {
+        ExplicitAddType::Enroll
+    };
+
+    let result = wrapper.req(term).parsed().drop_section(drop_type, section_id).await
         .map_err(|e| {
-            error!("Enrollment error: {:?}", e);
+            error!("Drop error: {:?}", e);
             e
         })?;
 
-    info!("Enrollment attempt result: {:?}", result);
+    info!("Drop attempt result for section {}: {:?}", section_id, result);
     Ok(result)
 }
 
+/// Experimental: put the section on the plan ahead of the adds. Planning is a
+/// lighter request, so it can land while the add is still contended.
+async fn plan_ahead(
+    client: &impl WebRegClient,
+    term: &str,
+    section_id: &str,
+    section_code: &str,
+    department: &str,
+    course_code: &str,
+    unit_count: u8,
+    latency: &LatencyTracker,
+) {
+    let started = Instant::now();
+    let planned = client.add_to_plan(term, department, course_code, section_id, section_code, unit_count).await;
+    latency.record(started.elapsed());
+
+    // A failed plan doesn't block the add; it only loses the head start
+    match planned {
+        Ok(true) => info!("Planned section {} ahead of enrollment", section_id),
+        Ok(false) => warn!("WebReg declined to plan section {}, enrolling directly", section_id),
+        Err(e) => warn!("Could not plan section {}: {:?}, enrolling directly", section_id, e),
+    }
+}
+
 pub async fn try_enroll_with_retry(
-    wrapper: &WebRegWrapper,
+    client: &impl WebRegClient,
     term: &str,
     section_id: &str,
     department: &str,
     course_code: &str,
     section: &str,
+    options: EnrollOptions,
     notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    budget: &RetryBudget,
     stats: &mut EnrollmentStats,
+    history: Option<&mut Vec<EnrollAttempt>>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let outcome = attempt_enroll(
+        client, term, section_id, department, course_code, section, options, notifier, latency, throttle, budget, history,
+    ).await?;
+    report_enroll_result(term, department, course_code, section, options, notifier, stats, outcome).await;
+    Ok(outcome.succeeded())
+}
+
+/// The WebReg side of `try_enroll_with_retry`: retries, throttle handling and
+/// verification, without touching stats, so several can run at once
+pub async fn attempt_enroll(
+    client: &impl WebRegClient,
+    term: &str,
+    section_id: &str,
+    department: &str,
+    course_code: &str,
+    section: &str,
+    options: EnrollOptions,
+    notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    budget: &RetryBudget,
+    mut history: Option<&mut Vec<EnrollAttempt>>,
+) -> Result<EnrollOutcome, Box<dyn StdError + Send + Sync>> {
+    let waitlisted = |added: bool| if added { EnrollOutcome::Waitlisted } else { EnrollOutcome::Rejected };
+
+    match options.target {
+        EnrollTarget::EnrollOnly => {
+            let added = attempt_add_with_grace(
+                client, term, section_id, department, course_code, section, options, notifier, latency, throttle, budget, false, history,
+            ).await?;
+            Ok(if added { EnrollOutcome::Enrolled } else { EnrollOutcome::Rejected })
+        }
+        EnrollTarget::WaitlistOnly => attempt_add_with_grace(
+            client, term, section_id, department, course_code, section, options, notifier, latency, throttle, budget, true, history,
+        ).await.map(waitlisted),
+        EnrollTarget::EnrollOrWaitlist => {
+            match attempt_add_with_grace(
+                client, term, section_id, department, course_code, section, options, notifier, latency, throttle, budget, false,
+                history.as_deref_mut(),
+            ).await {
+                Ok(true) => return Ok(EnrollOutcome::Enrolled),
+                // Waitlisting into a throttle would only extend it
+                Err(e) if throttle.cooldown_remaining().is_some() => return Err(e),
+                _ => {}
+            }
+
+            info!("Could not enroll in {} {} section {}, joining the waitlist instead", department, course_code, section);
+            attempt_add_with_grace(
+                client, term, section_id, department, course_code, section, options, notifier, latency, throttle, budget, true, history,
+            ).await.map(waitlisted)
+        }
+    }
+}
+
+/// Add every section in `sections` (code, id) or none of them. WebReg takes one add per
+/// request, so a failed add drops the sections already added. Groups join waitlists only
+/// when the target is `WaitlistOnly`.
+pub async fn attempt_enroll_group<S: AsRef<str> + Sync>(
+    client: &impl WebRegClient,
+    term: &str,
+    sections: &[(S, String)],
+    department: &str,
+    course_code: &str,
+    options: EnrollOptions,
+    notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    budget: &RetryBudget,
+    mut history: Option<&mut Vec<EnrollAttempt>>,
+) -> Result<EnrollOutcome, Box<dyn StdError + Send + Sync>> {
+    let waitlist = options.target == EnrollTarget::WaitlistOnly;
+    let mut added = Vec::new();
+
+    for (section, section_id) in sections {
+        let result = attempt_add_with_grace(
+            client, term, section_id, department, course_code, section.as_ref(), options, notifier, latency, throttle, budget,
+            waitlist, history.as_deref_mut(),
+        ).await;
+        if let Ok(true) = result {
+            added.push((section.as_ref(), section_id.as_str()));
+            continue;
+        }
+
+        roll_back_group(client, term, &added, waitlist, department, course_code, notifier).await;
+        return result.map(|_| EnrollOutcome::Rejected);
+    }
+
+    Ok(if waitlist { EnrollOutcome::Waitlisted } else { EnrollOutcome::Enrolled })
+}
+
+/// Try `sections` (code, id) in order until one is added, for a lecture that takes exactly
+/// one discussion. With `EnrollOrWaitlist` every open seat is tried before any waitlist.
+/// Returns the index of the last section tried along with its outcome.
+pub async fn attempt_enroll_one_of<S: AsRef<str> + Sync>(
+    client: &impl WebRegClient,
+    term: &str,
+    sections: &[(S, String)],
+    department: &str,
+    course_code: &str,
+    options: EnrollOptions,
+    notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    budget: &RetryBudget,
+    mut history: Option<&mut Vec<EnrollAttempt>>,
+) -> (usize, Result<EnrollOutcome, Box<dyn StdError + Send + Sync>>) {
+    let passes: &[EnrollTarget] = match options.target {
+        EnrollTarget::EnrollOrWaitlist => &[EnrollTarget::EnrollOnly, EnrollTarget::WaitlistOnly],
+        EnrollTarget::EnrollOnly => &[EnrollTarget::EnrollOnly],
+        EnrollTarget::WaitlistOnly => &[EnrollTarget::WaitlistOnly],
+    };
+    let mut tried = (0, Ok(EnrollOutcome::Rejected));
+
+    for target in passes {
+        for (index, (section, section_id)) in sections.iter().enumerate() {
+            let result = attempt_enroll(
+                client, term, section_id, department, course_code, section.as_ref(), options.with_target(*target), notifier,
+                latency, throttle, budget, history.as_deref_mut(),
+            ).await;
+            let succeeded = result.as_ref().is_ok_and(|outcome| outcome.succeeded());
+            tried = (index, result);
+            if succeeded {
+                return tried;
+            }
+        }
+    }
+
+    tried
+}
+
+/// Drop the sections a group managed to add before one of its adds failed
+async fn roll_back_group(
+    client: &impl WebRegClient,
+    term: &str,
+    added: &[(&str, &str)],
+    waitlist: bool,
+    department: &str,
+    course_code: &str,
+    notifier: &Notifier,
+) {
+    for (section, section_id) in added.iter().rev() {
+        match client.drop_section(term, section_id, waitlist).await {
+            Ok(true) => info!("Rolled back {} {} section {} after the rest of its group failed", department, course_code, section),
+            result => {
+                error!("Could not roll back {} {} section {}: {:?}", department, course_code, section, result);
+                let msg = format!(
+                    "⚠️  Only part of your {} {} group was added, and section {} could not be dropped again.\n\
+                    Drop it on WebReg if you don't want it on its own.\n\nTime: {}",
+                    department, course_code, section, local_now().format("%Y-%m-%d %H:%M:%S")
+                );
+                notifier.send_alert(&msg).await;
+            }
+        }
+    }
+}
+
+/// `attempt_add`, followed by up to `options.grace.retries` single quick adds when it
+/// fails, since a seat lost to a race often reappears within moments
+async fn attempt_add_with_grace(
+    client: &impl WebRegClient,
+    term: &str,
+    section_id: &str,
+    department: &str,
+    course_code: &str,
+    section: &str,
+    options: EnrollOptions,
+    notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    budget: &RetryBudget,
+    waitlist: bool,
+    mut history: Option<&mut Vec<EnrollAttempt>>,
 ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
-    let retry_strategy = get_retry_strategy();
+    // Planned once; the retries and grace re-adds below are only adds
+    if options.two_phase && throttle.cooldown_remaining().is_none() {
+        plan_ahead(client, term, section_id, section, department, course_code, options.plan_unit_count, latency).await;
+    }
 
-    let result = tokio_retry::Retry::spawn(retry_strategy, || async {
-        match try_enroll(wrapper, term, section_id).await {
-            Ok(result) => Ok(result),
+    let mut added = attempt_add(
+        client, term, section_id, department, course_code, section, options, notifier, latency, throttle, budget, waitlist,
+        history.as_deref_mut(),
+    ).await;
+
+    let quick = EnrollOptions { retry: RetryPolicy { max_retries: 0, ..options.retry }, ..options };
+    for grace in 1..=options.grace.retries {
+        match &added {
+            Ok(true) => break,
+            // Retrying into a throttle would only extend it
+            Err(_) if throttle.cooldown_remaining().is_some() => break,
+            _ => {}
+        }
+        // Grace re-adds count against the cycle's retries like any other
+        if !budget.try_spend() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(options.grace.delay_ms)).await;
+        info!("Grace retry {}/{} for {} {} section {}", grace, options.grace.retries, department, course_code, section);
+        added = attempt_add(
+            client, term, section_id, department, course_code, section, quick, notifier, latency, throttle, budget, waitlist,
+            history.as_deref_mut(),
+        ).await;
+    }
+
+    added
+}
+
+/// One enroll or waitlist add with retries and optional verification
+async fn attempt_add(
+    client: &impl WebRegClient,
+    term: &str,
+    section_id: &str,
+    department: &str,
+    course_code: &str,
+    section: &str,
+    options: EnrollOptions,
+    notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    budget: &RetryBudget,
+    waitlist: bool,
+    history: Option<&mut Vec<EnrollAttempt>>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    if let Some(remaining) = throttle.cooldown_remaining() {
+        return Err(format!("WebReg throttle cooldown, {}s left", remaining.as_secs()).into());
+    }
+
+    let retry_strategy = budgeted_retry_strategy(options.retry, budget);
+
+    let attempts = Mutex::new(Vec::new());
+    let record = |success: bool, result: String| {
+        attempts.lock().unwrap_or_else(|e| e.into_inner()).push(EnrollAttempt {
+            section: section.to_string(),
+            section_id: section_id.to_string(),
+            success,
+            result,
+            attempted_at: Utc::now(),
+        });
+    };
+
+    let retried = tokio_retry::RetryIf::spawn(retry_strategy, || async {
+        match try_enroll(client, term, section_id, waitlist, latency).await {
+            Ok(result) => {
+                record(result, format!("add_section returned {}", result));
+                Ok(result)
+            }
             Err(e) => {
+                record(false, e.to_string());
                 warn!("Enrollment error: {:?}, retrying...", e);
                 Err(e)
             }
         }
-    }).await?;
+    }, |e: &Box<dyn StdError + Send + Sync>| !is_throttled(e.as_ref())).await;
 
+    let mut result = match retried {
+        Ok(result) => {
+            throttle.record_success();
+            result
+        }
+        Err(e) => {
+            back_off_if_throttled(e.as_ref(), throttle, notifier).await;
+            if let Some(history) = history {
+                history.extend(attempts.into_inner().unwrap_or_else(|e| e.into_inner()));
+            }
+            return Err(e);
+        }
+    };
+
+    // Don't trust a reported success until the seat shows up on the schedule
+    if result && options.verify {
+        result = match client.is_on_schedule(term, section_id, waitlist).await {
+            Ok(true) => true,
+            Ok(false) => {
+                warn!("WebReg reported enrollment in {} {} section {} but it is not on the schedule",
+                    department, course_code, section);
+                record(false, "Reported success but the section is not on the schedule".to_string());
+                false
+            }
+            Err(e) => {
+                warn!("Could not verify enrollment in {} {} section {}: {:?}",
+                    department, course_code, section, e);
+                record(false, format!("Could not verify enrollment: {}", e));
+                false
+            }
+        };
+    }
+
+    if let Some(history) = history {
+        history.extend(attempts.into_inner().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    Ok(result)
+}
+
+/// Track per-section failures and notify the user about a finished attempt
+pub async fn report_enroll_result(
+    term: &str,
+    department: &str,
+    course_code: &str,
+    section: &str,
+    options: EnrollOptions,
+    notifier: &Notifier,
+    stats: &mut EnrollmentStats,
+    outcome: EnrollOutcome,
+) {
     let section_key = format!("{}_{}_{}_{}", department, course_code, section, term);
+    let course = CourseNotice { department, course_code, section, seats: None };
 
-    if result {
+    if outcome.succeeded() {
         // On success, remove any failure tracking for this section
         stats.section_failures.remove(&section_key);
 
+        let action = if outcome == EnrollOutcome::Waitlisted { "joined the waitlist for" } else { "enrolled in" };
         let msg = format!(
-            "Successfully enrolled in {} {} section {}!\n\nTime: {}\nPlease verify on WebReg.",
-            department, course_code, section, Local::now().format("%Y-%m-%d %H:%M:%S")
+            "Successfully {} {} {} section {}!\n\nTime: {}\nPlease verify on WebReg.",
+            action, department, course_code, section, local_now().format("%Y-%m-%d %H:%M:%S")
         );
-        notifier.send_notification(&msg).await;
+        notifier.send_course_notification(&msg, Priority::High, NoticeKind::Success, &course).await;
     } else {
         // Check if we should notify for this section
-        if stats.should_notify_for_section(&section_key) {
+        if stats.should_notify_for_section(&section_key, options.failure_notification_cap) {
             let msg = format!(
                 "Failed to enroll in {} {} section {} despite available seats.\n\nTime: {}\nPlease check WebReg manually.",
-                department, course_code, section, Local::now().format("%Y-%m-%d %H:%M:%S")
+                department, course_code, section, local_now().format("%Y-%m-%d %H:%M:%S")
             );
-            notifier.send_notification(&msg).await;
+            notifier.send_course_notification(&msg, Priority::Normal, NoticeKind::Error, &course).await;
         } else {
             info!("Suppressing notification for {} {} section {} (exceeded daily failure limit)",
                 department, course_code, section);
         }
     }
+}
 
-    Ok(result)
+/// Run `attempt` over `candidates` in order, with at most `max_concurrent` in flight.
+/// Each attempt reports whether it succeeded; with `stop_on_success`, no new attempt
+/// starts after one does, though attempts already in flight finish.
+pub async fn run_bounded<T, R, F, Fut>(
+    candidates: Vec<T>,
+    max_concurrent: usize,
+    stop_on_success: bool,
+    attempt: F,
+) -> Vec<R>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = (bool, R)>,
+{
+    let succeeded = AtomicBool::new(false);
+    stream::iter(candidates)
+        .take_while(|_| future::ready(!(stop_on_success && succeeded.load(Ordering::SeqCst))))
+        .map(attempt)
+        .buffer_unordered(max_concurrent.max(1))
+        .map(|(success, result)| {
+            if success {
+                succeeded.store(true, Ordering::SeqCst);
+            }
+            result
+        })
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webreg::mock::MockWebReg;
+
+    #[tokio::test]
+    async fn test_enroll_success_and_rejection() {
+        let mock = MockWebReg::default();
+        mock.push_add_result(Ok(true)).push_add_result(Ok(false));
+        let latency = LatencyTracker::default();
+
+        assert!(try_enroll(&mock, "FA25", "123456", false, &latency).await.unwrap());
+        assert!(!try_enroll(&mock, "FA25", "123456", false, &latency).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_enroll_error_propagates() {
+        let mock = MockWebReg::default();
+        mock.push_add_result(Err("Section is full".to_string()));
+
+        let err = try_enroll(&mock, "FA25", "123456", false, &LatencyTracker::default()).await.unwrap_err();
+        assert_eq!(err.to_string(), "Section is full");
+    }
+
+    #[tokio::test]
+    async fn test_enroll_passes_waitlist_flag() {
+        let mock = MockWebReg::default();
+        mock.push_add_result(Ok(true));
+
+        try_enroll(&mock, "FA25", "123456", true, &LatencyTracker::default()).await.unwrap();
+        assert_eq!(*mock.add_calls.lock().unwrap(), vec![("123456".to_string(), true)]);
+    }
+
+    #[tokio::test]
+    async fn test_run_bounded_caps_concurrency_and_stops_on_success() {
+        use std::sync::atomic::AtomicUsize;
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let attempted = run_bounded(vec![1, 2, 3, 4, 5], 2, false, |n| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                peak.fetch_max(in_flight.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                (false, n)
+            }
+        }).await;
+        assert_eq!(attempted.len(), 5);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        // One at a time, the first success ends the run
+        let attempted = run_bounded(vec![1, 2, 3], 1, true, |n| async move { (n == 2, n) }).await;
+        assert_eq!(attempted, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_enroll_gets_grace_retries() {
+        let mock = MockWebReg::default();
+        mock.push_add_result(Ok(false)).push_add_result(Err("Section is full".to_string())).push_add_result(Ok(true));
+        let notifier = Notifier::new(&crate::config::NotificationConfig::default()).unwrap();
+        let options = EnrollOptions {
+            retry: RetryPolicy { max_retries: 0, retry_delay: 1 },
+            grace: GraceRetry { retries: 2, delay_ms: 1 },
+            ..Default::default()
+        };
+        let mut history = Vec::new();
+
+        let outcome = attempt_enroll(&mock, "FA25", "123456", "CSE", "100", "A01", options, &notifier,
+            &LatencyTracker::default(), &ThrottleBackoff::default(), &RetryBudget::default(), Some(&mut history)).await.unwrap();
+        assert_eq!(outcome, EnrollOutcome::Enrolled);
+        assert_eq!(history.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_plans_once_across_contended_retries() {
+        let mock = MockWebReg::default();
+        // Other students win the first two adds
+        mock.push_add_result(Err("Section is full".to_string()))
+            .push_add_result(Err("Section is full".to_string()))
+            .push_add_result(Ok(true));
+        let notifier = Notifier::new(&crate::config::NotificationConfig::default()).unwrap();
+        let options = EnrollOptions {
+            two_phase: true,
+            plan_unit_count: 4,
+            retry: RetryPolicy { max_retries: 2, retry_delay: 1 },
+            ..Default::default()
+        };
+
+        let outcome = attempt_enroll(&mock, "FA25", "123456", "CSE", "100", "A01", options, &notifier,
+            &LatencyTracker::default(), &ThrottleBackoff::default(), &RetryBudget::default(), None).await.unwrap();
+        assert_eq!(outcome, EnrollOutcome::Enrolled);
+        assert_eq!(mock.add_calls.lock().unwrap().len(), 3);
+        assert_eq!(*mock.plan_calls.lock().unwrap(), vec!["123456".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_atomic_group_gets_both_or_neither() {
+        let mock = MockWebReg::default();
+        // The lecture goes through but the lab doesn't, then both go through
+        mock.push_add_result(Ok(true)).push_add_result(Ok(false))
+            .push_add_result(Ok(true)).push_add_result(Ok(true));
+        let notifier = Notifier::new(&crate::config::NotificationConfig::default()).unwrap();
+        let options = EnrollOptions { retry: RetryPolicy { max_retries: 0, retry_delay: 1 }, ..Default::default() };
+        let group = [("A00", "111111".to_string()), ("A50", "111112".to_string())];
+        let attempt = || attempt_enroll_group(&mock, "FA25", &group, "CHEM", "7L", options, &notifier,
+            &LatencyTracker::default(), &ThrottleBackoff::default(), &RetryBudget::default(), None);
+
+        assert_eq!(attempt().await.unwrap(), EnrollOutcome::Rejected);
+        assert_eq!(*mock.drop_calls.lock().unwrap(), vec!["111111".to_string()]);
+
+        assert_eq!(attempt().await.unwrap(), EnrollOutcome::Enrolled);
+        assert_eq!(mock.drop_calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_one_discussion_stops_after_first_success() {
+        let mock = MockWebReg::default();
+        // A01 is rejected, A02 goes through, A03 must not be tried
+        mock.push_add_result(Ok(false)).push_add_result(Ok(true)).push_add_result(Ok(true));
+        let notifier = Notifier::new(&crate::config::NotificationConfig::default()).unwrap();
+        let options = EnrollOptions { retry: RetryPolicy { max_retries: 0, retry_delay: 1 }, ..Default::default() };
+        let discussions = [
+            ("A01", "111112".to_string()),
+            ("A02", "111113".to_string()),
+            ("A03", "111114".to_string()),
+        ];
+
+        let (index, outcome) = attempt_enroll_one_of(&mock, "FA25", &discussions, "CSE", "100", options, &notifier,
+            &LatencyTracker::default(), &ThrottleBackoff::default(), &RetryBudget::default(), None).await;
+        assert_eq!(index, 1);
+        assert_eq!(outcome.unwrap(), EnrollOutcome::Enrolled);
+        let added: Vec<String> = mock.add_calls.lock().unwrap().iter().map(|(id, _)| id.clone()).collect();
+        assert!(!added.contains(&"111114".to_string()));
+    }
 }
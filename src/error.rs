@@ -0,0 +1,86 @@
+use std::error::Error as StdError;
+use std::fmt;
+use axum::http::StatusCode;
+use crate::webreg::Throttled;
+
+/// Failures callers need to tell apart, e.g. to pick an HTTP status.
+/// Anything without a dedicated variant is carried as `Other`.
+#[derive(Debug)]
+pub enum Error {
+    /// The WebReg cookie was rejected or has expired
+    CookieExpired,
+    WebRegThrottled,
+    /// WebReg couldn't be reached, so nothing is known about the cookie
+    WebRegUnavailable(String),
+    InvalidInput(String),
+    NotFound(String),
+    /// The request conflicts with current state (e.g. the job is already running)
+    Conflict(String),
+    LimitReached(String),
+    Db(sqlx::Error),
+    Encryption(String),
+    Other(Box<dyn StdError + Send + Sync>),
+}
+
+impl Error {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Error::CookieExpired | Error::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Conflict(_) | Error::LimitReached(_) => StatusCode::CONFLICT,
+            Error::WebRegThrottled | Error::WebRegUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Db(_) | Error::Encryption(_) | Error::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::CookieExpired => write!(f, "WebReg cookie is invalid or has expired"),
+            Error::WebRegThrottled => write!(f, "WebReg is throttling requests, try again later"),
+            Error::WebRegUnavailable(reason) => write!(f, "WebReg is unavailable, try again later: {}", reason),
+            Error::InvalidInput(msg)
+            | Error::NotFound(msg)
+            | Error::Conflict(msg)
+            | Error::LimitReached(msg) => write!(f, "{}", msg),
+            Error::Db(e) => write!(f, "Database error: {}", e),
+            Error::Encryption(msg) => write!(f, "Encryption error: {}", msg),
+            Error::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Db(e) => Some(e),
+            Error::Other(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        Error::Db(e)
+    }
+}
+
+/// Recovers the specific variant from code that still returns boxed errors
+impl From<Box<dyn StdError + Send + Sync>> for Error {
+    fn from(e: Box<dyn StdError + Send + Sync>) -> Self {
+        let e = match e.downcast::<Error>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<sqlx::Error>() {
+            Ok(e) => return Error::Db(*e),
+            Err(e) => e,
+        };
+        if e.downcast_ref::<Throttled>().is_some() {
+            return Error::WebRegThrottled;
+        }
+        Error::Other(e)
+    }
+}
@@ -2,13 +2,13 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use std::time::Duration;
 use tokio::time::sleep;
-use log::{info, error};
-use chrono::Local;
+use log::{info, warn, error};
 
 use crate::state::AppState;
-use crate::monitor::monitor_section_with_retry;
-use crate::enroll::try_enroll_with_retry;
+use crate::monitor::{fetch_course_listing, monitor_section_with_retry};
+use crate::enroll::{try_enroll_with_retry, EnrollOptions};
 use crate::config::{CourseDetails, to_section_groups};
+use crate::utils::{local_now, within, RetryBudget, SessionCheckCycles};
 
 pub struct JobManager {
     pub state: Arc<Mutex<AppState>>,
@@ -42,11 +42,14 @@ impl JobManager {
         let mut shutdown_rx = self.shutdown_tx.subscribe();
 
         tokio::spawn(async move {
-            let mut cookie_refresh_timer = {
+            let (mut cookie_refresh_timer, mut session_cycles) = {
                 let state_guard = state.lock().await;
-                tokio::time::interval(Duration::from_secs(
-                    state_guard.config.monitoring.cookie_refresh_interval,
-                ))
+                (
+                    tokio::time::interval(Duration::from_secs(
+                        state_guard.config.monitoring.cookie_refresh_interval,
+                    )),
+                    SessionCheckCycles::new(state_guard.config.monitoring.session_check_cycles),
+                )
             };
 
             loop {
@@ -58,6 +61,7 @@ impl JobManager {
                         break;
                     }
                     _ = cookie_refresh_timer.tick() => {
+                        session_cycles.checked();
                         let mut state_guard = state.lock().await;
                         if let Err(e) = crate::state::refresh_cookie(&mut state_guard).await {
                             log::error!("Failed to refresh cookie: {:?}", e);
@@ -81,54 +85,114 @@ impl JobManager {
                             return;
                         }
 
+                        // WebReg is throttling us; wait out the cooldown instead of polling into it
+                        if let Some(remaining) = state_guard.throttle.cooldown_remaining() {
+                            drop(state_guard);
+                            sleep(remaining).await;
+                            return;
+                        }
+
                         // Clone all the values we need
-                        let term = state_guard.term.clone();
                         let polling_interval_val = state_guard.config.webreg.polling_interval;
-                        let wrapper = match state_guard.clone_wrapper() {
+                        let wrapper = match state_guard.current_wrapper().await {
                             Ok(w) => w,
                             Err(e) => {
-                                error!("Failed to clone WebRegWrapper: {:?}", e);
+                                error!("Failed to build WebRegWrapper: {:?}", e);
                                 drop(state_guard);
                                 sleep(Duration::from_secs(polling_interval_val)).await;
                                 return;
                             }
                         };
+                        let term = state_guard.term.clone();
                         let notifier = state_guard.notifier.clone();
+                        let latency = state_guard.latency.clone();
+                        let throttle = state_guard.throttle.clone();
+                        let openings = state_guard.openings.with_required(state_guard.config.monitoring.consecutive_polls);
                         let chem_config = state_guard.config.courses.chem.clone();
                         let bild_config = state_guard.config.courses.bild.clone();
                         let polling_interval = state_guard.config.webreg.polling_interval;
                         let seat_threshold = state_guard.config.monitoring.seat_threshold;
+                        let threshold_direction = state_guard.config.monitoring.threshold_direction;
+                        let mode = state_guard.config.monitoring.effective_mode();
+                        let retry = state_guard.config.monitoring.retry_policy();
+                        // Caps retries for the whole cycle, not per section
+                        let budget = RetryBudget::new(state_guard.config.monitoring.cycle_retry_budget);
+                        let enroll_options = EnrollOptions {
+                            verify: state_guard.config.monitoring.verify_enrollment,
+                            two_phase: state_guard.config.monitoring.two_phase_enroll,
+                            plan_unit_count: state_guard.config.monitoring.plan_unit_count,
+                            target: mode.default_target(),
+                            failure_notification_cap: state_guard.config.monitoring.failure_notification_cap,
+                            retry,
+                            grace: state_guard.config.monitoring.grace_retry(),
+                        };
+                        let recheck_freshness = Duration::from_millis(state_guard.config.monitoring.recheck_freshness_ms);
+                        // Bounds checks only; an enroll cut short could take a seat without recording it
+                        let section_timeout = Duration::from_secs(state_guard.config.monitoring.section_timeout_secs);
+
+                        // Courses in another session (e.g. summer) poll their own term on the same wrapper
+                        let chem_term = chem_config.term().unwrap_or(&term).to_string();
+                        let bild_term = bild_config.term.clone().unwrap_or_else(|| term.clone());
+
+                        // One listing fetch serves every CHEM section this cycle; without it CHEM sits the cycle out
+                        let chem_listing = within(section_timeout, fetch_course_listing(
+                            &wrapper, &chem_term, &chem_config.department(), &chem_config.course_code(), &notifier, &latency, &throttle, retry, &budget,
+                        )).await.ok();
 
                         // Monitor CHEM sections
-                        let chem_sections = match &chem_config {
-                            CourseDetails::New(details) => details.sections.clone(),
-                            CourseDetails::Legacy(details) => to_section_groups(details),
+                        let chem_sections = match (&chem_listing, &chem_config) {
+                            (None, _) => {
+                                warn!("CHEM listing unavailable, skipping CHEM this cycle");
+                                Vec::new()
+                            }
+                            (Some(_), CourseDetails::New(details)) => details.sections.clone(),
+                            (Some(_), CourseDetails::Legacy(details)) => to_section_groups(details),
                         };
 
                         for section_group in &chem_sections {
-                            // Monitor lecture section
-                            if let Ok(Some(section_id)) = monitor_section_with_retry(
+                            // Monitor lecture section (only reported when the group requires a discussion)
+                            state_guard.stats.total_checks += 1;
+                            if let Some(opening) = within(section_timeout, monitor_section_with_retry(
                                 &wrapper,
-                                &term,
-                                &section_group.lecture,
+                                chem_listing.as_ref(),
+                                &chem_term,
+                                section_group.lecture_key(),
                                 &chem_config.department(),
                                 &chem_config.course_code(),
                                 polling_interval,
                                 seat_threshold,
+                                threshold_direction,
+                                mode,
+                                section_group.enroll_target(mode),
+                                recheck_freshness,
+                                section_group.enrolls_lecture_alone(),
                                 &notifier,
-                            )
+                                &latency,
+                                &throttle,
+                                &openings,
+                                retry,
+                                &budget,
+                            ))
                             .await
+                            .ok()
+                            .flatten()
+                            .filter(|_| section_group.enrolls_lecture_alone())
                             {
                                 state_guard.stats.enrollment_attempts += 1;
                                 if let Ok(true) = try_enroll_with_retry(
                                     &wrapper,
-                                    &term,
-                                    &section_id,
+                                    &chem_term,
+                                    &opening.section_id,
                                     &chem_config.department(),
                                     &chem_config.course_code(),
                                     &section_group.lecture,
+                                    enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                                     &notifier,
+                                    &latency,
+                                    &throttle,
+                                    &budget,
                                     &mut state_guard.stats,
+                                    None,
                                 )
                                 .await
                                 {
@@ -138,28 +202,45 @@ impl JobManager {
 
                             // Monitor discussion sections
                             for discussion in &section_group.discussions {
-                                if let Ok(Some(section_id)) = monitor_section_with_retry(
+                                state_guard.stats.total_checks += 1;
+                                if let Ok(Some(opening)) = within(section_timeout, monitor_section_with_retry(
                                     &wrapper,
-                                    &term,
+                                    chem_listing.as_ref(),
+                                    &chem_term,
                                     discussion,
                                     &chem_config.department(),
                                     &chem_config.course_code(),
                                     polling_interval,
                                     seat_threshold,
+                                    threshold_direction,
+                                    mode,
+                                    section_group.enroll_target(mode),
+                                    recheck_freshness,
+                                    true,
                                     &notifier,
-                                )
+                                    &latency,
+                                    &throttle,
+                                    &openings,
+                                    retry,
+                                    &budget,
+                                ))
                                 .await
                                 {
                                     state_guard.stats.enrollment_attempts += 1;
                                     if let Ok(true) = try_enroll_with_retry(
                                         &wrapper,
-                                        &term,
-                                        &section_id,
+                                        &chem_term,
+                                        &opening.section_id,
                                         &chem_config.department(),
                                         &chem_config.course_code(),
                                         discussion,
+                                        enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                                         &notifier,
+                                        &latency,
+                                        &throttle,
+                                        &budget,
                                         &mut state_guard.stats,
+                                        None,
                                     )
                                     .await
                                     {
@@ -169,33 +250,63 @@ impl JobManager {
                             }
                         }
 
+                        // One listing fetch serves every BILD section this cycle; without it BILD sits the cycle out
+                        let bild_listing = within(section_timeout, fetch_course_listing(
+                            &wrapper, &bild_term, &bild_config.department, &bild_config.course_code, &notifier, &latency, &throttle, retry, &budget,
+                        )).await.ok();
+
                         // Monitor BILD sections
-                        let bild_sections = to_section_groups(&bild_config);
+                        let bild_sections = if bild_listing.is_some() {
+                            to_section_groups(&bild_config)
+                        } else {
+                            warn!("BILD listing unavailable, skipping BILD this cycle");
+                            Vec::new()
+                        };
 
                         for section_group in &bild_sections {
-                            // Monitor lecture section
-                            if let Ok(Some(section_id)) = monitor_section_with_retry(
+                            // Monitor lecture section (only reported when the group requires a discussion)
+                            state_guard.stats.total_checks += 1;
+                            if let Some(opening) = within(section_timeout, monitor_section_with_retry(
                                 &wrapper,
-                                &term,
-                                &section_group.lecture,
+                                bild_listing.as_ref(),
+                                &bild_term,
+                                section_group.lecture_key(),
                                 &bild_config.department,
                                 &bild_config.course_code,
                                 polling_interval,
                                 seat_threshold,
+                                threshold_direction,
+                                mode,
+                                section_group.enroll_target(mode),
+                                recheck_freshness,
+                                section_group.enrolls_lecture_alone(),
                                 &notifier,
-                            )
+                                &latency,
+                                &throttle,
+                                &openings,
+                                retry,
+                                &budget,
+                            ))
                             .await
+                            .ok()
+                            .flatten()
+                            .filter(|_| section_group.enrolls_lecture_alone())
                             {
                                 state_guard.stats.enrollment_attempts += 1;
                                 if let Ok(true) = try_enroll_with_retry(
                                     &wrapper,
-                                    &term,
-                                    &section_id,
+                                    &bild_term,
+                                    &opening.section_id,
                                     &bild_config.department,
                                     &bild_config.course_code,
                                     &section_group.lecture,
+                                    enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                                     &notifier,
+                                    &latency,
+                                    &throttle,
+                                    &budget,
                                     &mut state_guard.stats,
+                                    None,
                                 )
                                 .await
                                 {
@@ -205,28 +316,45 @@ impl JobManager {
 
                             // Monitor discussion sections
                             for discussion in &section_group.discussions {
-                                if let Ok(Some(section_id)) = monitor_section_with_retry(
+                                state_guard.stats.total_checks += 1;
+                                if let Ok(Some(opening)) = within(section_timeout, monitor_section_with_retry(
                                     &wrapper,
-                                    &term,
+                                    bild_listing.as_ref(),
+                                    &bild_term,
                                     discussion,
                                     &bild_config.department,
                                     &bild_config.course_code,
                                     polling_interval,
                                     seat_threshold,
+                                    threshold_direction,
+                                    mode,
+                                    section_group.enroll_target(mode),
+                                    recheck_freshness,
+                                    true,
                                     &notifier,
-                                )
+                                    &latency,
+                                    &throttle,
+                                    &openings,
+                                    retry,
+                                    &budget,
+                                ))
                                 .await
                                 {
                                     state_guard.stats.enrollment_attempts += 1;
                                     if let Ok(true) = try_enroll_with_retry(
                                         &wrapper,
-                                        &term,
-                                        &section_id,
+                                        &bild_term,
+                                        &opening.section_id,
                                         &bild_config.department,
                                         &bild_config.course_code,
                                         discussion,
+                                        enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                                         &notifier,
+                                        &latency,
+                                        &throttle,
+                                        &budget,
                                         &mut state_guard.stats,
+                                        None,
                                     )
                                     .await
                                     {
@@ -238,13 +366,17 @@ impl JobManager {
 
                         let health = state_guard.check_health().await;
                         info!("Health status: {:?}", health);
-                        state_guard.last_check_time = Local::now().to_string();
+                        state_guard.last_check_time = local_now().to_string();
 
                         // Release lock before sleeping to allow cookie refresh and API calls
                         drop(state_guard);
 
                         sleep(Duration::from_secs(polling_interval)).await;
-                    } => {}
+                    } => {
+                        if session_cycles.cycle_done() {
+                            cookie_refresh_timer.reset_immediately();
+                        }
+                    }
                 }
             }
         });
@@ -13,14 +13,13 @@ use tokio::signal::ctrl_c;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use std::error::Error as StdError;
-use log::{info, error};
-use chrono::Local;
+use log::{info, warn, error};
 
 use config::{CourseDetails, to_section_groups};
-use state::{AppState, refresh_cookie};
-use monitor::monitor_section_with_retry;
-use enroll::try_enroll_with_retry;
-use utils::setup_logging;
+use state::{AppState, load_config, refresh_cookie};
+use monitor::{fetch_course_listing, monitor_section_with_retry};
+use enroll::{try_enroll_with_retry, EnrollOptions};
+use utils::{set_data_dir, setup_logging, within, ReloadSignal, RetryBudget, SessionCheckCycles, local_now};
 
 async fn run_monitor(
     state: Arc<Mutex<AppState>>,
@@ -30,6 +29,8 @@ async fn run_monitor(
     let mut cookie_refresh_timer = tokio::time::interval(
         Duration::from_secs(state.lock().await.config.monitoring.cookie_refresh_interval)
     );
+    let mut session_cycles = SessionCheckCycles::new(state.lock().await.config.monitoring.session_check_cycles);
+    let mut reload_signal = ReloadSignal::new()?;
 
     loop {
         tokio::select! {
@@ -37,7 +38,23 @@ async fn run_monitor(
                 info!("Received shutdown signal, stopping monitoring...");
                 break;
             }
+            _ = reload_signal.recv() => {
+                info!("Received SIGHUP, reloading config.toml...");
+                let mut state_guard = state.lock().await;
+                match state_guard.reload_config().await {
+                    Ok(changes) if changes.is_empty() => info!("Config reloaded, nothing changed"),
+                    Ok(changes) => {
+                        info!("Config reloaded, applied changes to: {}", changes.join(", "));
+                        cookie_refresh_timer = tokio::time::interval(
+                            Duration::from_secs(state_guard.config.monitoring.cookie_refresh_interval)
+                        );
+                        session_cycles = SessionCheckCycles::new(state_guard.config.monitoring.session_check_cycles);
+                    }
+                    Err(e) => error!("Failed to reload config, keeping the running config: {:?}", e),
+                }
+            }
             _ = cookie_refresh_timer.tick() => {
+                session_cycles.checked();
                 let mut state_guard = state.lock().await;
                 if let Err(e) = refresh_cookie(&mut state_guard).await {
                     log::error!("Failed to refresh cookie: {:?}", e);
@@ -56,52 +73,109 @@ async fn run_monitor(
                         return;
                     }
 
+                    // WebReg is throttling us; wait out the cooldown instead of polling into it
+                    if let Some(remaining) = state_guard.throttle.cooldown_remaining() {
+                        drop(state_guard);
+                        sleep(remaining).await;
+                        return;
+                    }
+
                     // Clone all the values we need
-                    let term = state_guard.term.clone();
                     let polling_interval_val = state_guard.config.webreg.polling_interval;
-                    let wrapper = match state_guard.clone_wrapper() {
+                    let wrapper = match state_guard.current_wrapper().await {
                         Ok(w) => w,
                         Err(e) => {
-                            error!("Failed to clone WebRegWrapper: {:?}", e);
+                            error!("Failed to build WebRegWrapper: {:?}", e);
                             drop(state_guard);
                             sleep(Duration::from_secs(polling_interval_val)).await;
                             return;
                         }
                     };
+                    let term = state_guard.term.clone();
                     let notifier = state_guard.notifier.clone();
+                    let latency = state_guard.latency.clone();
+                    let throttle = state_guard.throttle.clone();
+                    let openings = state_guard.openings.with_required(state_guard.config.monitoring.consecutive_polls);
                     let chem_config = state_guard.config.courses.chem.clone();
                     let bild_config = state_guard.config.courses.bild.clone();
                     let polling_interval = state_guard.config.webreg.polling_interval;
                     let seat_threshold = state_guard.config.monitoring.seat_threshold;
+                    let threshold_direction = state_guard.config.monitoring.threshold_direction;
+                    let mode = state_guard.config.monitoring.effective_mode();
+                    let retry = state_guard.config.monitoring.retry_policy();
+                    // Every retry this cycle, across all sections, draws from one budget
+                    let budget = RetryBudget::new(state_guard.config.monitoring.cycle_retry_budget);
+                    let enroll_options = EnrollOptions {
+                        verify: state_guard.config.monitoring.verify_enrollment,
+                        two_phase: state_guard.config.monitoring.two_phase_enroll,
+                        plan_unit_count: state_guard.config.monitoring.plan_unit_count,
+                        target: mode.default_target(),
+                        failure_notification_cap: state_guard.config.monitoring.failure_notification_cap,
+                        retry,
+                        grace: state_guard.config.monitoring.grace_retry(),
+                    };
+                    let recheck_freshness = Duration::from_millis(state_guard.config.monitoring.recheck_freshness_ms);
+                    // Bounds checks only; an enroll cut short could take a seat without recording it
+                    let section_timeout = Duration::from_secs(state_guard.config.monitoring.section_timeout_secs);
+
+                    // Courses in another session (e.g. summer) poll their own term on the same wrapper
+                    let chem_term = chem_config.term().unwrap_or(&term).to_string();
+                    let bild_term = bild_config.term.clone().unwrap_or_else(|| term.clone());
+
+                    // One listing fetch serves every CHEM section this cycle; without it CHEM sits the cycle out
+                    let chem_listing = within(section_timeout, fetch_course_listing(
+                        &wrapper, &chem_term, &chem_config.department(), &chem_config.course_code(), &notifier, &latency, &throttle, retry, &budget,
+                    )).await.ok();
 
                     // Monitor CHEM sections
-                    let chem_sections = match &chem_config {
-                        CourseDetails::New(details) => details.sections.clone(),
-                        CourseDetails::Legacy(details) => to_section_groups(details),
+                    let chem_sections = match (&chem_listing, &chem_config) {
+                        (None, _) => {
+                            warn!("CHEM listing unavailable, skipping CHEM this cycle");
+                            Vec::new()
+                        }
+                        (Some(_), CourseDetails::New(details)) => details.sections.clone(),
+                        (Some(_), CourseDetails::Legacy(details)) => to_section_groups(details),
                     };
 
                     for section_group in &chem_sections {
-                    // Monitor lecture section
-                    if let Ok(Some(section_id)) = monitor_section_with_retry(
+                    // Monitor lecture section (only reported when the group requires a discussion)
+                    state_guard.stats.total_checks += 1;
+                    if let Some(opening) = within(section_timeout, monitor_section_with_retry(
                         &wrapper,
-                        &term,
-                        &section_group.lecture,
+                        chem_listing.as_ref(),
+                        &chem_term,
+                        section_group.lecture_key(),
                         &chem_config.department(),
                         &chem_config.course_code(),
                         polling_interval,
                         seat_threshold,
+                        threshold_direction,
+                        mode,
+                        section_group.enroll_target(mode),
+                        recheck_freshness,
+                        section_group.enrolls_lecture_alone(),
                         &notifier,
-                    ).await {
+                        &latency,
+                        &throttle,
+                        &openings,
+                        retry,
+                        &budget,
+                    )).await.ok().flatten().filter(|_| section_group.enrolls_lecture_alone()) {
                         state_guard.stats.enrollment_attempts += 1;
                         if let Ok(true) = try_enroll_with_retry(
                             &wrapper,
-                            &term,
-                            &section_id,
+                            &chem_term,
+                            &opening.section_id,
                             &chem_config.department(),
                             &chem_config.course_code(),
                             &section_group.lecture,
+                            enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                             &notifier,
+                            &latency,
+                            &throttle,
+                            &budget,
                             &mut state_guard.stats,
+                            None,
                         ).await {
                             state_guard.stats.successful_enrollments += 1;
                         }
@@ -109,26 +183,43 @@ async fn run_monitor(
 
                     // Monitor discussion sections
                     for discussion in &section_group.discussions {
-                        if let Ok(Some(section_id)) = monitor_section_with_retry(
+                        state_guard.stats.total_checks += 1;
+                        if let Ok(Some(opening)) = within(section_timeout, monitor_section_with_retry(
                             &wrapper,
-                            &term,
+                            chem_listing.as_ref(),
+                            &chem_term,
                             discussion,
                             &chem_config.department(),
                             &chem_config.course_code(),
                             polling_interval,
                             seat_threshold,
+                            threshold_direction,
+                            mode,
+                            section_group.enroll_target(mode),
+                            recheck_freshness,
+                            true,
                             &notifier,
-                        ).await {
+                            &latency,
+                            &throttle,
+                            &openings,
+                            retry,
+                            &budget,
+                        )).await {
                             state_guard.stats.enrollment_attempts += 1;
                             if let Ok(true) = try_enroll_with_retry(
                                 &wrapper,
-                                &term,
-                                &section_id,
+                                &chem_term,
+                                &opening.section_id,
                                 &chem_config.department(),
                                 &chem_config.course_code(),
                                 discussion,
+                                enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                                 &notifier,
+                                &latency,
+                                &throttle,
+                                &budget,
                                 &mut state_guard.stats,
+                                None,
                             ).await {
                                 state_guard.stats.successful_enrollments += 1;
                             }
@@ -136,31 +227,58 @@ async fn run_monitor(
                     }
                 }
 
+                // One listing fetch serves every BILD section this cycle; without it BILD sits the cycle out
+                let bild_listing = within(section_timeout, fetch_course_listing(
+                    &wrapper, &bild_term, &bild_config.department, &bild_config.course_code, &notifier, &latency, &throttle, retry, &budget,
+                )).await.ok();
+
                 // Monitor BILD sections
-                let bild_sections = to_section_groups(&bild_config);
+                let bild_sections = if bild_listing.is_some() {
+                    to_section_groups(&bild_config)
+                } else {
+                    warn!("BILD listing unavailable, skipping BILD this cycle");
+                    Vec::new()
+                };
 
                 for section_group in &bild_sections {
-                    // Monitor lecture section
-                    if let Ok(Some(section_id)) = monitor_section_with_retry(
+                    // Monitor lecture section (only reported when the group requires a discussion)
+                    state_guard.stats.total_checks += 1;
+                    if let Some(opening) = within(section_timeout, monitor_section_with_retry(
                         &wrapper,
-                        &term,
-                        &section_group.lecture,
+                        bild_listing.as_ref(),
+                        &bild_term,
+                        section_group.lecture_key(),
                         &bild_config.department,
                         &bild_config.course_code,
                         polling_interval,
                         seat_threshold,
+                        threshold_direction,
+                        mode,
+                        section_group.enroll_target(mode),
+                        recheck_freshness,
+                        section_group.enrolls_lecture_alone(),
                         &notifier,
-                    ).await {
+                        &latency,
+                        &throttle,
+                        &openings,
+                        retry,
+                        &budget,
+                    )).await.ok().flatten().filter(|_| section_group.enrolls_lecture_alone()) {
                         state_guard.stats.enrollment_attempts += 1;
                         if let Ok(true) = try_enroll_with_retry(
                             &wrapper,
-                            &term,
-                            &section_id,
+                            &bild_term,
+                            &opening.section_id,
                             &bild_config.department,
                             &bild_config.course_code,
                             &section_group.lecture,
+                            enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                             &notifier,
+                            &latency,
+                            &throttle,
+                            &budget,
                             &mut state_guard.stats,
+                            None,
                         ).await {
                             state_guard.stats.successful_enrollments += 1;
                         }
@@ -168,26 +286,43 @@ async fn run_monitor(
 
                     // Monitor discussion sections
                     for discussion in &section_group.discussions {
-                        if let Ok(Some(section_id)) = monitor_section_with_retry(
+                        state_guard.stats.total_checks += 1;
+                        if let Ok(Some(opening)) = within(section_timeout, monitor_section_with_retry(
                             &wrapper,
-                            &term,
+                            bild_listing.as_ref(),
+                            &bild_term,
                             discussion,
                             &bild_config.department,
                             &bild_config.course_code,
                             polling_interval,
                             seat_threshold,
+                            threshold_direction,
+                            mode,
+                            section_group.enroll_target(mode),
+                            recheck_freshness,
+                            true,
                             &notifier,
-                        ).await {
+                            &latency,
+                            &throttle,
+                            &openings,
+                            retry,
+                            &budget,
+                        )).await {
                             state_guard.stats.enrollment_attempts += 1;
                             if let Ok(true) = try_enroll_with_retry(
                                 &wrapper,
-                                &term,
-                                &section_id,
+                                &bild_term,
+                                &opening.section_id,
                                 &bild_config.department,
                                 &bild_config.course_code,
                                 discussion,
+                                enroll_options.with_target(section_group.enroll_target(mode).for_seats(opening.available_seats)),
                                 &notifier,
+                                &latency,
+                                &throttle,
+                                &budget,
                                 &mut state_guard.stats,
+                                None,
                             ).await {
                                 state_guard.stats.successful_enrollments += 1;
                             }
@@ -197,14 +332,19 @@ async fn run_monitor(
 
                     let health = state_guard.check_health().await;
                     info!("Health status: {:?}", health);
-                    state_guard.last_check_time = Local::now().to_string();
+                    state_guard.last_check_time = local_now().to_string();
 
                     polling_interval
                 }; // Lock is released here
 
                 // Sleep without holding the lock
                 sleep(Duration::from_secs(polling_interval)).await;
-            } => {}
+            } => {
+                // Catch a cookie that expires mid-interval without waiting for the next refresh
+                if session_cycles.cycle_done() {
+                    cookie_refresh_timer.reset_immediately();
+                }
+            }
         }
     }
 
@@ -215,6 +355,12 @@ async fn run_monitor(
 async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     println!("Starting main...");
 
+    // Config comes first so the log file opens under its data_dir
+    let config = load_config()?;
+    if let Some(dir) = &config.monitoring.data_dir {
+        set_data_dir(dir)?;
+    }
+
     // Setup logging
     println!("Setting up logging...");
     setup_logging()?;
@@ -222,7 +368,7 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
 
     // Initialize application state
     println!("Initializing application state...");
-    let state = Arc::new(Mutex::new(AppState::new().await?));
+    let state = Arc::new(Mutex::new(AppState::new(config).await?));
     println!("Application state initialized successfully");
 
     // Setup shutdown channel
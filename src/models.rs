@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
+use crate::config::{dedupe_section_groups, normalize_course_field, CourseRole, DiscordFormat, EnrollTarget, MonitoringMode, SectionList};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -10,6 +11,7 @@ pub struct User {
     pub email: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub is_admin: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -18,7 +20,10 @@ pub struct Job {
     pub user_id: Uuid,
     pub term: String,
     pub polling_interval: i32,
+    // Secrets never leave the server, even encrypted; responses use dedicated DTOs
+    #[serde(skip_serializing)]
     pub cookie_encrypted: String,
+    #[serde(skip_serializing)]
     pub encryption_nonce: String,
     pub seat_threshold: i32,
     pub monitoring_mode: String,
@@ -27,6 +32,36 @@ pub struct Job {
     pub last_check_time: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub enroll: bool,
+    pub verify_enrollment: bool,
+    pub stop_on_first_success: bool,
+    pub max_run_duration_hours: Option<i32>,
+    pub cookie_refresh_interval: i32,
+    pub threshold_direction: String,
+    #[serde(skip_serializing)]
+    pub backup_cookie_encrypted: Option<String>,
+    #[serde(skip_serializing)]
+    pub backup_encryption_nonce: Option<String>,
+    pub max_concurrent_enrolls: i32,
+    pub stop_cycle_on_success: bool,
+    pub watch_enrolled: bool,
+    pub held_sections: sqlx::types::JsonValue,
+    pub poll_immediately: bool,
+    pub enrollment_opens_at: Option<DateTime<Utc>>,
+    pub force_enroll: bool,
+    pub ignore_threshold: bool,
+    pub filled_groups: sqlx::types::JsonValue,
+    pub primary_enrolled: bool,
+}
+
+/// A section the job enrolled or waitlisted in, re-checked when `watch_enrolled` is on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldSection {
+    pub department: String,
+    pub course_code: String,
+    pub section: String,
+    pub section_id: String,
+    pub waitlisted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -36,6 +71,8 @@ pub struct Course {
     pub department: String,
     pub course_code: String,
     pub created_at: DateTime<Utc>,
+    pub section_pattern: Option<String>,
+    pub role: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -45,6 +82,11 @@ pub struct Section {
     pub lecture: String,
     pub discussions: sqlx::types::JsonValue,
     pub created_at: DateTime<Utc>,
+    pub section_id: Option<String>,
+    pub enroll_target: Option<String>,
+    pub atomic: bool,
+    pub one_discussion: bool,
+    pub discussion_required: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -61,17 +103,43 @@ pub struct EnrollmentStatsDb {
     pub last_updated: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserPreferences {
+    pub user_id: Uuid,
+    pub polling_interval: Option<i32>,
+    pub seat_threshold: Option<i32>,
+    pub monitoring_mode: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct NotificationSettings {
     pub id: Uuid,
     pub user_id: Uuid,
     pub gmail_address: Option<String>,
+    // Secrets never leave the server, even encrypted; see NotificationSettingsResponse
+    #[serde(skip_serializing)]
     pub gmail_app_password_encrypted: Option<String>,
+    #[serde(skip_serializing)]
     pub gmail_encryption_nonce: Option<String>,
     pub email_recipients: sqlx::types::JsonValue,
     pub discord_webhook_url: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub success_webhook_url: Option<String>,
+    pub alert_webhook_url: Option<String>,
+    pub alert_email_recipients: sqlx::types::JsonValue,
+    pub notify_on_close: bool,
+    pub pushover_user_key: Option<String>,
+    // Only tokens saved before encryption; a new save clears it
+    #[serde(skip_serializing)]
+    pub pushover_api_token: Option<String>,
+    pub notify_on_recovery: bool,
+    pub discord_format: String,
+    #[serde(skip_serializing)]
+    pub pushover_api_token_encrypted: Option<String>,
+    #[serde(skip_serializing)]
+    pub pushover_encryption_nonce: Option<String>,
 }
 
 // Request/Response DTOs
@@ -81,29 +149,516 @@ pub struct CreateJobRequest {
     pub polling_interval: i32,
     pub cookie: String,
     pub seat_threshold: i32,
-    pub monitoring_mode: String,
+    pub monitoring_mode: MonitoringMode,  // available, low_seats or waitlist_only; anything else is rejected
     pub courses: Vec<CourseRequest>,
+    #[serde(default = "default_true")]
+    pub enroll: bool,  // false = notify on openings but never attempt enrollment
+    #[serde(default = "default_true")]
+    pub verify_enrollment: bool,
+    #[serde(default)]
+    pub stop_on_first_success: bool,  // Stop the job after the first successful enrollment
+    #[serde(default)]
+    pub max_run_duration_hours: Option<i32>,  // Auto-stop after this many hours (None = unlimited)
+    #[serde(default = "default_cookie_refresh_interval")]
+    pub cookie_refresh_interval: i32,  // Seconds between WebReg session checks
+    #[serde(default = "default_threshold_direction")]
+    pub threshold_direction: String,  // "at_most" (few seats left) or "at_least" (comfortably many)
+    #[serde(default)]
+    pub backup_cookie: Option<String>,  // Failed over to when the primary cookie expires
+    #[serde(default = "default_max_concurrent_enrolls")]
+    pub max_concurrent_enrolls: i32,  // Enrollment attempts in flight at once when several sections open
+    #[serde(default)]
+    pub stop_cycle_on_success: bool,  // Skip the cycle's remaining openings after one enrollment succeeds
+    #[serde(default)]
+    pub watch_enrolled: bool,  // Re-check enrolled sections and re-enroll if WebReg drops them
+    #[serde(default = "default_true")]
+    pub poll_immediately: bool,  // false = wait a random part of the interval first, spreading out jobs started together
+    #[serde(default)]
+    pub enrollment_opens_at: Option<DateTime<Utc>>,  // Enrollment appointment; the job only monitors until then
+    #[serde(default)]
+    pub force_enroll: bool,  // Enroll even before enrollment_opens_at
+    #[serde(default)]
+    pub ignore_threshold: bool,  // Attempt on any open seat, as if seat_threshold were 0
+}
+
+impl CreateJobRequest {
+    /// Raise a polling interval below the server's floor, returning a note for the response
+    pub fn enforce_min_polling_interval(&mut self, floor: u64) -> Option<String> {
+        let floor = i32::try_from(floor).unwrap_or(i32::MAX);
+        if self.polling_interval >= floor {
+            return None;
+        }
+
+        let requested = self.polling_interval;
+        self.polling_interval = floor;
+        Some(format!(
+            "Polling interval raised from {}s to this server's minimum of {}s",
+            requested, floor
+        ))
+    }
+
+    /// A note when the threshold direction has no effect in the job's mode
+    pub fn ignored_direction_note(&self) -> Option<String> {
+        let direction = self.threshold_direction.parse().ok()?;
+        self.monitoring_mode.ignored_direction(direction)
+    }
+
+    /// Reject jobs over the server's size limits (0 = unlimited). A course's sections
+    /// are every lecture and discussion it polls.
+    pub fn check_size(&self, max_courses: usize, max_sections_per_course: usize) -> Result<(), String> {
+        if max_courses > 0 && self.courses.len() > max_courses {
+            return Err(format!(
+                "Job has {} courses, more than this server's limit of {} per job",
+                self.courses.len(), max_courses
+            ));
+        }
+
+        if max_sections_per_course > 0 {
+            for course in &self.courses {
+                let sections: usize = course.sections.iter().map(|s| 1 + s.discussions.len()).sum();
+                if sections > max_sections_per_course {
+                    return Err(format!(
+                        "{} {} has {} sections, more than this server's limit of {} per course",
+                        course.department, course.course_code, sections, max_sections_per_course
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Store department and course code in the form WebReg's course lookup expects
+    pub fn normalize(&mut self) {
+        for course in &mut self.courses {
+            course.department = normalize_course_field(&course.department);
+            course.course_code = normalize_course_field(&course.course_code);
+            course.section_pattern = course.section_pattern
+                .as_deref()
+                .map(normalize_course_field)
+                .filter(|pattern| !pattern.is_empty());
+        }
+    }
+
+    /// Merge repeated courses and drop sections listed more than once, so each is
+    /// monitored once. Call after `normalize`. Returns the removed repeats, e.g. "CHEM 6A A01".
+    pub fn dedupe_sections(&mut self) -> Vec<String> {
+        let mut courses: Vec<CourseRequest> = Vec::new();
+        for course in std::mem::take(&mut self.courses) {
+            match courses.iter_mut().find(|c| c.department == course.department && c.course_code == course.course_code) {
+                Some(earlier) => {
+                    earlier.sections.extend(course.sections);
+                    earlier.section_pattern = earlier.section_pattern.take().or(course.section_pattern);
+                    earlier.role = earlier.role.or(course.role);
+                }
+                None => courses.push(course),
+            }
+        }
+
+        let mut removed = Vec::new();
+        for course in &mut courses {
+            for section in dedupe_section_groups(&mut course.sections) {
+                removed.push(format!("{} {} {}", course.department, course.course_code, section));
+            }
+        }
+        self.courses = courses;
+        removed
+    }
+}
+
+impl SectionList for SectionRequest {
+    fn lecture_key(&self) -> &str {
+        self.section_id.as_deref().unwrap_or(&self.lecture)
+    }
+
+    fn discussions_mut(&mut self) -> &mut Vec<String> {
+        &mut self.discussions
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_concurrent_enrolls() -> i32 {
+    1
+}
+
+fn default_cookie_refresh_interval() -> i32 {
+    480  // 8 minutes
+}
+
+fn default_threshold_direction() -> String {
+    "at_most".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CourseRequest {
     pub department: String,
     pub course_code: String,
+    #[serde(default)]
     pub sections: Vec<SectionRequest>,
+    /// Also monitor every listed section matching this, e.g. "A*" or "B0?"; no wildcard means a prefix
+    #[serde(default)]
+    pub section_pattern: Option<String>,
+    /// Enrolling in a `primary` course stops monitoring the job's `backup` courses
+    #[serde(default)]
+    pub role: Option<CourseRole>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SectionRequest {
     pub lecture: String,
     pub discussions: Vec<String>,
+    /// WebReg's numeric id for the lecture, matched instead of the code when set
+    #[serde(default)]
+    pub section_id: Option<String>,
+    /// "enroll_only", "waitlist_only" or "enroll_or_waitlist"; unset follows the job's mode
+    #[serde(default)]
+    pub enroll_target: Option<EnrollTarget>,
+    /// Enroll the lecture and a discussion together, or neither
+    #[serde(default)]
+    pub atomic: bool,
+    /// Stop trying the other discussions once one is enrolled or waitlisted
+    #[serde(default)]
+    pub one_discussion: bool,
+    /// Never enroll the lecture alone; it's only taken through one of the discussions
+    #[serde(default)]
+    pub discussion_required: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateNotificationRequest {
     pub gmail_address: Option<String>,
+    /// Left out keeps the stored password; an empty string clears it
+    #[serde(default)]
     pub gmail_app_password: Option<String>,
     pub email_recipients: Vec<String>,
     pub discord_webhook_url: Option<String>,
+    #[serde(default)]
+    pub success_webhook_url: Option<String>,
+    /// Separate destination for cookie-expiry alerts; opening alerts keep the channels above
+    #[serde(default)]
+    pub alert_webhook_url: Option<String>,
+    #[serde(default)]
+    pub alert_email_recipients: Vec<String>,
+    /// Also notify when a section reported open closes again
+    #[serde(default)]
+    pub notify_on_close: bool,
+    /// Alert when a job's WebReg session works again after expiring
+    #[serde(default = "default_true")]
+    pub notify_on_recovery: bool,
+    /// "plain" text or color-coded "embed" messages on the Discord webhooks; left out keeps the stored format
+    #[serde(default)]
+    pub discord_format: Option<DiscordFormat>,
+    /// Pushover settings left out are kept as they are; an empty string clears them
+    #[serde(default)]
+    pub pushover_user_key: Option<String>,
+    #[serde(default)]
+    pub pushover_api_token: Option<String>,
+}
+
+/// Filters and ordering for `GET /api/jobs`
+#[derive(Debug, Default, Deserialize)]
+pub struct JobListQuery {
+    pub is_active: Option<bool>,
+    pub is_connected: Option<bool>,
+    pub term: Option<String>,
+    #[serde(default)]
+    pub sort: JobSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// Sortable job columns; only these names are accepted from the query string
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobSort {
+    #[default]
+    CreatedAt,
+    LastCheckTime,
+}
+
+impl JobSort {
+    pub fn column(self) -> &'static str {
+        match self {
+            JobSort::CreatedAt => "created_at",
+            JobSort::LastCheckTime => "last_check_time",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Body of `PUT /api/jobs/:job_id/ignore_threshold`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThresholdOverrideRequest {
+    pub ignore_threshold: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DropSectionRequest {
+    pub section_id: String,
+    #[serde(default)]
+    pub waitlisted: bool,
+}
+
+/// A user's saved defaults for job fields, from `GET /api/preferences`; unset fields have no default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobDefaults {
+    #[serde(default)]
+    pub polling_interval: Option<i32>,
+    #[serde(default)]
+    pub seat_threshold: Option<i32>,
+    #[serde(default)]
+    pub monitoring_mode: Option<MonitoringMode>,
+}
+
+impl JobDefaults {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.polling_interval.is_some_and(|interval| interval < 1) {
+            return Err("Default polling interval must be at least 1 second".to_string());
+        }
+        if self.seat_threshold.is_some_and(|threshold| threshold < 0) {
+            return Err("Default seat threshold can't be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// Parse a create-job body, taking any field it omits (or sends as null) from these defaults
+    pub fn request_from(&self, mut body: serde_json::Value) -> Result<CreateJobRequest, String> {
+        if let (Some(fields), serde_json::Value::Object(defaults)) = (body.as_object_mut(), serde_json::json!(self)) {
+            for (key, value) in defaults.into_iter().filter(|(_, value)| !value.is_null()) {
+                match fields.get(&key) {
+                    Some(given) if !given.is_null() => {}
+                    _ => {
+                        fields.insert(key, value);
+                    }
+                }
+            }
+        }
+        serde_json::from_value(body).map_err(|e| format!("Invalid job: {}", e))
+    }
+}
+
+impl From<UserPreferences> for JobDefaults {
+    fn from(preferences: UserPreferences) -> Self {
+        Self {
+            polling_interval: preferences.polling_interval,
+            seat_threshold: preferences.seat_threshold,
+            monitoring_mode: preferences.monitoring_mode.and_then(|mode| mode.parse().ok()),
+        }
+    }
+}
+
+/// A job's courses and settings without its cookies, from `GET /api/jobs/:job_id/export`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobExport {
+    pub term: String,
+    pub polling_interval: i32,
+    pub seat_threshold: i32,
+    pub monitoring_mode: MonitoringMode,
+    pub courses: Vec<CourseRequest>,
+    #[serde(default = "default_true")]
+    pub enroll: bool,
+    #[serde(default = "default_true")]
+    pub verify_enrollment: bool,
+    #[serde(default)]
+    pub stop_on_first_success: bool,
+    #[serde(default)]
+    pub max_run_duration_hours: Option<i32>,
+    #[serde(default = "default_cookie_refresh_interval")]
+    pub cookie_refresh_interval: i32,
+    #[serde(default = "default_threshold_direction")]
+    pub threshold_direction: String,
+    #[serde(default = "default_max_concurrent_enrolls")]
+    pub max_concurrent_enrolls: i32,
+    #[serde(default)]
+    pub stop_cycle_on_success: bool,
+    #[serde(default)]
+    pub watch_enrolled: bool,
+    #[serde(default = "default_true")]
+    pub poll_immediately: bool,
+    #[serde(default)]
+    pub enrollment_opens_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub force_enroll: bool,
+    #[serde(default)]
+    pub ignore_threshold: bool,
+}
+
+impl From<CreateJobRequest> for JobExport {
+    fn from(request: CreateJobRequest) -> Self {
+        Self {
+            term: request.term,
+            polling_interval: request.polling_interval,
+            seat_threshold: request.seat_threshold,
+            monitoring_mode: request.monitoring_mode,
+            courses: request.courses,
+            enroll: request.enroll,
+            verify_enrollment: request.verify_enrollment,
+            stop_on_first_success: request.stop_on_first_success,
+            max_run_duration_hours: request.max_run_duration_hours,
+            cookie_refresh_interval: request.cookie_refresh_interval,
+            threshold_direction: request.threshold_direction,
+            max_concurrent_enrolls: request.max_concurrent_enrolls,
+            stop_cycle_on_success: request.stop_cycle_on_success,
+            watch_enrolled: request.watch_enrolled,
+            poll_immediately: request.poll_immediately,
+            enrollment_opens_at: request.enrollment_opens_at,
+            force_enroll: request.force_enroll,
+            ignore_threshold: request.ignore_threshold,
+        }
+    }
+}
+
+/// Body of `POST /api/jobs/import`: an exported job with the cookie to run it under
+#[derive(Debug, Deserialize)]
+pub struct ImportJobRequest {
+    pub cookie: String,
+    #[serde(default)]
+    pub backup_cookie: Option<String>,
+    #[serde(flatten)]
+    pub job: JobExport,
+}
+
+impl From<ImportJobRequest> for CreateJobRequest {
+    fn from(import: ImportJobRequest) -> Self {
+        let job = import.job;
+        Self {
+            term: job.term,
+            polling_interval: job.polling_interval,
+            cookie: import.cookie,
+            seat_threshold: job.seat_threshold,
+            monitoring_mode: job.monitoring_mode,
+            courses: job.courses,
+            enroll: job.enroll,
+            verify_enrollment: job.verify_enrollment,
+            stop_on_first_success: job.stop_on_first_success,
+            max_run_duration_hours: job.max_run_duration_hours,
+            cookie_refresh_interval: job.cookie_refresh_interval,
+            threshold_direction: job.threshold_direction,
+            backup_cookie: import.backup_cookie,
+            max_concurrent_enrolls: job.max_concurrent_enrolls,
+            stop_cycle_on_success: job.stop_cycle_on_success,
+            watch_enrolled: job.watch_enrolled,
+            poll_immediately: job.poll_immediately,
+            enrollment_opens_at: job.enrollment_opens_at,
+            force_enroll: job.force_enroll,
+            ignore_threshold: job.ignore_threshold,
+        }
+    }
+}
+
+/// Overrides for `POST /api/jobs/:job_id/clone`; anything unset is copied from the source job
+#[derive(Debug, Default, Deserialize)]
+pub struct CloneJobRequest {
+    #[serde(default)]
+    pub term: Option<String>,
+    #[serde(default)]
+    pub cookie: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ValidateCookieRequest {
+    pub cookie: String,
+    pub term: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CookieValidation {
+    pub valid: bool,
+    pub terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EnrollmentAttempt {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub section: String,
+    pub section_id: String,
+    pub success: bool,
+    pub result: String,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// A notification in the outbox; `payload` is a serialized `notifier::OutboxMessage`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct QueuedNotification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub job_id: Option<Uuid>,
+    pub channel: String,
+    pub payload: sqlx::types::JsonValue,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// An active job as seen by an admin, across all users
+#[derive(Debug, Serialize, FromRow)]
+pub struct AdminJobSummary {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub owner_email: String,
+    pub term: String,
+    pub polling_interval: i32,
+    pub is_active: bool,
+    pub is_connected: bool,
+    pub last_check_time: Option<DateTime<Utc>>,
+}
+
+/// How many jobs this server holds in memory, from `GET /api/admin/metrics`. More resident
+/// jobs than active ones in the database points at stopped jobs that were never removed.
+#[derive(Debug, Serialize)]
+pub struct JobMapMetrics {
+    pub resident_jobs: usize,     // Entries in the running-jobs map
+    pub monitor_loops: usize,     // Monitoring tasks the watchdog is tracking
+    pub active_jobs: i64,         // Jobs marked active in the database, on any server
+    pub busy_jobs: usize,         // Resident jobs mid-cycle, counted at their base size only
+    pub approx_bytes: usize,      // Rough size of the resident jobs' settings and section lists
+}
+
+/// A configured section's current availability and whether the loop would act on it
+#[derive(Debug, Serialize)]
+pub struct SectionPreview {
+    pub department: String,
+    pub course_code: String,
+    pub section: String,
+    /// `None` when the section isn't in WebReg's listing
+    pub available_seats: Option<i64>,
+    pub would_attempt: bool,
+}
+
+/// What one section did in a manually triggered poll cycle
+#[derive(Debug, Serialize)]
+pub struct SectionPollResult {
+    pub department: String,
+    pub course_code: String,
+    pub section: String,
+    pub opening: bool,  // Met the job's criteria and survived the recheck
+    /// Set when the section couldn't be checked
+    pub error: Option<String>,
+    /// `enrolled`, `waitlisted`, `rejected` or WebReg's error, when an add was attempted
+    pub enroll_result: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -113,6 +668,20 @@ pub struct JobResponse {
     pub polling_interval: i32,
     pub seat_threshold: i32,
     pub monitoring_mode: String,
+    pub threshold_direction: String,
+    pub has_backup_cookie: bool,
+    pub enroll: bool,
+    pub stop_on_first_success: bool,
+    pub max_concurrent_enrolls: i32,
+    pub stop_cycle_on_success: bool,
+    pub watch_enrolled: bool,
+    pub held_sections: Vec<HeldSection>,
+    pub poll_immediately: bool,
+    pub enrollment_opens_at: Option<DateTime<Utc>>,
+    pub force_enroll: bool,
+    pub ignore_threshold: bool,
+    pub max_run_duration_hours: Option<i32>,
+    pub cookie_refresh_interval: i32,
     pub is_active: bool,
     pub is_connected: bool,
     pub last_check_time: Option<DateTime<Utc>>,
@@ -126,6 +695,8 @@ pub struct CourseResponse {
     pub department: String,
     pub course_code: String,
     pub sections: Vec<SectionResponse>,
+    pub section_pattern: Option<String>,
+    pub role: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -133,6 +704,11 @@ pub struct SectionResponse {
     pub id: Uuid,
     pub lecture: String,
     pub discussions: Vec<String>,
+    pub section_id: Option<String>,
+    pub enroll_target: Option<String>,
+    pub atomic: bool,
+    pub one_discussion: bool,
+    pub discussion_required: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -146,3 +722,240 @@ pub struct EnrollmentStatsResponse {
     pub start_time: DateTime<Utc>,
     pub last_updated: DateTime<Utc>,
 }
+
+/// Notification settings as shown to the user: secrets are reported as set or not, never echoed
+#[derive(Debug, Serialize)]
+pub struct NotificationSettingsResponse {
+    pub gmail_address: Option<String>,
+    pub has_gmail_password: bool,
+    pub email_recipients: Vec<String>,
+    pub discord_webhook_url: Option<String>,
+    pub success_webhook_url: Option<String>,
+    pub alert_webhook_url: Option<String>,
+    pub alert_email_recipients: Vec<String>,
+    pub notify_on_close: bool,
+    pub notify_on_recovery: bool,
+    pub discord_format: String,
+    pub pushover_user_key: Option<String>,
+    pub has_pushover_token: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<NotificationSettings> for NotificationSettingsResponse {
+    fn from(settings: NotificationSettings) -> Self {
+        Self {
+            gmail_address: settings.gmail_address,
+            has_gmail_password: settings.gmail_app_password_encrypted.is_some_and(|p| !p.is_empty()),
+            email_recipients: serde_json::from_value(settings.email_recipients).unwrap_or_default(),
+            discord_webhook_url: settings.discord_webhook_url,
+            success_webhook_url: settings.success_webhook_url,
+            alert_webhook_url: settings.alert_webhook_url,
+            alert_email_recipients: serde_json::from_value(settings.alert_email_recipients).unwrap_or_default(),
+            notify_on_close: settings.notify_on_close,
+            notify_on_recovery: settings.notify_on_recovery,
+            discord_format: settings.discord_format,
+            pushover_user_key: settings.pushover_user_key,
+            has_pushover_token: settings.pushover_api_token_encrypted.or(settings.pushover_api_token).is_some_and(|t| !t.is_empty()),
+            updated_at: settings.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_secrets_are_never_serialized() {
+        let settings = NotificationSettings {
+            id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            gmail_address: Some("me@gmail.com".to_string()),
+            gmail_app_password_encrypted: Some("secret-ciphertext".to_string()),
+            gmail_encryption_nonce: Some("secret-nonce".to_string()),
+            email_recipients: serde_json::json!([]),
+            discord_webhook_url: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            success_webhook_url: None,
+            alert_webhook_url: None,
+            alert_email_recipients: serde_json::json!([]),
+            notify_on_close: false,
+            pushover_user_key: None,
+            pushover_api_token: None,
+            notify_on_recovery: true,
+            discord_format: "embed".to_string(),
+            pushover_api_token_encrypted: Some("secret-token-ciphertext".to_string()),
+            pushover_encryption_nonce: Some("secret-token-nonce".to_string()),
+        };
+
+        let row = serde_json::to_string(&settings).unwrap();
+        let response = serde_json::to_value(NotificationSettingsResponse::from(settings)).unwrap();
+        assert!(!row.contains("secret-"), "row leaked a secret: {}", row);
+        assert!(!response.to_string().contains("secret-"), "response leaked a secret: {}", response);
+        assert_eq!(response["has_gmail_password"], true);
+        assert_eq!(response["has_pushover_token"], true);
+    }
+
+    #[test]
+    fn test_notification_update_leaves_out_what_the_form_omits() {
+        // The dashboard form sends only these fields
+        let request: UpdateNotificationRequest = serde_json::from_value(serde_json::json!({
+            "gmail_address": "me@gmail.com",
+            "gmail_app_password": null,
+            "email_recipients": ["me@gmail.com"],
+            "discord_webhook_url": null,
+        })).unwrap();
+        assert_eq!(request.discord_format, None);
+        assert_eq!(request.gmail_app_password, None);
+        assert_eq!(request.pushover_api_token, None);
+
+        let request: UpdateNotificationRequest = serde_json::from_value(serde_json::json!({
+            "email_recipients": [],
+            "discord_format": "embed",
+        })).unwrap();
+        assert_eq!(request.discord_format, Some(DiscordFormat::Embed));
+    }
+
+    fn section(lecture: &str, discussions: &[&str]) -> SectionRequest {
+        SectionRequest {
+            lecture: lecture.to_string(),
+            discussions: discussions.iter().map(|d| d.to_string()).collect(),
+            section_id: None,
+            enroll_target: None,
+            atomic: false,
+            one_discussion: false,
+            discussion_required: false,
+        }
+    }
+
+    fn job_request(courses: Vec<CourseRequest>) -> CreateJobRequest {
+        CreateJobRequest {
+            term: "FA25".to_string(),
+            polling_interval: 30,
+            cookie: String::new(),
+            seat_threshold: 0,
+            monitoring_mode: MonitoringMode::Available,
+            courses,
+            enroll: true,
+            verify_enrollment: true,
+            stop_on_first_success: false,
+            max_run_duration_hours: None,
+            cookie_refresh_interval: 480,
+            threshold_direction: "at_most".to_string(),
+            backup_cookie: None,
+            max_concurrent_enrolls: 1,
+            stop_cycle_on_success: false,
+            watch_enrolled: false,
+            poll_immediately: true,
+            enrollment_opens_at: None,
+            force_enroll: false,
+            ignore_threshold: false,
+        }
+    }
+
+    #[test]
+    fn test_job_size_limits() {
+        let request = job_request(vec![CourseRequest {
+            department: "CSE".to_string(),
+            course_code: "100".to_string(),
+            section_pattern: None,
+            role: None,
+            sections: (0..100).map(|i| {
+                let lecture = format!("A{}", i);
+                section(&lecture, &[format!("{}01", lecture).as_str()])
+            }).collect(),
+        }]);
+
+        let err = request.check_size(10, 25).unwrap_err();
+        assert!(err.contains("200 sections") && err.contains("limit of 25"), "{}", err);
+        assert!(request.check_size(0, 0).is_ok());
+        assert!(request.check_size(1, 200).is_ok());
+    }
+
+    #[test]
+    fn test_repeated_sections_are_monitored_once() {
+        let course = |sections| CourseRequest { department: "CHEM".to_string(), course_code: "6A".to_string(), sections, section_pattern: None, role: None };
+        let mut request = job_request(vec![
+            course(vec![section("A00", &["A01", "A02"]), section("B00", &["A01", "B01"])]),
+            course(vec![section("A00", &["A02", "A03"])]),
+        ]);
+
+        let mut removed = request.dedupe_sections();
+        removed.sort();
+        assert_eq!(removed, ["CHEM 6A A00", "CHEM 6A A01", "CHEM 6A A02"]);
+
+        assert_eq!(request.courses.len(), 1);
+        let groups: Vec<_> = request.courses[0].sections.iter().map(|s| (s.lecture.as_str(), s.discussions.clone())).collect();
+        assert_eq!(groups, [("A00", vec!["A01".to_string(), "A02".to_string(), "A03".to_string()]), ("B00", vec!["B01".to_string()])]);
+    }
+
+    #[test]
+    fn test_exported_job_imports_unchanged() {
+        let mut original = job_request(vec![CourseRequest {
+            department: "CSE".to_string(),
+            course_code: "100".to_string(),
+            section_pattern: Some("A*".to_string()),
+            role: Some(CourseRole::Backup),
+            sections: vec![SectionRequest { enroll_target: Some(EnrollTarget::WaitlistOnly), atomic: true, ..section("A00", &["A01"]) }],
+        }]);
+        original.cookie = "secret-cookie".to_string();
+        original.backup_cookie = Some("secret-backup".to_string());
+        original.polling_interval = 45;
+        original.stop_cycle_on_success = true;
+
+        let exported = serde_json::to_value(JobExport::from(original)).unwrap();
+        assert!(!exported.to_string().contains("secret-"), "export leaked a cookie: {}", exported);
+
+        let mut body = exported.clone();
+        body["cookie"] = serde_json::json!("new-cookie");
+        let imported = CreateJobRequest::from(serde_json::from_value::<ImportJobRequest>(body).unwrap());
+        assert_eq!(imported.cookie, "new-cookie");
+        assert_eq!(serde_json::to_value(JobExport::from(imported)).unwrap(), exported);
+    }
+
+    #[test]
+    fn test_unknown_monitoring_mode_is_rejected() {
+        let body = |mode: &str| serde_json::json!({
+            "term": "FA25",
+            "polling_interval": 30,
+            "cookie": "cookie",
+            "seat_threshold": 0,
+            "monitoring_mode": mode,
+            "courses": []
+        });
+
+        let err = serde_json::from_value::<CreateJobRequest>(body("banana")).unwrap_err();
+        assert!(err.to_string().contains("Unknown monitoring mode 'banana'"), "{}", err);
+
+        // The dashboard still sends the older capitalized names
+        let request: CreateJobRequest = serde_json::from_value(body("Include")).unwrap();
+        assert_eq!(request.monitoring_mode, MonitoringMode::Available);
+    }
+
+    #[test]
+    fn test_saved_defaults_fill_omitted_job_fields() {
+        let defaults = JobDefaults {
+            polling_interval: Some(45),
+            seat_threshold: None,
+            monitoring_mode: Some(MonitoringMode::WaitlistOnly),
+        };
+        let body = serde_json::json!({
+            "term": "FA25",
+            "cookie": "cookie",
+            "seat_threshold": 2,
+            "monitoring_mode": "available",
+            "courses": []
+        });
+
+        let request = defaults.request_from(body.clone()).unwrap();
+        assert_eq!(request.polling_interval, 45);
+        // Fields the request does give win over the defaults
+        assert_eq!(request.monitoring_mode, MonitoringMode::Available);
+        assert_eq!(request.seat_threshold, 2);
+
+        // Without a saved default, a missing field is still an error
+        let err = JobDefaults::default().request_from(body).unwrap_err();
+        assert!(err.contains("polling_interval"), "{}", err);
+    }
+}
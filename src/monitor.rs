@@ -1,26 +1,354 @@
+use std::collections::HashMap;
 use std::error::Error as StdError;
 use std::fs::OpenOptions;
 use std::io::Write;
-use webweg::wrapper::WebRegWrapper;
-use chrono::Local;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use log::{info, warn};
-use crate::notifier::Notifier;
-use crate::utils::get_retry_strategy;
+use crate::config::{BackoffThresholds, EnrollTarget, MonitoringMode, ThresholdDirection, SECTION_DETAILS_LOG};
+use crate::notifier::{CourseNotice, NoticeKind, Notifier, Priority};
+use crate::stats::LatencyTracker;
+use crate::utils::{budgeted_retry_strategy, data_path, local_now, RetryBudget, RetryPolicy};
+use crate::webreg::{back_off_if_throttled, is_throttled, SectionAvailability, ThrottleBackoff, WebRegClient};
 
+/// Outcome of evaluating a section's availability against the monitoring mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Criteria met; enroll (or waitlist in waitlist-only mode)
+    Attempt,
+    /// No open seats
+    Full,
+    /// Seats are open but above an at-most threshold
+    AboveThreshold,
+    /// Seats are open but below an at-least threshold
+    BelowThreshold,
+    /// Waitlist-only mode, but the section can be joined directly
+    NoWaitlistNeeded,
+}
+
+impl Decision {
+    pub fn is_attempt(self) -> bool {
+        self == Decision::Attempt
+    }
+}
+
+/// Decide whether a section's current availability satisfies the monitoring mode.
+/// Pure so the loop, the recheck and the preview endpoint all agree. `seat_threshold`
+/// and `direction` only matter in `LowSeats`; the other modes ignore them.
+pub fn should_attempt_enrollment(
+    available_seats: i64,
+    waitlist_ct: i64,
+    seat_threshold: i64,
+    direction: ThresholdDirection,
+    mode: MonitoringMode,
+) -> Decision {
+    match mode {
+        MonitoringMode::Available if available_seats > 0 => Decision::Attempt,
+        // A threshold of 0 means any availability
+        MonitoringMode::LowSeats if available_seats > 0 => match direction {
+            _ if seat_threshold == 0 => Decision::Attempt,
+            ThresholdDirection::AtMost if available_seats > seat_threshold => Decision::AboveThreshold,
+            ThresholdDirection::AtLeast if available_seats < seat_threshold => Decision::BelowThreshold,
+            _ => Decision::Attempt,
+        },
+        MonitoringMode::Available | MonitoringMode::LowSeats => Decision::Full,
+        // Open seats held for people already waiting still mean joining the waitlist
+        MonitoringMode::WaitlistOnly if available_seats <= 0 || waitlist_ct > 0 => Decision::Attempt,
+        MonitoringMode::WaitlistOnly => Decision::NoWaitlistNeeded,
+    }
+}
+
+/// `should_attempt_enrollment` for a section with its own enroll/waitlist target.
+/// A job's default target gives the same answer as its mode alone.
+pub fn should_attempt_for_target(
+    available_seats: i64,
+    waitlist_ct: i64,
+    seat_threshold: i64,
+    direction: ThresholdDirection,
+    mode: MonitoringMode,
+    target: EnrollTarget,
+) -> Decision {
+    match target {
+        EnrollTarget::WaitlistOnly => should_attempt_enrollment(
+            available_seats, waitlist_ct, seat_threshold, direction, MonitoringMode::WaitlistOnly,
+        ),
+        EnrollTarget::EnrollOnly => should_attempt_enrollment(
+            available_seats, waitlist_ct, seat_threshold, direction, mode.seat_mode(),
+        ),
+        // A full section is still worth an attempt, which falls back to the waitlist
+        EnrollTarget::EnrollOrWaitlist => match should_attempt_enrollment(
+            available_seats, waitlist_ct, seat_threshold, direction, mode.seat_mode(),
+        ) {
+            Decision::Full => Decision::Attempt,
+            decision => decision,
+        },
+    }
+}
+
+/// Consecutive polls each section has looked open, so a seat that flickers for a
+/// single poll never triggers an enrollment. Cloning shares the same counts.
+#[derive(Debug, Clone)]
+pub struct OpeningFilter {
+    required: u32,
+    streaks: Arc<Mutex<HashMap<String, u32>>>,
+    last_seats: Arc<Mutex<HashMap<String, i64>>>,  // Available seats each section's last poll saw
+}
+
+impl Default for OpeningFilter {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl OpeningFilter {
+    pub fn new(required: u32) -> Self {
+        Self {
+            required: required.max(1),
+            streaks: Arc::default(),
+            last_seats: Arc::default(),
+        }
+    }
+
+    /// The same counts with a different requirement, for when the config changes
+    pub fn with_required(&self, required: u32) -> Self {
+        Self {
+            required: required.max(1),
+            streaks: Arc::clone(&self.streaks),
+            last_seats: Arc::clone(&self.last_seats),
+        }
+    }
+
+    /// Remember a section's available seats, returning what the previous poll saw
+    pub fn record_seats(&self, key: &str, available_seats: i64) -> Option<i64> {
+        let mut last_seats = self.last_seats.lock().unwrap_or_else(|e| e.into_inner());
+        last_seats.insert(key.to_string(), available_seats)
+    }
+
+    /// Record one poll of a section and what it means given the polls before it
+    pub fn observe(&self, key: &str, open: bool) -> Transition {
+        let mut streaks = self.streaks.lock().unwrap_or_else(|e| e.into_inner());
+        if !open {
+            // Only the first closed poll after a reported opening counts as closing again
+            return match streaks.remove(key) {
+                Some(streak) if streak >= self.required => Transition::ClosedAgain,
+                _ => Transition::Waiting,
+            };
+        }
+
+        let streak = streaks.entry(key.to_string()).or_insert(0);
+        *streak += 1;
+        if *streak < self.required {
+            info!("Section {} open for {}/{} consecutive polls, waiting before enrolling",
+                key, streak, self.required);
+            return Transition::Waiting;
+        }
+        Transition::Open
+    }
+}
+
+/// What one poll of a section means, as judged by `OpeningFilter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Open for enough polls in a row to act on
+    Open,
+    /// Closed, or open but not yet for enough polls
+    Waiting,
+    /// Closed after having been reported open
+    ClosedAgain,
+}
+
+impl Transition {
+    pub fn is_open(self) -> bool {
+        self == Transition::Open
+    }
+}
+
+/// Whether a section code matches a `section_pattern`, ignoring case. `*` matches any
+/// run of characters and `?` exactly one; a pattern without either is a prefix.
+pub fn section_matches(pattern: &str, code: &str) -> bool {
+    fn matches(pattern: &[char], code: &[char]) -> bool {
+        match (pattern.first(), code.first()) {
+            (None, None) => true,
+            (Some('*'), _) => matches(&pattern[1..], code) || (!code.is_empty() && matches(pattern, &code[1..])),
+            (Some('?'), Some(_)) => matches(&pattern[1..], &code[1..]),
+            (Some(p), Some(c)) => p.eq_ignore_ascii_case(c) && matches(&pattern[1..], &code[1..]),
+            _ => false,
+        }
+    }
+
+    let mut pattern: Vec<char> = pattern.trim().chars().collect();
+    if !pattern.iter().any(|c| *c == '*' || *c == '?') {
+        pattern.push('*');
+    }
+    let code: Vec<char> = code.trim().chars().collect();
+    matches(&pattern, &code)
+}
+
+/// Cycles each section still has to sit out, so hopelessly full sections poll
+/// slowly while ones near capacity poll every cycle. Cloning shares the waits.
+#[derive(Debug, Clone, Default)]
+pub struct PollBackoff {
+    thresholds: BackoffThresholds,
+    waits: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl PollBackoff {
+    pub fn new(thresholds: BackoffThresholds) -> Self {
+        Self { thresholds, waits: Arc::default() }
+    }
+
+    /// Whether a section is polled this cycle; a skipped cycle counts down its wait
+    pub fn is_due(&self, key: &str) -> bool {
+        let mut waits = self.waits.lock().unwrap_or_else(|e| e.into_inner());
+        match waits.get_mut(key) {
+            Some(wait) if *wait > 0 => {
+                *wait -= 1;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Set a section's wait from the demand it was just seen at
+    pub fn observe(&self, key: &str, demand: f64) {
+        let mut waits = self.waits.lock().unwrap_or_else(|e| e.into_inner());
+        match self.thresholds.skip_for(demand) {
+            0 => { waits.remove(key); }
+            skip => { waits.insert(key.to_string(), skip); }
+        }
+    }
+}
+
+/// One fetch of a course's sections, shared by all of its configured sections in a cycle
+pub struct CourseListing {
+    sections: Vec<SectionAvailability>,
+    fetched_at: Instant,
+}
+
+impl CourseListing {
+    pub fn sections(&self) -> &[SectionAvailability] {
+        &self.sections
+    }
+
+    /// (enrolled + waitlisted) / seats, or None when the section isn't listed or has no seats
+    pub fn demand(&self, section: &str) -> Option<f64> {
+        self.sections
+            .iter()
+            .find(|info| info.section_code == section || info.section_id == section)
+            .filter(|info| info.total_seats > 0)
+            .map(|info| (info.enrolled_ct + info.waitlist_ct) as f64 / info.total_seats as f64)
+    }
+}
+
+/// Fetch a course's listing once per cycle, with the same retry and throttle
+/// handling as a section check
+pub async fn fetch_course_listing(
+    client: &impl WebRegClient,
+    term: &str,
+    department: &str,
+    course_code: &str,
+    notifier: &Notifier,
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    retry: RetryPolicy,
+    budget: &RetryBudget,
+) -> Result<CourseListing, Box<dyn StdError + Send + Sync>> {
+    if let Some(remaining) = throttle.cooldown_remaining() {
+        return Err(format!("WebReg throttle cooldown, {}s left", remaining.as_secs()).into());
+    }
+
+    let retry_strategy = budgeted_retry_strategy(retry, budget);
+    let result = tokio_retry::RetryIf::spawn(retry_strategy, || async {
+        let started = Instant::now();
+        let sections = client.get_course_info(term, department, course_code).await;
+        latency.record(started.elapsed());
+        sections.map(|sections| (sections, started)).map_err(|e| {
+            warn!("Error fetching {} {}: {:?}, retrying...", department, course_code, e);
+            e
+        })
+    }, |e: &Box<dyn StdError + Send + Sync>| !is_throttled(e.as_ref())).await;
+
+    match result {
+        Ok((sections, fetched_at)) => {
+            throttle.record_success();
+            Ok(CourseListing { sections, fetched_at })
+        }
+        Err(e) => {
+            back_off_if_throttled(e.as_ref(), throttle, notifier).await;
+            Err(e)
+        }
+    }
+}
+
+/// A section that qualified, with the seats it had when confirmed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opening {
+    pub section_id: String,
+    pub available_seats: i64,
+    pub total_seats: i64,
+}
+
+/// What one check of a section found: an opening if it qualified, and the seats the
+/// latest data showed either way (None when the section wasn't listed)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionCheck {
+    pub opening: Option<Opening>,
+    pub available_seats: Option<i64>,
+}
+
+impl Opening {
+    /// The seat line of an opening notification, with the change since `previous_seats`
+    fn seat_summary(&self, previous_seats: Option<i64>) -> String {
+        if self.available_seats <= 0 {
+            return format!("No open seats ({} total); the waitlist is open.", self.total_seats);
+        }
+
+        let seats = match self.available_seats {
+            1 => "1 seat".to_string(),
+            n => format!("{} seats", n),
+        };
+        match previous_seats {
+            Some(previous) => format!("{} now available (was {} a moment ago), {} total.", seats, previous, self.total_seats),
+            None => format!("{} now available, {} total.", seats, self.total_seats),
+        }
+    }
+}
+
+/// Check one section, from `listing` when the course was already fetched this cycle.
+/// A confirming recheck always goes to WebReg.
 pub async fn monitor_section(
-    wrapper: &WebRegWrapper,
+    client: &impl WebRegClient,
+    listing: Option<&CourseListing>,
     term: &str,
     section: &str,
     department: &str,
     course_code: &str,
     polling_interval: u64,
     seat_threshold: i64,
-) -> Result<Option<String>, Box<dyn StdError + Send + Sync>> {
-    let course_info = wrapper.req(term).parsed().get_course_info(department, course_code).await?;
+    threshold_direction: ThresholdDirection,
+    mode: MonitoringMode,
+    target: EnrollTarget,
+    recheck_freshness: Duration,
+    latency: &LatencyTracker,
+) -> Result<SectionCheck, Box<dyn StdError + Send + Sync>> {
+    let fetched;
+    let (course_info, fetched_at) = match listing {
+        Some(listing) => (listing.sections.as_slice(), listing.fetched_at),
+        None => {
+            // Age is measured from the request, since WebReg may answer with data from any point during it
+            let started = Instant::now();
+            fetched = client.get_course_info(term, department, course_code).await?;
+            latency.record(started.elapsed());
+            (fetched.as_slice(), started)
+        }
+    };
 
+    let mut available_seats = None;
     for section_info in course_info {
-        if section_info.section_code == section {
-            let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S.%f").to_string();
+        // A section can be named by its code ("A01") or by WebReg's numeric section id
+        if section_info.section_code == section || section_info.section_id == section {
+            available_seats = Some(section_info.available_seats);
+            let timestamp = local_now().format("%Y-%m-%d %H:%M:%S.%f").to_string();
             let details = format!(
                 "[{}] {} {} Section {} Details:\n\
                 Section ID: {}\n\
@@ -29,7 +357,7 @@ pub async fn monitor_section(
                 Total Seats: {}\n\
                 Enrolled Count: {}\n\
                 Waitlist Count: {}\n\
-                Raw API Response: {:#?}\n\
+                Raw API Response: {}\n\
                 -------------------\n",
                 timestamp,
                 department,
@@ -41,35 +369,54 @@ pub async fn monitor_section(
                 section_info.total_seats,
                 section_info.enrolled_ct,
                 section_info.waitlist_ct,
-                section_info  // Log the complete raw response
+                section_info.raw  // Log the complete raw response
             );
 
             let mut file = OpenOptions::new()
                 .create(true)
                 .append(true)
-                .open("section_details.log")?;
+                .open(data_path(SECTION_DETAILS_LOG))?;
 
             writeln!(file, "{}", details)?;
 
-            // Determine if we should attempt enrollment based on threshold
-            // threshold = 0: Any availability (available_seats > 0)
-            // threshold > 0: Seats available AND within threshold (0 < available_seats <= threshold)
-            let has_availability = section_info.available_seats > 0;
-            let within_threshold = seat_threshold == 0 || section_info.available_seats <= seat_threshold;
-            let should_attempt = has_availability && within_threshold;
+            let should_attempt = should_attempt_for_target(
+                section_info.available_seats,
+                section_info.waitlist_ct,
+                seat_threshold,
+                threshold_direction,
+                mode,
+                target,
+            ).is_attempt();
 
             if should_attempt {
+                // Data this fresh is trusted as-is, trading a small false-positive risk for one fewer fetch
+                if fetched_at.elapsed() < recheck_freshness {
+                    info!("🎯 Section {} has {} seats available (recheck skipped, data {}ms old)",
+                        section, section_info.available_seats, fetched_at.elapsed().as_millis());
+                    return Ok(SectionCheck {
+                        opening: Some(Opening {
+                            section_id: section_info.section_id.clone(),
+                            available_seats: section_info.available_seats,
+                            total_seats: section_info.total_seats,
+                        }),
+                        available_seats,
+                    });
+                }
+
                 // Double-check the section immediately before returning
-                let recheck = wrapper.req(term).parsed().get_course_info(department, course_code).await?;
+                let started = Instant::now();
+                let recheck = client.get_course_info(term, department, course_code).await?;
+                latency.record(started.elapsed());
                 for recheck_info in recheck {
-                    if recheck_info.section_code == section {
+                    if recheck_info.section_code == section || recheck_info.section_id == section {
+                        available_seats = Some(recheck_info.available_seats);
                         // Log the recheck
                         let recheck_details = format!(
                             "[{}] RECHECK {} {} Section {}:\n\
                             Available Seats: {} -> {}\n\
                             Enrolled Count: {} -> {}\n\
                             -------------------\n",
-                            Local::now().format("%Y-%m-%d %H:%M:%S.%f"),
+                            local_now().format("%Y-%m-%d %H:%M:%S.%f"),
                             department,
                             course_code,
                             section,
@@ -81,24 +428,41 @@ pub async fn monitor_section(
                         writeln!(file, "{}", recheck_details)?;
 
                         // Recheck with same logic
-                        let recheck_has_availability = recheck_info.available_seats > 0;
-                        let recheck_within_threshold = seat_threshold == 0 || recheck_info.available_seats <= seat_threshold;
-                        let recheck_should_attempt = recheck_has_availability && recheck_within_threshold;
+                        let recheck_should_attempt = should_attempt_for_target(
+                            recheck_info.available_seats,
+                            recheck_info.waitlist_ct,
+                            seat_threshold,
+                            threshold_direction,
+                            mode,
+                            target,
+                        ).is_attempt();
 
                         // Only proceed if both checks show availability
                         if recheck_should_attempt {
-                            let threshold_msg = if seat_threshold == 0 {
-                                "Found opening!".to_string()
-                            } else {
-                                format!("Seats are at or below threshold ({})!", seat_threshold)
+                            let threshold_msg = match target {
+                                EnrollTarget::WaitlistOnly => "Section is full, waitlist is open!".to_string(),
+                                EnrollTarget::EnrollOrWaitlist if recheck_info.available_seats <= 0 =>
+                                    "Section is full, joining the waitlist!".to_string(),
+                                _ if seat_threshold == 0 => "Found opening!".to_string(),
+                                _ => match threshold_direction {
+                                    ThresholdDirection::AtMost => format!("Seats are at or below threshold ({})!", seat_threshold),
+                                    ThresholdDirection::AtLeast => format!("Seats are at or above threshold ({})!", seat_threshold),
+                                },
                             };
                             info!("🎯 {} Section {} has {} seats available (verified)",
                                 threshold_msg, section, recheck_info.available_seats);
-                            return Ok(Some(section_info.section_id.clone()));
+                            return Ok(SectionCheck {
+                                opening: Some(Opening {
+                                    section_id: section_info.section_id.clone(),
+                                    available_seats: recheck_info.available_seats,
+                                    total_seats: recheck_info.total_seats,
+                                }),
+                                available_seats,
+                            });
                         } else {
                             info!("⚠️  False positive: Section {} showed availability but recheck failed",
                                 section);
-                            return Ok(None);
+                            return Ok(SectionCheck { opening: None, available_seats });
                         }
                     }
                 }
@@ -115,38 +479,372 @@ pub async fn monitor_section(
         }
     }
 
-    Ok(None)
+    Ok(SectionCheck { opening: None, available_seats })
 }
 
 pub async fn monitor_section_with_retry(
-    wrapper: &WebRegWrapper,
+    client: &impl WebRegClient,
+    listing: Option<&CourseListing>,
     term: &str,
     section: &str,
     department: &str,
     course_code: &str,
     polling_interval: u64,
     seat_threshold: i64,
+    threshold_direction: ThresholdDirection,
+    mode: MonitoringMode,
+    target: EnrollTarget,
+    recheck_freshness: Duration,
+    will_enroll: bool,
     notifier: &Notifier,
-) -> Result<Option<String>, Box<dyn StdError + Send + Sync>> {
-    let retry_strategy = get_retry_strategy();
+    latency: &LatencyTracker,
+    throttle: &ThrottleBackoff,
+    openings: &OpeningFilter,
+    retry: RetryPolicy,
+    budget: &RetryBudget,
+) -> Result<Option<Opening>, Box<dyn StdError + Send + Sync>> {
+    if let Some(remaining) = throttle.cooldown_remaining() {
+        return Err(format!("WebReg throttle cooldown, {}s left", remaining.as_secs()).into());
+    }
+
+    let retry_strategy = budgeted_retry_strategy(retry, budget);
 
-    let result = tokio_retry::Retry::spawn(retry_strategy, || async {
-        match monitor_section(wrapper, term, section, department, course_code, polling_interval, seat_threshold).await {
+    // Retrying into a throttle only prolongs it, so that error ends the attempt at once
+    let result = tokio_retry::RetryIf::spawn(retry_strategy, || async {
+        match monitor_section(client, listing, term, section, department, course_code, polling_interval, seat_threshold, threshold_direction, mode, target, recheck_freshness, latency).await {
             Ok(result) => Ok(result),
             Err(e) => {
                 warn!("Error monitoring section {}: {:?}, retrying...", section, e);
                 Err(e)
             }
         }
-    }).await?;
+    }, |e: &Box<dyn StdError + Send + Sync>| !is_throttled(e.as_ref())).await;
 
-    if let Some(_section_id) = &result {
+    let previous_seats;
+    let result = match result {
+        Ok(SectionCheck { opening, available_seats }) => {
+            throttle.record_success();
+            let key = format!("{} {} {}", department, course_code, section);
+            previous_seats = available_seats.and_then(|seats| openings.record_seats(&key, seats));
+            match openings.observe(&key, opening.is_some()) {
+                Transition::Open => opening,
+                Transition::Waiting => None,
+                Transition::ClosedAgain => {
+                    info!("Section {} closed again", key);
+                    if notifier.notifies_on_close() {
+                        let msg = format!(
+                            "{} {} section {} closed again; the opening reported earlier is gone.\n\nTime: {}",
+                            department, course_code, section, local_now().format("%Y-%m-%d %H:%M:%S")
+                        );
+                        let course = CourseNotice { department, course_code, section, seats: None };
+                        notifier.send_course_notification(&msg, Priority::Normal, NoticeKind::Info, &course).await;
+                    }
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            back_off_if_throttled(e.as_ref(), throttle, notifier).await;
+            return Err(e);
+        }
+    };
+
+    if let Some(opening) = &result {
+        let next_step = if will_enroll {
+            "Attempting enrollment..."
+        } else {
+            "Monitor-only mode: enroll manually on WebReg."
+        };
         let msg = format!(
-            "Found opening in {} {} section {}!\n\nAttempting enrollment...\nTime: {}",
-            department, course_code, section, Local::now().format("%Y-%m-%d %H:%M:%S")
+            "Found opening in {} {} section {}!\n{}\n\n{}\nTime: {}",
+            department, course_code, section, opening.seat_summary(previous_seats),
+            next_step, local_now().format("%Y-%m-%d %H:%M:%S")
         );
-        notifier.send_notification(&msg).await;
+        let course = CourseNotice {
+            department,
+            course_code,
+            section,
+            seats: Some((opening.available_seats, opening.total_seats)),
+        };
+        notifier.send_course_notification(&msg, Priority::Normal, NoticeKind::Opening, &course).await;
     }
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotificationConfig;
+    use crate::webreg::mock::{section, MockWebReg};
+
+    async fn monitor(mock: &MockWebReg, recheck_freshness: Duration) -> Option<String> {
+        monitor_section(mock, None, "FA25", "A01", "CSE", "100", 5, 0, ThresholdDirection::AtMost,
+            MonitoringMode::Available, EnrollTarget::EnrollOnly, recheck_freshness, &LatencyTracker::default()).await.unwrap()
+            .opening.map(|opening| opening.section_id)
+    }
+
+    #[tokio::test]
+    async fn test_opening_detected_after_recheck() {
+        let mock = MockWebReg::default();
+        mock.push_course_info(vec![section("A01", "123456", 2, 0)])
+            .push_course_info(vec![section("A01", "123456", 1, 0)]);
+
+        assert_eq!(monitor(&mock, Duration::ZERO).await, Some("123456".to_string()));
+        assert_eq!(mock.remaining_course_info(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_false_positive_recheck() {
+        let mock = MockWebReg::default();
+        mock.push_course_info(vec![section("A01", "123456", 1, 0)])
+            .push_course_info(vec![section("A01", "123456", 0, 0)]);
+
+        assert_eq!(monitor(&mock, Duration::ZERO).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_full_section_skips_recheck() {
+        let mock = MockWebReg::default();
+        mock.push_course_info(vec![section("A00", "111111", 5, 0), section("A01", "123456", 0, 4)]);
+
+        assert_eq!(monitor(&mock, Duration::ZERO).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_fresh_data_skips_recheck() {
+        let mock = MockWebReg::default();
+        mock.push_course_info(vec![section("A01", "123456", 1, 0)])
+            .push_course_info(vec![section("A01", "123456", 0, 0)]);
+
+        assert_eq!(monitor(&mock, Duration::from_secs(60)).await, Some("123456".to_string()));
+        assert_eq!(mock.remaining_course_info(), 1);
+
+        // The same opening in an older listing is rechecked, and the recheck finds it gone
+        let listing = CourseListing {
+            sections: vec![section("A01", "123456", 1, 0)],
+            fetched_at: Instant::now().checked_sub(Duration::from_secs(5)).unwrap(),
+        };
+        let check = monitor_section(&mock, Some(&listing), "FA25", "A01", "CSE", "100", 5, 0, ThresholdDirection::AtMost,
+            MonitoringMode::Available, EnrollTarget::EnrollOnly, Duration::from_secs(1), &LatencyTracker::default()).await;
+        // The seats reported are the recheck's, not the stale listing's
+        assert_eq!(check.unwrap(), SectionCheck { opening: None, available_seats: Some(0) });
+        assert_eq!(mock.remaining_course_info(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sections_share_one_listing() {
+        // Nothing is scripted, so any fetch beyond the shared listing would fail
+        let mock = MockWebReg::default();
+        let listing = CourseListing {
+            sections: vec![section("A00", "111111", 0, 0), section("A01", "123456", 0, 0), section("A02", "123457", 3, 0)],
+            fetched_at: Instant::now(),
+        };
+
+        for (code, expected) in [("A00", None), ("A01", None), ("A02", Some("123457".to_string()))] {
+            let check = monitor_section(&mock, Some(&listing), "FA25", code, "CSE", "100", 5, 0, ThresholdDirection::AtMost,
+                MonitoringMode::Available, EnrollTarget::EnrollOnly, Duration::from_secs(60), &LatencyTracker::default()).await;
+            assert_eq!(check.unwrap().opening.map(|opening| opening.section_id), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_throttle_starts_cooldown_without_retrying() {
+        let mock = MockWebReg::default();
+        mock.push_throttle()
+            .push_course_info(vec![section("A01", "123456", 1, 0)]);
+        let notifier = Notifier::new(&NotificationConfig::default()).unwrap();
+        let throttle = ThrottleBackoff::default();
+        let retry = RetryPolicy { max_retries: 3, retry_delay: 1 };
+
+        let check = || monitor_section_with_retry(&mock, None, "FA25", "A01", "CSE", "100", 5, 0,
+            ThresholdDirection::AtMost, MonitoringMode::Available, EnrollTarget::EnrollOnly, Duration::from_secs(60), true,
+            &notifier, &LatencyTracker::default(), &throttle, &OpeningFilter::default(), retry, &RetryBudget::default());
+
+        assert!(check().await.is_err());
+        // The scripted opening behind the throttle was never fetched
+        assert_eq!(mock.remaining_course_info(), 1);
+        assert!(throttle.cooldown_remaining().unwrap() > Duration::from_secs(60));
+
+        // Later checks in the same cycle wait out the cooldown instead of hitting WebReg
+        assert!(check().await.is_err());
+        assert_eq!(mock.remaining_course_info(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_closed_poll_records_seats_without_a_listing() {
+        let mock = MockWebReg::default();
+        mock.push_course_info(vec![section("A01", "123456", 0, 2)]);
+        let notifier = Notifier::new(&NotificationConfig::default()).unwrap();
+        let openings = OpeningFilter::default();
+
+        let opening = monitor_section_with_retry(&mock, None, "FA25", "A01", "CSE", "100", 5, 0,
+            ThresholdDirection::AtMost, MonitoringMode::Available, EnrollTarget::EnrollOnly, Duration::from_secs(60), true,
+            &notifier, &LatencyTracker::default(), &ThrottleBackoff::default(), &openings, RetryPolicy::default(), &RetryBudget::default()).await;
+        assert_eq!(opening.unwrap(), None);
+        // The next opening's alert can say the section was full a moment ago
+        assert_eq!(openings.record_seats("CSE 100 A01", 3), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_is_shared_across_sections() {
+        // Nothing is scripted, so every fetch fails
+        let mock = MockWebReg::default();
+        let notifier = Notifier::new(&NotificationConfig::default()).unwrap();
+        let throttle = ThrottleBackoff::default();
+        let retry = RetryPolicy { max_retries: 3, retry_delay: 1 };
+        let budget = RetryBudget::new(4);
+
+        for course_code in ["100", "101"] {
+            let listing = fetch_course_listing(&mock, "FA25", "CSE", course_code, &notifier, &LatencyTracker::default(),
+                &throttle, retry, &budget).await;
+            assert!(listing.is_err());
+        }
+        // The first course took three retries, leaving the second only one
+        assert!(!budget.try_spend());
+    }
+
+    #[test]
+    fn test_opening_filter_requires_consecutive_polls() {
+        let openings = OpeningFilter::new(3);
+        assert!(!openings.observe("CSE 100 A01", true).is_open());
+        assert!(!openings.observe("CSE 100 A01", true).is_open());
+        assert!(openings.observe("CSE 100 A01", true).is_open());
+
+        // A closed poll starts the count over
+        assert!(!openings.observe("CSE 100 A01", false).is_open());
+        assert!(!openings.observe("CSE 100 A01", true).is_open());
+
+        // Sections are counted separately
+        assert!(!openings.observe("CSE 100 A02", true).is_open());
+        assert!(OpeningFilter::default().observe("CSE 100 A03", true).is_open());
+    }
+
+    #[test]
+    fn test_close_after_opening_is_reported_once() {
+        let openings = OpeningFilter::new(2);
+        // Closing before the opening was confirmed isn't a close-again
+        openings.observe("CSE 100 A01", true);
+        assert_eq!(openings.observe("CSE 100 A01", false), Transition::Waiting);
+
+        openings.observe("CSE 100 A01", true);
+        assert_eq!(openings.observe("CSE 100 A01", true), Transition::Open);
+        assert_eq!(openings.observe("CSE 100 A01", false), Transition::ClosedAgain);
+        assert_eq!(openings.observe("CSE 100 A01", false), Transition::Waiting);
+    }
+
+    #[test]
+    fn test_opening_message_reports_seat_change() {
+        let openings = OpeningFilter::default();
+        assert_eq!(openings.record_seats("CSE 100 A01", 0), None);
+        let previous = openings.record_seats("CSE 100 A01", 3);
+
+        let opening = Opening { section_id: "123456".to_string(), available_seats: 3, total_seats: 40 };
+        assert_eq!(opening.seat_summary(previous), "3 seats now available (was 0 a moment ago), 40 total.");
+        assert_eq!(opening.seat_summary(None), "3 seats now available, 40 total.");
+
+        let waitlist = Opening { available_seats: 0, ..opening };
+        assert_eq!(waitlist.seat_summary(previous), "No open seats (40 total); the waitlist is open.");
+    }
+
+    #[test]
+    fn test_section_pattern_matching() {
+        for code in ["A01", "A02", "a03"] {
+            assert!(section_matches("A*", code));
+        }
+        assert!(!section_matches("A*", "B01"));
+        assert!(section_matches("B0?", "B01"));
+        assert!(!section_matches("B0?", "B010"));
+        // No wildcard is a prefix
+        assert!(section_matches("C", "C04"));
+        assert!(!section_matches("C", "A04"));
+    }
+
+    #[test]
+    fn test_full_sections_poll_slower_than_near_open_ones() {
+        let backoff = PollBackoff::new(BackoffThresholds { slowest_demand: 2.0, max_skip: 4 });
+        backoff.observe("CSE 100 A01", 2.5);
+        backoff.observe("CSE 100 A02", 0.95);
+
+        let polls = |key: &str| (0..10).filter(|_| backoff.is_due(key)).count();
+        assert_eq!(polls("CSE 100 A01"), 6);
+        assert_eq!(polls("CSE 100 A02"), 10);
+
+        // Halfway to the slowest demand waits half as long, and disabled thresholds never wait
+        assert_eq!(BackoffThresholds { slowest_demand: 2.0, max_skip: 4 }.skip_for(1.5), 2);
+        assert_eq!(BackoffThresholds::default().skip_for(5.0), 0);
+    }
+
+    #[test]
+    fn test_available_mode() {
+        assert_eq!(should_attempt_enrollment(1, 0, 0, ThresholdDirection::AtMost, MonitoringMode::Available), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(0, 0, 0, ThresholdDirection::AtMost, MonitoringMode::Available), Decision::Full);
+        // The threshold only applies in low-seats mode
+        assert_eq!(should_attempt_enrollment(20, 0, 3, ThresholdDirection::AtMost, MonitoringMode::Available), Decision::Attempt);
+        // So does the direction, which is flagged when set
+        assert_eq!(should_attempt_enrollment(1, 0, 3, ThresholdDirection::AtLeast, MonitoringMode::Available), Decision::Attempt);
+        assert!(MonitoringMode::Available.ignored_direction(ThresholdDirection::AtLeast).is_some());
+        assert!(MonitoringMode::LowSeats.ignored_direction(ThresholdDirection::AtLeast).is_none());
+        assert!(MonitoringMode::WaitlistOnly.ignored_direction(ThresholdDirection::AtMost).is_none());
+    }
+
+    #[test]
+    fn test_low_seats_threshold_zero_means_any() {
+        assert_eq!(should_attempt_enrollment(50, 0, 0, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(0, 0, 0, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::Full);
+    }
+
+    #[test]
+    fn test_low_seats_threshold_boundary() {
+        assert_eq!(should_attempt_enrollment(3, 0, 3, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(1, 0, 3, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(4, 0, 3, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::AboveThreshold);
+        assert_eq!(should_attempt_enrollment(0, 0, 3, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::Full);
+    }
+
+    #[test]
+    fn test_at_least_threshold() {
+        let at_least = ThresholdDirection::AtLeast;
+        assert_eq!(should_attempt_enrollment(5, 0, 5, at_least, MonitoringMode::LowSeats), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(12, 0, 5, at_least, MonitoringMode::LowSeats), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(4, 0, 5, at_least, MonitoringMode::LowSeats), Decision::BelowThreshold);
+        assert_eq!(should_attempt_enrollment(0, 0, 5, at_least, MonitoringMode::LowSeats), Decision::Full);
+        assert_eq!(should_attempt_enrollment(1, 0, 0, at_least, MonitoringMode::LowSeats), Decision::Attempt);
+    }
+
+    #[test]
+    fn test_zero_seats_with_waitlist() {
+        assert_eq!(should_attempt_enrollment(0, 5, 0, ThresholdDirection::AtMost, MonitoringMode::Available), Decision::Full);
+        assert_eq!(should_attempt_enrollment(0, 5, 3, ThresholdDirection::AtMost, MonitoringMode::LowSeats), Decision::Full);
+        assert_eq!(should_attempt_enrollment(0, 5, 0, ThresholdDirection::AtMost, MonitoringMode::WaitlistOnly), Decision::Attempt);
+    }
+
+    #[test]
+    fn test_waitlist_only_mode() {
+        assert_eq!(should_attempt_enrollment(0, 0, 0, ThresholdDirection::AtMost, MonitoringMode::WaitlistOnly), Decision::Attempt);
+        // Negative counts show up when a section is over-enrolled
+        assert_eq!(should_attempt_enrollment(-2, 0, 0, ThresholdDirection::AtMost, MonitoringMode::WaitlistOnly), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(2, 3, 0, ThresholdDirection::AtMost, MonitoringMode::WaitlistOnly), Decision::Attempt);
+        assert_eq!(should_attempt_enrollment(2, 0, 0, ThresholdDirection::AtMost, MonitoringMode::WaitlistOnly), Decision::NoWaitlistNeeded);
+    }
+
+    #[test]
+    fn test_enroll_targets() {
+        let decide = |seats, waitlist, target| should_attempt_for_target(
+            seats, waitlist, 0, ThresholdDirection::AtMost, MonitoringMode::Available, target);
+
+        // Waitlist-only never goes after an open seat nobody is waiting for
+        assert_eq!(decide(3, 0, EnrollTarget::WaitlistOnly), Decision::NoWaitlistNeeded);
+        assert_eq!(decide(0, 4, EnrollTarget::WaitlistOnly), Decision::Attempt);
+        assert_eq!(decide(0, 4, EnrollTarget::EnrollOnly), Decision::Full);
+        assert_eq!(decide(3, 0, EnrollTarget::EnrollOrWaitlist), Decision::Attempt);
+        assert_eq!(decide(0, 4, EnrollTarget::EnrollOrWaitlist), Decision::Attempt);
+
+        // A waitlist-only job's default target keeps its old behaviour
+        let mode = MonitoringMode::WaitlistOnly;
+        assert_eq!(
+            should_attempt_for_target(2, 0, 0, ThresholdDirection::AtMost, mode, mode.default_target()),
+            should_attempt_enrollment(2, 0, 0, ThresholdDirection::AtMost, mode),
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
-    routing::{delete, get, post},
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -10,9 +11,14 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::auth::AuthenticatedUser;
+use crate::config::LOG_FILE;
+use crate::enroll::EnrollEligibility;
 use crate::multi_user_state::MultiUserState;
 use crate::models::*;
 use crate::db;
+use crate::encryption::EncryptionKey;
+use crate::error::Error;
+use crate::webreg::ScheduleEntry;
 
 // ============================================================================
 // API State
@@ -22,6 +28,33 @@ pub struct MultiUserApiState {
     pub state: Arc<MultiUserState>,
 }
 
+/// An authenticated user whose account is flagged `is_admin`
+pub struct AdminUser(pub User);
+
+#[async_trait]
+impl FromRequestParts<Arc<MultiUserApiState>> for AdminUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<MultiUserApiState>) -> Result<Self, Self::Rejection> {
+        let auth = AuthenticatedUser::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+        let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to get user: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        if !user.is_admin {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(AdminUser(user))
+    }
+}
+
 // ============================================================================
 // Request/Response Types
 // ============================================================================
@@ -31,6 +64,9 @@ pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Non-fatal notes about the request, e.g. a value the server adjusted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl<T> ApiResponse<T> {
@@ -39,6 +75,7 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            warnings: Vec::new(),
         }
     }
 
@@ -47,8 +84,25 @@ impl<T> ApiResponse<T> {
             success: false,
             data: None,
             error: Some(error),
+            warnings: Vec::new(),
         }
     }
+
+    pub fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+}
+
+/// Responds with the matching status and the message in the usual JSON envelope
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status.is_server_error() {
+            log::error!("Request failed: {:?}", self);
+        }
+        (status, Json(ApiResponse::<()>::error(self.to_string()))).into_response()
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -58,6 +112,7 @@ pub struct JobListItem {
     pub polling_interval: i32,
     pub seat_threshold: i32,
     pub monitoring_mode: String,
+    pub enroll: bool,
     pub is_active: bool,
     pub is_connected: bool,
     pub last_check_time: Option<String>,
@@ -68,6 +123,12 @@ pub struct JobListItem {
 pub struct JobDetailResponse {
     pub job: JobResponse,
     pub is_running: bool,
+    pub is_paused: bool,
+    pub eligibility: Vec<EnrollEligibility>,
+    pub avg_latency_ms: Option<f64>,
+    pub p95_latency_ms: Option<u64>,
+    /// The running job couldn't write its latest stats; the stored ones are stale
+    pub db_degraded: bool,
 }
 
 // ============================================================================
@@ -87,12 +148,12 @@ async fn health_check() -> Json<serde_json::Value> {
 async fn get_current_user(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
-) -> Result<Json<ApiResponse<User>>, StatusCode> {
+) -> Result<Json<ApiResponse<User>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     Ok(Json(ApiResponse::success(user)))
@@ -102,44 +163,249 @@ async fn get_current_user(
 async fn create_job(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
-    Json(request): Json<CreateJobRequest>,
-) -> Result<Json<ApiResponse<Uuid>>, StatusCode> {
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<ApiResponse<Uuid>>, Error> {
     // Get or create user
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
+    // Fields the request leaves out come from the user's saved defaults
+    let mut request = state.state.job_defaults(user.id).await?
+        .request_from(body)
+        .map_err(Error::InvalidInput)?;
+
+    // A replayed request returns its original job, even if the user is now at the limit
+    let idempotency_key = headers
+        .get("Idempotency-Key")
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty());
+    if let Some(key) = idempotency_key {
+        let existing = db::get_idempotent_job(&state.state.pool, user.id, key)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to look up idempotency key: {:?}", e);
+                Error::from(e)
+            })?;
+        if let Some(job_id) = existing {
+            return Ok(Json(ApiResponse::success(job_id)));
+        }
+    }
+
+    // Very short intervals risk WebReg banning everyone on this deployment
+    let warnings: Vec<String> = request
+        .enforce_min_polling_interval(state.state.settings.min_polling_interval)
+        .into_iter()
+        .chain(request.ignored_direction_note())
+        .collect();
+
     // Create job
-    let job_id = state.state.create_job(user.id, request)
+    let job_id = state.state.create_job(user.id, request, idempotency_key)
         .await
         .map_err(|e| {
             log::error!("Failed to create job: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            e
+        })?;
+
+    Ok(Json(ApiResponse::success(job_id).with_warnings(warnings)))
+}
+
+/// Create several monitoring jobs in one request (all or nothing)
+async fn create_jobs_bulk(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Json(bodies): Json<Vec<serde_json::Value>>,
+) -> Result<Json<ApiResponse<Vec<Uuid>>>, Error> {
+    if bodies.is_empty() {
+        return Err(Error::InvalidInput("At least one job is required".to_string()));
+    }
+
+    // Get or create user
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    let defaults = state.state.job_defaults(user.id).await?;
+    let mut requests = bodies
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| defaults.request_from(body).map_err(|e| Error::InvalidInput(format!("Job {}: {}", i + 1, e))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let warnings: Vec<String> = requests
+        .iter_mut()
+        .enumerate()
+        .flat_map(|(i, request)| {
+            request.enforce_min_polling_interval(state.state.settings.min_polling_interval)
+                .into_iter()
+                .chain(request.ignored_direction_note())
+                .map(move |note| format!("Job {}: {}", i + 1, note))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let job_ids = state.state.create_jobs(user.id, requests)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to bulk create jobs: {:?}", e);
+            e
+        })?;
+
+    Ok(Json(ApiResponse::success(job_ids).with_warnings(warnings)))
+}
+
+/// Copy a job's courses and settings into a new job, e.g. for the next term
+async fn clone_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+    Json(overrides): Json<CloneJobRequest>,
+) -> Result<Json<ApiResponse<Uuid>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    let mut request = state.state.clone_request(job_id, user.id, overrides).await?;
+
+    // The source job may predate a raised floor
+    let warnings: Vec<String> = request
+        .enforce_min_polling_interval(state.state.settings.min_polling_interval)
+        .into_iter()
+        .collect();
+
+    let new_job_id = state.state.create_job(user.id, request, None)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to clone job {}: {:?}", job_id, e);
+            e
+        })?;
+
+    Ok(Json(ApiResponse::success(new_job_id).with_warnings(warnings)))
+}
+
+/// A job's courses and settings as JSON that `POST /api/jobs/import` accepts; cookies are left out
+async fn export_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<JobExport>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    Ok(Json(ApiResponse::success(state.state.export_job(job_id, user.id).await?)))
+}
+
+/// Turn a job's threshold override on or off, e.g. to grab any seat near a deadline
+async fn set_ignore_threshold(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+    Json(request): Json<ThresholdOverrideRequest>,
+) -> Result<Json<ApiResponse<String>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    state.state.set_ignore_threshold(job_id, user.id, request.ignore_threshold).await?;
+
+    let message = if request.ignore_threshold {
+        "Threshold override on: the job attempts enrollment on any open seat"
+    } else {
+        "Threshold override off"
+    };
+    Ok(Json(ApiResponse::success(message.to_string())))
+}
+
+/// Run one poll cycle of a running job now and report what each section did
+async fn poll_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<SectionPollResult>>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    Ok(Json(ApiResponse::success(state.state.poll_now(job_id, user.id).await?)))
+}
+
+/// Recreate a job from an export plus the cookie to run it under
+async fn import_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Json(import): Json<ImportJobRequest>,
+) -> Result<Json<ApiResponse<Uuid>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // The export may come from a server with a lower floor
+    let mut request = CreateJobRequest::from(import);
+    let warnings: Vec<String> = request
+        .enforce_min_polling_interval(state.state.settings.min_polling_interval)
+        .into_iter()
+        .collect();
+
+    let job_id = state.state.create_job(user.id, request, None)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to import job: {:?}", e);
+            e
         })?;
 
-    Ok(Json(ApiResponse::success(job_id)))
+    Ok(Json(ApiResponse::success(job_id).with_warnings(warnings)))
+}
+
+/// Check whether a cookie works for a term, without creating a job
+async fn validate_cookie(
+    State(state): State<Arc<MultiUserApiState>>,
+    _auth: AuthenticatedUser,
+    Json(request): Json<ValidateCookieRequest>,
+) -> Result<Json<ApiResponse<CookieValidation>>, Error> {
+    Ok(Json(ApiResponse::success(state.state.check_cookie(&request).await?)))
 }
 
 /// Get all jobs for the current user
 async fn get_user_jobs(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
-) -> Result<Json<ApiResponse<Vec<JobListItem>>>, StatusCode> {
+    Query(query): Query<JobListQuery>,
+) -> Result<Json<ApiResponse<Vec<JobListItem>>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
-    let jobs = state.state.get_user_jobs(user.id)
+    let jobs = state.state.get_user_jobs(user.id, &query)
         .await
         .map_err(|e| {
             log::error!("Failed to get jobs: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     let job_items: Vec<JobListItem> = jobs.iter().map(|j| JobListItem {
@@ -148,6 +414,7 @@ async fn get_user_jobs(
         polling_interval: j.polling_interval,
         seat_threshold: j.seat_threshold,
         monitoring_mode: j.monitoring_mode.clone(),
+        enroll: j.enroll,
         is_active: j.is_active,
         is_connected: j.is_connected,
         last_check_time: j.last_check_time.map(|t| t.to_string()),
@@ -162,32 +429,30 @@ async fn get_job_detail(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
     Path(job_id): Path<Uuid>,
-) -> Result<Json<ApiResponse<JobDetailResponse>>, StatusCode> {
+) -> Result<Json<ApiResponse<JobDetailResponse>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     let job = db::get_job_by_id(&state.state.pool, job_id, user.id)
         .await
         .map_err(|e| {
             log::error!("Failed to get job: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
 
     // Get courses
     let courses = db::get_job_courses(&state.state.pool, job_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     let mut course_responses = Vec::new();
     for course in courses {
         let sections = db::get_course_sections(&state.state.pool, course.id)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .await?;
 
         let section_responses: Vec<SectionResponse> = sections.iter().map(|s| {
             let discussions: Vec<String> = serde_json::from_value(s.discussions.clone())
@@ -196,6 +461,11 @@ async fn get_job_detail(
                 id: s.id,
                 lecture: s.lecture.clone(),
                 discussions,
+                section_id: s.section_id.clone(),
+                enroll_target: s.enroll_target.clone(),
+                atomic: s.atomic,
+                one_discussion: s.one_discussion,
+                discussion_required: s.discussion_required,
             }
         }).collect();
 
@@ -204,13 +474,14 @@ async fn get_job_detail(
             department: course.department,
             course_code: course.course_code,
             sections: section_responses,
+            section_pattern: course.section_pattern,
+            role: course.role,
         });
     }
 
     // Get stats
     let stats_db = db::get_job_stats(&state.state.pool, job_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     let stats = stats_db.map(|s| EnrollmentStatsResponse {
         total_checks: s.total_checks,
@@ -224,7 +495,12 @@ async fn get_job_detail(
     });
 
     // Check if job is currently running
-    let is_running = state.state.get_job_status(job_id).await.is_some();
+    let status = state.state.get_job_status(job_id).await;
+    let is_running = status.is_some();
+    let (is_paused, eligibility, avg_latency_ms, p95_latency_ms, db_degraded) = match status {
+        Some(s) => (s.is_paused, s.eligibility, s.avg_latency_ms, s.p95_latency_ms, s.db_degraded),
+        None => (false, Vec::new(), None, None, false),
+    };
 
     let job_response = JobResponse {
         id: job.id,
@@ -232,6 +508,20 @@ async fn get_job_detail(
         polling_interval: job.polling_interval,
         seat_threshold: job.seat_threshold,
         monitoring_mode: job.monitoring_mode,
+        threshold_direction: job.threshold_direction,
+        has_backup_cookie: job.backup_cookie_encrypted.is_some(),
+        enroll: job.enroll,
+        stop_on_first_success: job.stop_on_first_success,
+        max_concurrent_enrolls: job.max_concurrent_enrolls,
+        stop_cycle_on_success: job.stop_cycle_on_success,
+        watch_enrolled: job.watch_enrolled,
+        held_sections: serde_json::from_value(job.held_sections).unwrap_or_default(),
+        poll_immediately: job.poll_immediately,
+        enrollment_opens_at: job.enrollment_opens_at,
+        force_enroll: job.force_enroll,
+        ignore_threshold: job.ignore_threshold,
+        max_run_duration_hours: job.max_run_duration_hours,
+        cookie_refresh_interval: job.cookie_refresh_interval,
         is_active: job.is_active,
         is_connected: job.is_connected,
         last_check_time: job.last_check_time,
@@ -242,6 +532,11 @@ async fn get_job_detail(
     Ok(Json(ApiResponse::success(JobDetailResponse {
         job: job_response,
         is_running,
+        is_paused,
+        eligibility,
+        avg_latency_ms,
+        p95_latency_ms,
+        db_degraded,
     })))
 }
 
@@ -250,19 +545,19 @@ async fn start_job(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
     Path(job_id): Path<Uuid>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
+) -> Result<Json<ApiResponse<String>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     state.state.start_job(job_id, user.id)
         .await
         .map_err(|e| {
             log::error!("Failed to start job: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            e
         })?;
 
     Ok(Json(ApiResponse::success("Job started successfully".to_string())))
@@ -273,27 +568,284 @@ async fn stop_job(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
     Path(job_id): Path<Uuid>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
+) -> Result<Json<ApiResponse<String>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     // Verify ownership
     let _job = db::get_job_by_id(&state.state.pool, job_id, user.id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
 
     state.state.stop_job(job_id)
         .await
         .map_err(|e| {
             log::error!("Failed to stop job: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            e
+        })?;
+
+    Ok(Json(ApiResponse::success("Job stopped successfully".to_string())))
+}
+
+/// Stop polling a running job without unloading it
+async fn pause_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<String>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // Verify ownership
+    let _job = db::get_job_by_id(&state.state.pool, job_id, user.id)
+        .await?
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+    state.state.set_paused(job_id, true).await?;
+
+    Ok(Json(ApiResponse::success("Job paused".to_string())))
+}
+
+/// Resume polling a paused job
+async fn resume_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<String>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // Verify ownership
+    let _job = db::get_job_by_id(&state.state.pool, job_id, user.id)
+        .await?
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+    state.state.set_paused(job_id, false).await?;
+
+    Ok(Json(ApiResponse::success("Job resumed".to_string())))
+}
+
+const ATTEMPT_HISTORY_LIMIT: i64 = 200;
+
+/// Recent enrollment attempts for a job, with WebReg's response to each
+async fn get_job_attempts(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<EnrollmentAttempt>>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // Verify ownership
+    let _job = db::get_job_by_id(&state.state.pool, job_id, user.id)
+        .await?
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+    let attempts = db::get_enrollment_attempts(&state.state.pool, job_id, ATTEMPT_HISTORY_LIMIT)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get enrollment attempts: {:?}", e);
+            Error::from(e)
+        })?;
+
+    Ok(Json(ApiResponse::success(attempts)))
+}
+
+/// Drop a section from the user's WebReg schedule using the job's cookie
+async fn drop_section(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+    Json(request): Json<DropSectionRequest>,
+) -> Result<Json<ApiResponse<bool>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // Verify ownership
+    let _job = db::get_job_by_id(&state.state.pool, job_id, user.id)
+        .await?
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+    match state.state.drop_section(job_id, user.id, &request).await {
+        Ok(dropped) => Ok(Json(ApiResponse::success(dropped))),
+        Err(e) => {
+            log::error!("Failed to drop section {}: {:?}", request.section_id, e);
+            Err(Error::from(e))
+        }
+    }
+}
+
+/// The user's current WebReg schedule, fetched live with the job's cookie and term
+async fn get_job_schedule(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<ScheduleEntry>>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // The lookup is scoped to the user, so someone else's job is a 404 too
+    match state.state.job_schedule(job_id, user.id).await {
+        Ok(schedule) => Ok(Json(ApiResponse::success(schedule))),
+        Err(e) => {
+            log::error!("Failed to fetch the schedule for job {}: {:?}", job_id, e);
+            Err(e)
+        }
+    }
+}
+
+/// Show which configured sections currently meet the job's enrollment criteria
+async fn preview_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<SectionPreview>>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // Verify ownership
+    let _job = db::get_job_by_id(&state.state.pool, job_id, user.id)
+        .await?
+        .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+    match state.state.preview_job(job_id, user.id).await {
+        Ok(previews) => Ok(Json(ApiResponse::success(previews))),
+        Err(e) => {
+            log::error!("Failed to preview job {}: {:?}", job_id, e);
+            Err(Error::from(e))
+        }
+    }
+}
+
+/// Return WebReg's course info exactly as reported, for comparing against configured section codes
+async fn debug_course_info(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Path((term, department, course_code)): Path<(String, String, String)>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    // Requires one of the user's own jobs for the term, whose cookie is used for the lookup
+    match state.state.debug_course_info(user.id, &term, &department, &course_code).await {
+        Ok(Some(info)) => Ok(Json(ApiResponse::success(info))),
+        Ok(None) => Err(Error::NotFound(format!("No job found for term {}", term))),
+        Err(e) => {
+            log::error!("Failed to fetch course info for {} {}: {:?}", department, course_code, e);
+            Err(Error::from(e))
+        }
+    }
+}
+
+const DEFAULT_LOG_LINES: usize = 100;
+const MAX_LOG_LINES: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct LogQuery {
+    pub lines: Option<usize>,
+}
+
+/// Return the tail of the server log (admin only)
+async fn get_logs(
+    AdminUser(admin): AdminUser,
+    Query(query): Query<LogQuery>,
+) -> Result<Json<ApiResponse<Vec<String>>>, Error> {
+    let lines = query.lines.unwrap_or(DEFAULT_LOG_LINES).min(MAX_LOG_LINES);
+    log::info!("Admin {} fetched the last {} log lines", admin.email, lines);
+
+    let tail = tokio::task::spawn_blocking(move || crate::utils::tail_lines(&crate::utils::data_path(LOG_FILE), lines))
+        .await
+        .map_err(|e| Error::Other(Box::new(e)))?;
+
+    match tail {
+        Ok(tail) => Ok(Json(ApiResponse::success(tail))),
+        Err(e) => {
+            log::error!("Failed to read log file: {:?}", e);
+            Err(Error::Other(Box::new(e)))
+        }
+    }
+}
+
+/// List every active job across all users (admin only)
+async fn admin_list_jobs(
+    State(state): State<Arc<MultiUserApiState>>,
+    AdminUser(_admin): AdminUser,
+) -> Result<Json<ApiResponse<Vec<AdminJobSummary>>>, Error> {
+    let jobs = db::get_active_jobs_with_owner(&state.state.pool)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to list active jobs: {:?}", e);
+            Error::from(e)
+        })?;
+
+    Ok(Json(ApiResponse::success(jobs)))
+}
+
+/// Resident job counts and a rough memory estimate (admin only)
+async fn admin_metrics(
+    State(state): State<Arc<MultiUserApiState>>,
+    AdminUser(_admin): AdminUser,
+) -> Result<Json<ApiResponse<JobMapMetrics>>, Error> {
+    let metrics = state.state.job_metrics()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to collect job metrics: {:?}", e);
+            Error::from(e)
+        })?;
+
+    Ok(Json(ApiResponse::success(metrics)))
+}
+
+/// Stop any user's job (admin only)
+async fn admin_stop_job(
+    State(state): State<Arc<MultiUserApiState>>,
+    AdminUser(admin): AdminUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<String>>, Error> {
+    let found = state.state.admin_stop_job(job_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to stop job {}: {:?}", job_id, e);
+            Error::from(e)
         })?;
 
+    if !found {
+        return Err(Error::NotFound("Job not found".to_string()));
+    }
+
+    log::info!("Admin {} stopped job {}", admin.email, job_id);
     Ok(Json(ApiResponse::success("Job stopped successfully".to_string())))
 }
 
@@ -302,19 +854,19 @@ async fn delete_job(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
     Path(job_id): Path<Uuid>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
+) -> Result<Json<ApiResponse<String>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     state.state.delete_job(job_id, user.id)
         .await
         .map_err(|e| {
             log::error!("Failed to delete job: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     Ok(Json(ApiResponse::success("Job deleted successfully".to_string())))
@@ -324,19 +876,18 @@ async fn delete_job(
 async fn get_notifications(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
-) -> Result<Json<ApiResponse<NotificationSettings>>, StatusCode> {
+) -> Result<Json<ApiResponse<NotificationSettingsResponse>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
     let settings = db::get_or_create_notification_settings(&state.state.pool, user.id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
-    Ok(Json(ApiResponse::success(settings)))
+    Ok(Json(ApiResponse::success(settings.into())))
 }
 
 /// Update notification settings
@@ -344,22 +895,19 @@ async fn update_notifications(
     State(state): State<Arc<MultiUserApiState>>,
     auth: AuthenticatedUser,
     Json(request): Json<UpdateNotificationRequest>,
-) -> Result<Json<ApiResponse<String>>, StatusCode> {
+) -> Result<Json<ApiResponse<String>>, Error> {
     let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
         .await
         .map_err(|e| {
             log::error!("Failed to get user: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Error::from(e)
         })?;
 
-    // Encrypt gmail password if provided
-    let (gmail_encrypted, gmail_nonce) = if let Some(password) = &request.gmail_app_password {
-        let (enc, nonce) = state.state.encryption_key.encrypt(password)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        (Some(enc), Some(nonce))
-    } else {
-        (None, None)
-    };
+    // Secrets the form leaves out keep their stored values
+    let (gmail_encrypted, gmail_nonce) =
+        encrypt_setting_secret(&state.state.encryption_key, request.gmail_app_password.as_deref())?;
+    let (pushover_token_encrypted, pushover_token_nonce) =
+        encrypt_setting_secret(&state.state.encryption_key, request.pushover_api_token.as_deref())?;
 
     db::update_notification_settings(
         &state.state.pool,
@@ -369,13 +917,69 @@ async fn update_notifications(
         gmail_nonce.as_deref(),
         &request.email_recipients,
         request.discord_webhook_url.as_deref(),
+        request.success_webhook_url.as_deref(),
+        request.alert_webhook_url.as_deref(),
+        &request.alert_email_recipients,
+        request.notify_on_close,
+        request.pushover_user_key.as_deref(),
+        pushover_token_encrypted.as_deref(),
+        pushover_token_nonce.as_deref(),
+        request.notify_on_recovery,
+        request.discord_format.map(|format| format.to_string()).as_deref(),
     )
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(ApiResponse::success("Notifications updated successfully".to_string())))
 }
 
+/// Encrypt a secret from a settings update as (ciphertext, nonce). A secret left out stays
+/// `None`, keeping the stored one; an empty one becomes empty strings, which clear it.
+fn encrypt_setting_secret(key: &EncryptionKey, secret: Option<&str>) -> Result<(Option<String>, Option<String>), Error> {
+    match secret {
+        None => Ok((None, None)),
+        Some("") => Ok((Some(String::new()), Some(String::new()))),
+        Some(secret) => {
+            let (encrypted, nonce) = key.encrypt(secret).map_err(|e| Error::Encryption(e.to_string()))?;
+            Ok((Some(encrypted), Some(nonce)))
+        }
+    }
+}
+
+/// Get the defaults new jobs take for fields their request leaves out
+async fn get_preferences(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+) -> Result<Json<ApiResponse<JobDefaults>>, Error> {
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    Ok(Json(ApiResponse::success(state.state.job_defaults(user.id).await?)))
+}
+
+/// Replace the job defaults; a field left out or null has no default
+async fn update_preferences(
+    State(state): State<Arc<MultiUserApiState>>,
+    auth: AuthenticatedUser,
+    Json(defaults): Json<JobDefaults>,
+) -> Result<Json<ApiResponse<JobDefaults>>, Error> {
+    defaults.validate().map_err(Error::InvalidInput)?;
+
+    let user = db::get_or_create_user(&state.state.pool, &auth.clerk_user_id, &auth.email)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user: {:?}", e);
+            Error::from(e)
+        })?;
+
+    db::set_user_preferences(&state.state.pool, user.id, &defaults).await?;
+
+    Ok(Json(ApiResponse::success(defaults)))
+}
+
 // ============================================================================
 // Router
 // ============================================================================
@@ -389,12 +993,32 @@ pub fn create_router(state: Arc<MultiUserApiState>) -> Router {
         .route("/api/user", get(get_current_user))
         .route("/api/jobs", post(create_job))
         .route("/api/jobs", get(get_user_jobs))
+        .route("/api/jobs/bulk", post(create_jobs_bulk))
+        .route("/api/jobs/import", post(import_job))
+        .route("/api/cookie/validate", post(validate_cookie))
         .route("/api/jobs/:job_id", get(get_job_detail))
         .route("/api/jobs/:job_id/start", post(start_job))
         .route("/api/jobs/:job_id/stop", post(stop_job))
+        .route("/api/jobs/:job_id/pause", post(pause_job))
+        .route("/api/jobs/:job_id/resume", post(resume_job))
+        .route("/api/jobs/:job_id/drop", post(drop_section))
+        .route("/api/jobs/:job_id/preview", get(preview_job))
+        .route("/api/jobs/:job_id/poll", post(poll_job))
+        .route("/api/jobs/:job_id/ignore_threshold", put(set_ignore_threshold))
+        .route("/api/jobs/:job_id/schedule", get(get_job_schedule))
+        .route("/api/jobs/:job_id/attempts", get(get_job_attempts))
+        .route("/api/jobs/:job_id/clone", post(clone_job))
+        .route("/api/jobs/:job_id/export", get(export_job))
         .route("/api/jobs/:job_id", delete(delete_job))
+        .route("/api/debug/course/:term/:department/:course_code", get(debug_course_info))
+        .route("/api/logs", get(get_logs))
+        .route("/api/admin/jobs", get(admin_list_jobs))
+        .route("/api/admin/jobs/:job_id/stop", post(admin_stop_job))
+        .route("/api/admin/metrics", get(admin_metrics))
         .route("/api/notifications", get(get_notifications))
         .route("/api/notifications", post(update_notifications))
+        .route("/api/preferences", get(get_preferences))
+        .route("/api/preferences", put(update_preferences))
 
         .with_state(state)
 }
@@ -1,20 +1,30 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
-use std::time::{Duration, SystemTime};
+use tokio::sync::{Mutex, Notify, RwLock, Semaphore};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::time::sleep;
-use log::{info, error};
-use chrono::Local;
+use futures::{future, stream, StreamExt};
+use log::{debug, info, warn, error};
+use chrono::Utc;
 use uuid::Uuid;
+use sqlx::PgConnection;
 use webweg::wrapper::WebRegWrapper;
 
+use crate::config::{enrolls_lecture_alone, normalize_course_field, CourseRole, EnrollTarget, MonitoringMode, ServerConfig, ThresholdDirection, DEFAULT_FAILURE_NOTIFICATION_CAP};
 use crate::db::DbPool;
+use crate::error::Error;
 use crate::models::*;
 use crate::encryption::EncryptionKey;
-use crate::notifier::Notifier;
-use crate::stats::EnrollmentStats;
-use crate::monitor::monitor_section_with_retry;
-use crate::enroll::try_enroll_with_retry;
+use crate::notifier::{EnrollmentEvent, Notifier, Outbox, OutboxMessage, Priority};
+use crate::stats::{EnrollmentStats, LatencyTracker};
+use crate::monitor::{fetch_course_listing, monitor_section_with_retry, section_matches, should_attempt_for_target, Opening, OpeningFilter, PollBackoff};
+use crate::webreg::{accessible_terms, check_session, cookie_wrapper, fetch_schedule, ScheduleEntry, SessionStatus, ThrottleBackoff, WebRegClient};
+use crate::quota::{QuotaClient, RequestQuota};
+use crate::utils::{within, GraceRetry, RetryBudget, RetryPolicy, SessionCheckCycles, TimedOut, format_duration, initial_offset, jittered_interval, local_now};
+use crate::enroll::{attempt_enroll, attempt_enroll_group, attempt_enroll_one_of, report_enroll_result, run_bounded, try_drop, check_course_eligibility, EnrollAttempt, EnrollEligibility, EnrollOptions, EnrollOutcome};
+
+// Floor for a job's cookie_refresh_interval, in seconds
+const MIN_COOKIE_REFRESH_INTERVAL: i32 = 60;
 
 /// Represents a running monitoring job for a user
 pub struct UserJob {
@@ -22,16 +32,98 @@ pub struct UserJob {
     pub user_id: Uuid,
     pub term: String,
     pub wrapper: Arc<WebRegWrapper>,
+    pub backup_cookie: Option<String>,  // Failed over to once when the primary cookie expires
     pub notifier: Notifier,
     pub stats: EnrollmentStats,
     pub courses: Vec<CourseWithSections>,
     pub polling_interval: u64,
     pub seat_threshold: i64,
+    pub threshold_direction: ThresholdDirection,
+    pub mode: MonitoringMode,
+    pub enroll: bool,
+    pub verify_enrollment: bool,
+    pub stop_on_first_success: bool,
+    pub max_concurrent_enrolls: usize,
+    pub stop_cycle_on_success: bool,
+    pub watch_enrolled: bool,
+    pub held_sections: Vec<HeldSection>,  // Enrolled sections re-checked when watch_enrolled is on
+    pub filled_groups: HashSet<String>,  // One-discussion groups whose discussion is taken, e.g. "CSE 100 A00"
+    pub poll_immediately: bool,  // First poll right at start instead of after a staggered delay
+    pub enrollment_opens_at: Option<chrono::DateTime<Utc>>,  // Monitor only until the user's appointment
+    pub force_enroll: bool,  // Enroll regardless of enrollment_opens_at
+    pub ignore_threshold: bool,  // Attempt on any open seat; can be toggled while running
+    pub awaiting_enrollment_window: bool,  // Started before the appointment; announces when it opens
+    pub paused: bool,  // Skips polls while staying loaded, so resuming needs no restart
     pub is_running: bool,
     pub is_connected: bool,
     pub last_check_time: String,
     pub start_time: SystemTime,
     pub shutdown_tx: tokio::sync::broadcast::Sender<()>,
+    pub eligibility: Vec<EnrollEligibility>,
+    pub latency: LatencyTracker,
+    pub throttle: ThrottleBackoff,
+    pub openings: OpeningFilter,
+    pub poll_backoff: PollBackoff,  // Per-section cycles to skip while far past capacity
+    pub quota: Arc<RequestQuota>,
+    pub recheck_freshness: Duration,
+    pub max_run_duration: Option<Duration>,
+    pub two_phase_enroll: bool,
+    pub plan_unit_count: u8,
+    pub grace_retry: GraceRetry,
+    pub cycle_retry_budget: u32,  // Retries shared by every section in one cycle (0 = unlimited)
+    pub cookie_refresh_interval: u64,
+    pub cookie_refresh_jitter: bool,  // First cookie check at a random point in the interval
+    pub proxy_url: Option<String>,    // Server-wide proxy, kept for rebuilding the wrapper on failover
+    pub webreg_timeout: Duration,     // Likewise the server's WebReg request timeout
+    pub section_timeout: Duration,    // One section's check is abandoned after this
+    pub session_check_cycles: u32,    // Early cookie check every this many poll cycles (0 = timer only)
+    pub max_sections_per_course: usize,  // Caps what section patterns can add (0 = unlimited)
+    pub db_degraded: bool,  // Recent database writes failed; the stored stats are stale
+    heartbeat: Heartbeat,  // Beaten each time round the loop and for each section a cycle gets through
+}
+
+impl UserJob {
+    fn enroll_options(&self) -> EnrollOptions {
+        EnrollOptions {
+            verify: self.verify_enrollment,
+            two_phase: self.two_phase_enroll,
+            plan_unit_count: self.plan_unit_count,
+            target: self.mode.default_target(),
+            failure_notification_cap: DEFAULT_FAILURE_NOTIFICATION_CAP,
+            retry: RetryPolicy::default(),
+            grace: self.grace_retry,
+        }
+    }
+
+    fn effective_seat_threshold(&self) -> i64 {
+        effective_seat_threshold(self.seat_threshold, self.ignore_threshold)
+    }
+
+    /// Stop monitoring the job's backup courses. Returns false if it had none.
+    fn drop_backup_courses(&mut self) -> bool {
+        let before = self.courses.len();
+        self.courses.retain(|c| c.role != Some(CourseRole::Backup));
+        self.courses.len() < before
+    }
+
+    /// Rough bytes this job holds, leaving out its WebReg client and notifier
+    fn approx_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.courses.iter().map(CourseWithSections::approx_size).sum::<usize>()
+            + self.held_sections.len() * std::mem::size_of::<HeldSection>()
+            + self.filled_groups.iter().map(|group| std::mem::size_of::<String>() + group.capacity()).sum::<usize>()
+            + self.eligibility.len() * std::mem::size_of::<EnrollEligibility>()
+    }
+
+    /// Whether the loop polls WebReg this cycle
+    fn polls(&self) -> bool {
+        self.is_running && self.is_connected && !self.paused
+    }
+
+    /// Whether enrollment may be attempted yet; before the appointment the job only monitors
+    fn enrollment_open(&self) -> bool {
+        self.force_enroll || !matches!(self.enrollment_opens_at, Some(opens) if Utc::now() < opens)
+    }
 }
 
 #[derive(Clone)]
@@ -39,12 +131,149 @@ pub struct CourseWithSections {
     pub department: String,
     pub course_code: String,
     pub sections: Vec<SectionGroup>,
+    /// Listed sections matching this are added to `sections` while the job runs
+    pub section_pattern: Option<String>,
+    pub role: Option<CourseRole>,
+}
+
+impl CourseWithSections {
+    fn approx_size(&self) -> usize {
+        let text = |value: &String| std::mem::size_of::<String>() + value.capacity();
+        std::mem::size_of::<Self>()
+            + self.department.capacity()
+            + self.course_code.capacity()
+            + self.section_pattern.as_ref().map_or(0, String::capacity)
+            + self.sections.iter().map(|group| {
+                std::mem::size_of::<SectionGroup>()
+                    + group.lecture.capacity()
+                    + group.discussions.iter().map(text).sum::<usize>()
+                    + group.section_id.as_ref().map_or(0, String::capacity)
+            }).sum::<usize>()
+    }
 }
 
 #[derive(Clone)]
 pub struct SectionGroup {
     pub lecture: String,
     pub discussions: Vec<String>,
+    /// WebReg's numeric id for the lecture, matched instead of the code when set
+    pub section_id: Option<String>,
+    pub enroll_target: Option<EnrollTarget>,
+    /// Enroll the lecture with one open discussion in a single step, rolling back on failure
+    pub atomic: bool,
+    /// Enroll at most one discussion; the others are left alone once one is taken
+    pub one_discussion: bool,
+    /// The lecture is only taken through a discussion, never enrolled alone
+    pub discussion_required: bool,
+}
+
+impl SectionGroup {
+    /// Lecture followed by its discussions, in monitoring order
+    pub fn sections(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(self.section_id.as_ref().unwrap_or(&self.lecture)).chain(self.discussions.iter())
+    }
+
+    pub fn enroll_target(&self, mode: MonitoringMode) -> EnrollTarget {
+        self.enroll_target.unwrap_or(mode.default_target())
+    }
+
+    /// How an opening in the group's section at `index` (0 is the lecture) is enrolled
+    fn opening_role(&self, index: usize) -> OpeningRole {
+        if self.atomic {
+            OpeningRole::Atomic
+        } else if index == 0 && !enrolls_lecture_alone(self.discussion_required, &self.discussions) {
+            OpeningRole::ReportOnly
+        } else if index > 0 && self.one_discussion {
+            OpeningRole::OneOf
+        } else {
+            OpeningRole::Alone
+        }
+    }
+}
+
+/// What an open section of a group leads to in a poll cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpeningRole {
+    /// Tried with the rest of its atomic group, once the lecture and a discussion are open
+    Atomic,
+    /// One of a one-discussion group's discussions, tried in order with the others open
+    OneOf,
+    /// A lecture that's only taken with a discussion, so the opening is only reported
+    ReportOnly,
+    /// Enrolled on its own
+    Alone,
+}
+
+/// How often the watchdog looks for stalled monitoring loops
+const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a restart waits for an aborted loop to release its job before skipping the stats flush
+const STALLED_JOB_RELEASE_WAIT: Duration = Duration::from_secs(5);
+
+/// How often the resident job counts are written to the debug log
+const METRICS_LOG_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How often the outbox worker looks for notifications to retry when nothing wakes it
+const OUTBOX_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Notifications the outbox worker delivers per pass
+const OUTBOX_BATCH_SIZE: i64 = 50;
+
+/// How often the outbox worker deletes notifications older than the retention period
+const OUTBOX_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The threshold openings are judged against; 0 (any open seat) while the override is on
+fn effective_seat_threshold(seat_threshold: i64, ignore_threshold: bool) -> i64 {
+    if ignore_threshold { 0 } else { seat_threshold }
+}
+
+/// Writes a job's notifications to the outbox table for `run_outbox` to deliver
+struct DbOutbox {
+    pool: DbPool,
+    user_id: Uuid,
+    job_id: Uuid,
+    wake: Arc<Notify>,
+}
+
+#[async_trait::async_trait]
+impl Outbox for DbOutbox {
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let payload = serde_json::to_value(message)?;
+        crate::db::enqueue_notification(&self.pool, self.user_id, Some(self.job_id), message.channel.as_str(), &payload).await?;
+        self.wake.notify_one();
+        Ok(())
+    }
+}
+
+/// When a monitoring loop last made progress, readable without the job's lock
+#[derive(Clone)]
+struct Heartbeat(Arc<std::sync::Mutex<Instant>>);
+
+impl Heartbeat {
+    fn new() -> Self {
+        Self(Arc::new(std::sync::Mutex::new(Instant::now())))
+    }
+
+    fn beat(&self) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).elapsed()
+    }
+
+    fn is_same(&self, other: &Heartbeat) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A job's spawned monitoring task, as the watchdog sees it
+struct MonitorLoop {
+    user_id: Uuid,
+    task: tokio::task::JoinHandle<()>,
+    heartbeat: Heartbeat,
+    stall_after: Duration,
+    shutdown_tx: tokio::sync::broadcast::Sender<()>,
 }
 
 /// Global state managing all user jobs
@@ -52,99 +281,342 @@ pub struct MultiUserState {
     pub pool: DbPool,
     pub encryption_key: EncryptionKey,
     pub jobs: Arc<RwLock<HashMap<Uuid, Arc<Mutex<UserJob>>>>>,
+    pub settings: ServerConfig,
+    /// Bounds in-flight WebReg requests across every job on this server
+    pub webreg_permits: Arc<Semaphore>,
+    /// Daily request quota per user, shared by all of that user's jobs
+    quotas: Mutex<HashMap<Uuid, Arc<RequestQuota>>>,
+    /// Monitoring tasks of running jobs, checked by the watchdog
+    loops: Arc<Mutex<HashMap<Uuid, MonitorLoop>>>,
+    /// Woken when a notification is queued so the outbox worker delivers it right away
+    outbox_wake: Arc<Notify>,
 }
 
 impl MultiUserState {
-    pub fn new(pool: DbPool, encryption_key: EncryptionKey) -> Self {
+    pub fn new(pool: DbPool, encryption_key: EncryptionKey, settings: ServerConfig) -> Self {
+        let webreg_permits = Arc::new(Semaphore::new(settings.max_concurrent_requests));
+
         Self {
             pool,
             encryption_key,
             jobs: Arc::new(RwLock::new(HashMap::new())),
+            settings,
+            webreg_permits,
+            quotas: Mutex::new(HashMap::new()),
+            loops: Arc::new(Mutex::new(HashMap::new())),
+            outbox_wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// The user's quota, picking up today's count from the database the first time
+    async fn request_quota(&self, user_id: Uuid) -> Result<Arc<RequestQuota>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut quotas = self.quotas.lock().await;
+        if let Some(quota) = quotas.get(&user_id) {
+            return Ok(Arc::clone(quota));
         }
+
+        let today = local_now().date_naive();
+        let count = crate::db::get_request_count(&self.pool, user_id, today).await?;
+        let quota = Arc::new(RequestQuota::new(self.settings.daily_request_quota, today, count.max(0) as u64));
+        quotas.insert(user_id, Arc::clone(&quota));
+        Ok(quota)
     }
 
-    /// Create a new job for a user
+    /// Create a new job for a user. With an idempotency key, a repeated
+    /// request returns the job created by the first one.
     pub async fn create_job(
         &self,
         user_id: Uuid,
-        request: CreateJobRequest,
-    ) -> Result<Uuid, Box<dyn std::error::Error + Send + Sync>> {
+        mut request: CreateJobRequest,
+        idempotency_key: Option<&str>,
+    ) -> Result<Uuid, Error> {
+        request.normalize();
+        Self::dedupe_sections(user_id, &mut request);
+
+        if let Some(key) = idempotency_key {
+            if let Some(job_id) = crate::db::get_idempotent_job(&self.pool, user_id, key).await? {
+                return Ok(job_id);
+            }
+        }
+
+        self.validate_cookie(&request.cookie, &request.term).await?;
+
+        // Job, courses, sections and stats are committed together so a
+        // failure partway through never leaves a half-created job behind
+        let mut tx = self.pool.begin().await?;
+        self.check_job_limit(&mut *tx, user_id, 1).await?;
+        let job_id = self.insert_job(&mut *tx, user_id, &request).await?;
+
+        if let Some(key) = idempotency_key {
+            if !crate::db::claim_idempotency_key(&mut *tx, user_id, key, job_id).await? {
+                // A concurrent request with the same key won; discard ours and return its job
+                tx.rollback().await?;
+                return crate::db::get_idempotent_job(&self.pool, user_id, key)
+                    .await?
+                    .ok_or_else(|| Error::Conflict("Idempotency key is held by another request".to_string()));
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(job_id)
+    }
+
+    /// Create several jobs at once; either all of them are stored or none are
+    pub async fn create_jobs(
+        &self,
+        user_id: Uuid,
+        mut requests: Vec<CreateJobRequest>,
+    ) -> Result<Vec<Uuid>, Error> {
+        for request in &mut requests {
+            request.normalize();
+            Self::dedupe_sections(user_id, request);
+            self.validate_cookie(&request.cookie, &request.term).await?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+        // The limit applies to the combined total, not each job individually
+        self.check_job_limit(&mut *tx, user_id, requests.len()).await?;
+        let mut job_ids = Vec::with_capacity(requests.len());
+
+        for request in &requests {
+            // Returning early drops the transaction, which rolls it back
+            job_ids.push(self.insert_job(&mut *tx, user_id, request).await?);
+        }
+
+        tx.commit().await?;
+        Ok(job_ids)
+    }
+
+    /// Drop repeated sections from a new job, warning about each one
+    fn dedupe_sections(user_id: Uuid, request: &mut CreateJobRequest) {
+        for repeat in request.dedupe_sections() {
+            warn!("Job for user {} lists {} more than once; monitoring it once", user_id, repeat);
+        }
+    }
+
+    /// Build a creation request from an existing job, applying the overrides
+    pub async fn clone_request(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+        overrides: CloneJobRequest,
+    ) -> Result<CreateJobRequest, Error> {
+        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+        // A new cookie means a new session, so the old backup is dropped with the old cookie
+        let (cookie, backup_cookie) = match overrides.cookie.filter(|c| !c.trim().is_empty()) {
+            Some(cookie) => (cookie, None),
+            None => {
+                let cookie = self.encryption_key.decrypt(&job.cookie_encrypted, &job.encryption_nonce)
+                    .map_err(|e| Error::Encryption(e.to_string()))?;
+                let backup = match (&job.backup_cookie_encrypted, &job.backup_encryption_nonce) {
+                    (Some(encrypted), Some(nonce)) => Some(
+                        self.encryption_key.decrypt(encrypted, nonce).map_err(|e| Error::Encryption(e.to_string()))?
+                    ),
+                    _ => None,
+                };
+                (cookie, backup)
+            }
+        };
+
+        let term = overrides.term.filter(|t| !t.trim().is_empty());
+        let mut request = self.job_request(job).await?;
+        if let Some(term) = term {
+            // Section ids are numbered per term, so only the codes carry over
+            if term != request.term {
+                for section in request.courses.iter_mut().flat_map(|course| course.sections.iter_mut()) {
+                    section.section_id = None;
+                }
+            }
+            request.term = term;
+        }
+        request.cookie = cookie;
+        request.backup_cookie = backup_cookie;
+        Ok(request)
+    }
+
+    /// A job's courses and settings for `GET /api/jobs/:job_id/export`, leaving out its cookies
+    pub async fn export_job(&self, job_id: Uuid, user_id: Uuid) -> Result<JobExport, Error> {
+        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+        Ok(JobExport::from(self.job_request(job).await?))
+    }
+
+    /// The creation request a stored job was made from, with empty cookies
+    async fn job_request(&self, job: Job) -> Result<CreateJobRequest, Error> {
+        let courses = self.load_course_sections(job.id)
+            .await?
+            .into_iter()
+            .map(|course| CourseRequest {
+                department: course.department,
+                course_code: course.course_code,
+                sections: course.sections
+                    .into_iter()
+                    .map(|group| SectionRequest {
+                        lecture: group.lecture,
+                        discussions: group.discussions,
+                        section_id: group.section_id,
+                        enroll_target: group.enroll_target,
+                        atomic: group.atomic,
+                        one_discussion: group.one_discussion,
+                        discussion_required: group.discussion_required,
+                    })
+                    .collect(),
+                section_pattern: course.section_pattern,
+                role: course.role,
+            })
+            .collect();
+
+        Ok(CreateJobRequest {
+            term: job.term,
+            polling_interval: job.polling_interval,
+            cookie: String::new(),
+            seat_threshold: job.seat_threshold,
+            monitoring_mode: job.monitoring_mode.parse().map_err(Error::InvalidInput)?,
+            courses,
+            enroll: job.enroll,
+            verify_enrollment: job.verify_enrollment,
+            stop_on_first_success: job.stop_on_first_success,
+            max_run_duration_hours: job.max_run_duration_hours,
+            cookie_refresh_interval: job.cookie_refresh_interval,
+            threshold_direction: job.threshold_direction,
+            backup_cookie: None,
+            max_concurrent_enrolls: job.max_concurrent_enrolls,
+            stop_cycle_on_success: job.stop_cycle_on_success,
+            watch_enrolled: job.watch_enrolled,
+            poll_immediately: job.poll_immediately,
+            enrollment_opens_at: job.enrollment_opens_at,
+            force_enroll: job.force_enroll,
+            ignore_threshold: job.ignore_threshold,
+        })
+    }
+
+    /// Check the cookie against WebReg so a bad one is rejected at creation, not on start
+    async fn validate_cookie(&self, cookie: &str, term: &str) -> Result<(), Error> {
+        let wrapper = cookie_wrapper(cookie, self.settings.proxy_url.as_deref(), self.settings.webreg_timeout)
+            .ok_or(Error::CookieExpired)?;
+        // Counts against the server-wide cap like any job's request
+        let _permit = self.webreg_permits.acquire().await;
+        match check_session(&wrapper, term).await {
+            SessionStatus::Valid => Ok(()),
+            SessionStatus::Expired => Err(Error::CookieExpired),
+            SessionStatus::TermUnavailable => {
+                Err(Error::InvalidInput(format!("Term {} is not available to this WebReg account", term)))
+            }
+            SessionStatus::Unreachable(reason) => Err(Error::WebRegUnavailable(reason)),
+        }
+    }
+
+    /// Test a pasted cookie against WebReg without storing anything
+    pub async fn check_cookie(&self, request: &ValidateCookieRequest) -> Result<CookieValidation, Error> {
+        let invalid = CookieValidation { valid: false, terms: Vec::new() };
+        let Some(wrapper) = cookie_wrapper(&request.cookie, self.settings.proxy_url.as_deref(), self.settings.webreg_timeout) else {
+            return Ok(invalid);
+        };
+        // Held for both the session check and the term listing
+        let _permit = self.webreg_permits.acquire().await;
+        let valid = match check_session(&wrapper, &request.term).await {
+            SessionStatus::Valid => true,
+            // The cookie works, so still list the terms it can use
+            SessionStatus::TermUnavailable => false,
+            SessionStatus::Expired => return Ok(invalid),
+            SessionStatus::Unreachable(reason) => return Err(Error::WebRegUnavailable(reason)),
+        };
+
+        // The session check already passed, so a failed listing doesn't make the cookie invalid
+        let terms = accessible_terms(&wrapper).await.unwrap_or_else(|e| {
+            error!("Failed to list accessible terms: {:?}", e);
+            Vec::new()
+        });
+        Ok(CookieValidation { valid, terms })
+    }
+
+    /// Reject adding `additional` jobs when it would exceed the per-user limit.
+    /// Run inside the insert transaction; the user's row stays locked until it ends.
+    async fn check_job_limit(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        additional: usize,
+    ) -> Result<(), Error> {
+        let limit = self.settings.max_jobs_per_user;
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let existing = crate::db::count_user_jobs_for_update(conn, user_id).await? as usize;
+        if existing + additional > limit {
+            return Err(Error::LimitReached(format!(
+                "Job limit reached: {} existing + {} new exceeds the limit of {}",
+                existing, additional, limit
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Insert a job with its courses, sections and stats row
+    async fn insert_job(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        request: &CreateJobRequest,
+    ) -> Result<Uuid, Error> {
+        // Reject directions the monitoring loop wouldn't understand
+        request.threshold_direction.parse::<ThresholdDirection>().map_err(Error::InvalidInput)?;
+        if request.max_concurrent_enrolls < 1 {
+            return Err(Error::InvalidInput("max_concurrent_enrolls must be at least 1".to_string()));
+        }
+        request.check_size(self.settings.max_courses_per_job, self.settings.max_sections_per_course)
+            .map_err(Error::InvalidInput)?;
+
         // Encrypt the cookie
-        let (cookie_encrypted, encryption_nonce) = self.encryption_key.encrypt(&request.cookie)?;
+        let (cookie_encrypted, encryption_nonce) = self.encryption_key.encrypt(&request.cookie)
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+        let backup_cookie = match request.backup_cookie.as_deref().filter(|c| !c.trim().is_empty()) {
+            Some(cookie) => Some(self.encryption_key.encrypt(cookie).map_err(|e| Error::Encryption(e.to_string()))?),
+            None => None,
+        };
 
         // Create job in database
         let job = crate::db::create_job(
-            &self.pool,
+            &mut *conn,
             user_id,
-            &request,
+            request,
             &cookie_encrypted,
             &encryption_nonce,
+            backup_cookie.as_ref().map(|(encrypted, nonce)| (encrypted.as_str(), nonce.as_str())),
         )
         .await?;
 
         // Create courses in database
-        let courses = crate::db::create_courses(&self.pool, job.id, &request.courses).await?;
+        let courses = crate::db::create_courses(&mut *conn, job.id, &request.courses).await?;
 
         // Create sections for each course
         for (i, course) in courses.iter().enumerate() {
-            crate::db::create_sections(&self.pool, course.id, &request.courses[i].sections).await?;
+            crate::db::create_sections(&mut *conn, course.id, &request.courses[i].sections).await?;
         }
 
         // Initialize stats
-        crate::db::init_job_stats(&self.pool, job.id).await?;
+        crate::db::init_job_stats(&mut *conn, job.id).await?;
 
         Ok(job.id)
     }
 
-    /// Start a job for a user
-    pub async fn start_job(&self, job_id: Uuid, user_id: Uuid) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Get job from database
-        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
-            .await?
-            .ok_or("Job not found")?;
-
-        // Check if job is already running
-        let jobs_read = self.jobs.read().await;
-        if jobs_read.contains_key(&job_id) {
-            return Err("Job is already running".into());
-        }
-        drop(jobs_read);
-
-        // Decrypt cookie
-        let cookie = self.encryption_key.decrypt(&job.cookie_encrypted, &job.encryption_nonce)?;
-
-        // Create WebReg wrapper
-        let wrapper = WebRegWrapper::builder()
-            .with_cookies(&cookie)
-            .try_build_wrapper()
-            .ok_or("Failed to create WebRegWrapper")?;
-
-        // Get courses and sections
-        let courses = crate::db::get_job_courses(&self.pool, job_id).await?;
-        let mut course_sections = Vec::new();
-
-        for course in courses {
-            let sections = crate::db::get_course_sections(&self.pool, course.id).await?;
-            let section_groups: Vec<SectionGroup> = sections
-                .iter()
-                .map(|s| {
-                    let discussions: Vec<String> = serde_json::from_value(s.discussions.clone())
-                        .unwrap_or_default();
-                    SectionGroup {
-                        lecture: s.lecture.clone(),
-                        discussions,
-                    }
-                })
-                .collect();
-
-            course_sections.push(CourseWithSections {
-                department: course.department,
-                course_code: course.course_code,
-                sections: section_groups,
-            });
-        }
+    /// The user's saved job defaults, empty if they never set any
+    pub async fn job_defaults(&self, user_id: Uuid) -> Result<JobDefaults, Error> {
+        Ok(crate::db::get_user_preferences(&self.pool, user_id).await?
+            .map(JobDefaults::from)
+            .unwrap_or_default())
+    }
 
-        // Get notification settings
+    /// A notifier for the user's current notification settings, sending directly
+    async fn user_notifier(&self, user_id: Uuid) -> Result<Notifier, Error> {
         let notification_settings = crate::db::get_or_create_notification_settings(&self.pool, user_id).await?;
 
         // Decrypt gmail password if present
@@ -152,10 +624,19 @@ impl MultiUserState {
             &notification_settings.gmail_app_password_encrypted,
             &notification_settings.gmail_encryption_nonce,
         ) {
-            Some(self.encryption_key.decrypt(encrypted, nonce)?)
+            Some(self.encryption_key.decrypt(encrypted, nonce).map_err(|e| Error::Encryption(e.to_string()))?)
         } else {
             None
         };
+        let pushover_token = match (
+            &notification_settings.pushover_api_token_encrypted,
+            &notification_settings.pushover_encryption_nonce,
+        ) {
+            (Some(encrypted), Some(nonce)) => {
+                Some(self.encryption_key.decrypt(encrypted, nonce).map_err(|e| Error::Encryption(e.to_string()))?)
+            }
+            _ => notification_settings.pushover_api_token.clone(),
+        };
 
         // Create notifier configuration
         let email_recipients: Vec<String> = serde_json::from_value(notification_settings.email_recipients.clone())
@@ -166,9 +647,112 @@ impl MultiUserState {
             gmail_app_password: gmail_password.unwrap_or_default(),
             email_recipients,
             discord_webhook_url: notification_settings.discord_webhook_url.clone().unwrap_or_default(),
+            success_webhook_url: notification_settings.success_webhook_url.clone().unwrap_or_default(),
+            alert_webhook_url: notification_settings.alert_webhook_url.clone().unwrap_or_default(),
+            alert_email_recipients: serde_json::from_value(notification_settings.alert_email_recipients.clone())
+                .unwrap_or_default(),
+            notify_on_close: notification_settings.notify_on_close,
+            notify_on_recovery: notification_settings.notify_on_recovery,
+            discord_format: notification_settings.discord_format.parse().unwrap_or_default(),
+            pushover_user_key: notification_settings.pushover_user_key.clone().unwrap_or_default(),
+            pushover_api_token: pushover_token.unwrap_or_default(),
+            email_min_interval_secs: self.settings.email_min_interval_secs,
+            discord_min_interval_secs: self.settings.discord_min_interval_secs,
+            pushover_min_interval_secs: self.settings.pushover_min_interval_secs,
+        };
+
+        Ok(Notifier::with_proxy(&notification_config, self.settings.proxy_url.as_deref())?)
+    }
+
+    /// Start a job for a user
+    pub async fn start_job(&self, job_id: Uuid, user_id: Uuid) -> Result<(), Error> {
+        self.launch_job(job_id, user_id, false).await
+    }
+
+    /// Restart the jobs the database lists as active, e.g. after a server restart. At most
+    /// `resume_concurrency` are set up at once, and each waits a random part of its polling
+    /// interval before its first WebReg request.
+    pub async fn resume_active_jobs(self: Arc<Self>) {
+        let jobs = match crate::db::get_all_active_jobs(&self.pool).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("Could not load active jobs to resume: {}", e);
+                return;
+            }
+        };
+        if jobs.is_empty() {
+            return;
+        }
+
+        let total = jobs.len();
+        let started = Instant::now();
+        info!("Resuming {} active jobs, {} at a time", total, self.settings.resume_concurrency);
+
+        let state = &*self;
+        let resumed = stream::iter(jobs)
+            .map(|job| async move {
+                match state.launch_job(job.id, job.user_id, true).await {
+                    Ok(()) | Err(Error::Conflict(_)) => true,
+                    Err(e) => {
+                        warn!("Could not resume job {}: {}", job.id, e);
+                        // Listed as active with nothing running would mislead the dashboard
+                        Self::persist_job_status(&state.pool, job.id, false, false).await;
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(self.settings.resume_concurrency)
+            .filter(|resumed| future::ready(*resumed))
+            .count()
+            .await;
+
+        info!("Resumed {} of {} active jobs in {}", resumed, total, format_duration(started.elapsed()));
+    }
+
+    /// Set up and spawn a job's monitoring loop; a `staggered` one delays all of its WebReg requests
+    async fn launch_job(&self, job_id: Uuid, user_id: Uuid, staggered: bool) -> Result<(), Error> {
+        // Get job from database
+        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+        // Check if job is already running
+        let jobs_read = self.jobs.read().await;
+        if jobs_read.contains_key(&job_id) {
+            return Err(Error::Conflict("Job is already running".to_string()));
+        }
+        drop(jobs_read);
+
+        let mode: MonitoringMode = job.monitoring_mode.parse().map_err(Error::InvalidInput)?;
+        let threshold_direction: ThresholdDirection = job.threshold_direction.parse().map_err(Error::InvalidInput)?;
+
+        // Decrypt cookie
+        let cookie = self.encryption_key.decrypt(&job.cookie_encrypted, &job.encryption_nonce)
+            .map_err(|e| Error::Encryption(e.to_string()))?;
+        let backup_cookie = match (&job.backup_cookie_encrypted, &job.backup_encryption_nonce) {
+            (Some(encrypted), Some(nonce)) => Some(
+                self.encryption_key.decrypt(encrypted, nonce).map_err(|e| Error::Encryption(e.to_string()))?
+            ),
+            _ => None,
         };
 
-        let notifier = Notifier::new(&notification_config)?;
+        // Create WebReg wrapper
+        let wrapper = cookie_wrapper(&cookie, self.settings.proxy_url.as_deref(), self.settings.webreg_timeout)
+            .ok_or(Error::CookieExpired)?;
+
+        // Get courses and sections; backups are done once a primary course was enrolled
+        let mut course_sections = self.load_course_sections(job_id).await?;
+        if job.primary_enrolled {
+            course_sections.retain(|course| course.role != Some(CourseRole::Backup));
+        }
+
+        // Notifications go through the outbox so a restart can't lose them
+        let notifier = self.user_notifier(user_id).await?.with_outbox(Arc::new(DbOutbox {
+            pool: self.pool.clone(),
+            user_id,
+            job_id,
+            wake: Arc::clone(&self.outbox_wake),
+        }));
 
         // Get or initialize stats
         let stats_db = crate::db::get_job_stats(&self.pool, job_id).await?
@@ -185,6 +769,15 @@ impl MultiUserState {
             section_failures: serde_json::from_value(stats_db.section_failures).unwrap_or_default(),
         };
 
+        let quota = self.request_quota(user_id).await?;
+
+        // Very short intervals would hammer WebReg with session checks
+        let cookie_refresh_interval = job.cookie_refresh_interval.max(MIN_COOKIE_REFRESH_INTERVAL);
+        if cookie_refresh_interval != job.cookie_refresh_interval {
+            log::warn!("Job {} cookie_refresh_interval of {}s raised to the {}s minimum",
+                       job_id, job.cookie_refresh_interval, MIN_COOKIE_REFRESH_INTERVAL);
+        }
+
         // Create shutdown channel
         let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
 
@@ -194,18 +787,68 @@ impl MultiUserState {
             user_id,
             term: job.term.clone(),
             wrapper: Arc::new(wrapper),
+            backup_cookie,
             notifier,
             stats,
             courses: course_sections,
-            polling_interval: job.polling_interval as u64,
+            // Jobs saved before the floor existed (or under a lower one) are raised too
+            polling_interval: (job.polling_interval.max(0) as u64).max(self.settings.min_polling_interval),
             seat_threshold: job.seat_threshold as i64,
+            threshold_direction,
+            mode,
+            enroll: job.enroll,
+            verify_enrollment: job.verify_enrollment,
+            stop_on_first_success: job.stop_on_first_success,
+            max_concurrent_enrolls: job.max_concurrent_enrolls.max(1) as usize,
+            stop_cycle_on_success: job.stop_cycle_on_success,
+            watch_enrolled: job.watch_enrolled,
+            held_sections: serde_json::from_value(job.held_sections.clone()).unwrap_or_default(),
+            filled_groups: serde_json::from_value(job.filled_groups.clone()).unwrap_or_default(),
+            poll_immediately: job.poll_immediately,
+            enrollment_opens_at: job.enrollment_opens_at,
+            force_enroll: job.force_enroll,
+            ignore_threshold: job.ignore_threshold,
+            awaiting_enrollment_window: false,
+            paused: false,
             is_running: true,
             is_connected: true,
-            last_check_time: Local::now().to_string(),
+            last_check_time: local_now().to_string(),
             start_time: SystemTime::now(),
             shutdown_tx: shutdown_tx.clone(),
+            eligibility: Vec::new(),
+            latency: LatencyTracker::default(),
+            throttle: ThrottleBackoff::default(),
+            openings: OpeningFilter::new(self.settings.consecutive_polls),
+            poll_backoff: PollBackoff::new(self.settings.poll_backoff),
+            quota,
+            recheck_freshness: Duration::from_millis(self.settings.recheck_freshness_ms),
+            max_run_duration: job.max_run_duration_hours
+                .filter(|hours| *hours > 0)
+                .map(|hours| Duration::from_secs(hours as u64 * 3600)),
+            two_phase_enroll: self.settings.two_phase_enroll,
+            plan_unit_count: self.settings.plan_unit_count,
+            grace_retry: self.settings.grace_retry,
+            cycle_retry_budget: self.settings.cycle_retry_budget,
+            cookie_refresh_interval: cookie_refresh_interval as u64,
+            cookie_refresh_jitter: self.settings.cookie_refresh_jitter,
+            proxy_url: self.settings.proxy_url.clone(),
+            webreg_timeout: self.settings.webreg_timeout,
+            section_timeout: self.settings.section_timeout,
+            session_check_cycles: self.settings.session_check_cycles,
+            max_sections_per_course: self.settings.max_sections_per_course,
+            db_degraded: false,
+            heartbeat: Heartbeat::new(),
         }));
 
+        {
+            let mut job_lock = user_job.lock().await;
+            if job_lock.enroll && !job_lock.enrollment_open() {
+                job_lock.awaiting_enrollment_window = true;
+                info!("Job {} only monitors until its enrollment window opens at {}", job_id,
+                    job_lock.enrollment_opens_at.map(|opens| opens.to_rfc3339()).unwrap_or_default());
+            }
+        }
+
         // Add to jobs map
         let mut jobs_write = self.jobs.write().await;
         jobs_write.insert(job_id, user_job.clone());
@@ -214,29 +857,50 @@ impl MultiUserState {
         // Update job status in database
         crate::db::update_job_status(&self.pool, job_id, true, true).await?;
 
+        // A healthy loop goes round at least once per poll or cookie check
+        let (stall_after, heartbeat) = {
+            let job_lock = user_job.lock().await;
+            let stall_after = Duration::from_secs(job_lock.polling_interval.max(job_lock.cookie_refresh_interval))
+                * self.settings.watchdog_stall_intervals;
+            (stall_after, job_lock.heartbeat.clone())
+        };
+
         // Spawn monitoring task
         let pool_clone = self.pool.clone();
-        tokio::spawn(async move {
-            Self::run_monitoring_loop(user_job, pool_clone).await;
+        let webreg_permits = Arc::clone(&self.webreg_permits);
+        let stats_flush_interval = Duration::from_secs(self.settings.stats_flush_interval);
+        let jobs = Arc::clone(&self.jobs);
+        // Held until the entry is in, so a loop that ends at once can't look for it too early
+        let mut loops = self.loops.lock().await;
+        let task = tokio::spawn({
+            let heartbeat = heartbeat.clone();
+            let loops = Arc::clone(&self.loops);
+            async move {
+                Self::run_monitoring_loop(user_job, pool_clone, webreg_permits, stats_flush_interval, jobs, loops, heartbeat, staggered).await;
+            }
         });
+        loops.insert(job_id, MonitorLoop { user_id, task, heartbeat, stall_after, shutdown_tx });
 
         Ok(())
     }
 
     /// Stop a job
-    pub async fn stop_job(&self, job_id: Uuid) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn stop_job(&self, job_id: Uuid) -> Result<(), Error> {
         let jobs_read = self.jobs.read().await;
-        let job = jobs_read.get(&job_id).ok_or("Job not running")?;
+        let job = jobs_read.get(&job_id).ok_or_else(|| Error::Conflict("Job not running".to_string()))?;
 
-        let job_lock = job.lock().await;
-        let _ = job_lock.shutdown_tx.send(());
-        drop(job_lock);
+        // A cycle stuck on WebReg holds the job's lock, so the signal goes out without it
+        match self.loops.lock().await.get(&job_id) {
+            Some(monitor) => { let _ = monitor.shutdown_tx.send(()); }
+            None => { let _ = job.lock().await.shutdown_tx.send(()); }
+        }
         drop(jobs_read);
 
         // Remove from jobs map
         let mut jobs_write = self.jobs.write().await;
         jobs_write.remove(&job_id);
         drop(jobs_write);
+        self.loops.lock().await.remove(&job_id);
 
         // Update database
         crate::db::update_job_status(&self.pool, job_id, false, false).await?;
@@ -244,6 +908,71 @@ impl MultiUserState {
         Ok(())
     }
 
+    /// Pause or resume a running job's polling. It stays loaded, so resuming takes effect
+    /// from the next cycle without a restart.
+    pub async fn set_paused(&self, job_id: Uuid, paused: bool) -> Result<(), Error> {
+        let job = self.jobs.read().await.get(&job_id).cloned()
+            .ok_or_else(|| Error::Conflict("Job not running".to_string()))?;
+        let mut job_lock = job.lock().await;
+        if job_lock.paused != paused {
+            job_lock.paused = paused;
+            info!("Job {} {}", job_id, if paused { "paused" } else { "resumed" });
+        }
+        Ok(())
+    }
+
+    /// Turn the threshold override on or off, taking effect from a running job's next cycle
+    pub async fn set_ignore_threshold(&self, job_id: Uuid, user_id: Uuid, ignore_threshold: bool) -> Result<(), Error> {
+        if !crate::db::set_ignore_threshold(&self.pool, job_id, user_id, ignore_threshold).await? {
+            return Err(Error::NotFound("Job not found".to_string()));
+        }
+
+        let running = self.jobs.read().await.get(&job_id).cloned();
+        if let Some(job) = running {
+            job.lock().await.ignore_threshold = ignore_threshold;
+        }
+
+        info!("Job {} threshold override {}", job_id, if ignore_threshold { "on: attempting on any open seat" } else { "off" });
+        Ok(())
+    }
+
+    /// Run one monitoring and enroll cycle now, outside the loop's schedule. Waits for
+    /// the loop's cycle in progress, if any, since both hold the job's lock.
+    pub async fn poll_now(&self, job_id: Uuid, user_id: Uuid) -> Result<Vec<SectionPollResult>, Error> {
+        let job = self.jobs.read().await.get(&job_id).cloned()
+            .ok_or_else(|| Error::Conflict("Job not running".to_string()))?;
+        let mut job_lock = job.lock().await;
+        if job_lock.user_id != user_id {
+            return Err(Error::NotFound("Job not found".to_string()));
+        }
+        if !job_lock.is_running {
+            return Err(Error::Conflict("Job not running".to_string()));
+        }
+        if !job_lock.is_connected {
+            return Err(Error::CookieExpired);
+        }
+        if job_lock.throttle.cooldown_remaining().is_some() {
+            return Err(Error::WebRegThrottled);
+        }
+        if job_lock.quota.is_exhausted() {
+            return Err(Error::LimitReached("Today's WebReg request quota is used up".to_string()));
+        }
+
+        info!("Manual poll of job {}", job_id);
+        Ok(Self::poll_cycle(&mut job_lock, &self.pool, &self.webreg_permits, false).await)
+    }
+
+    /// Stop any user's job (admin). Returns false when the job doesn't exist.
+    pub async fn admin_stop_job(&self, job_id: Uuid) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        if self.jobs.read().await.contains_key(&job_id) {
+            self.stop_job(job_id).await?;
+            return Ok(true);
+        }
+
+        // Not running on this server, but the database may still list it as active
+        crate::db::deactivate_job(&self.pool, job_id).await
+    }
+
     /// Get job status
     pub async fn get_job_status(&self, job_id: Uuid) -> Option<JobStatusInfo> {
         let jobs_read = self.jobs.read().await;
@@ -252,142 +981,1315 @@ impl MultiUserState {
 
         Some(JobStatusInfo {
             is_running: job_lock.is_running,
+            is_paused: job_lock.paused,
             is_connected: job_lock.is_connected,
             last_check_time: job_lock.last_check_time.clone(),
             stats: job_lock.stats.clone(),
+            eligibility: job_lock.eligibility.clone(),
+            avg_latency_ms: job_lock.latency.average_ms(),
+            p95_latency_ms: job_lock.latency.p95_ms(),
+            db_degraded: job_lock.db_degraded,
         })
     }
 
-    /// Monitoring loop for a user job
-    async fn run_monitoring_loop(job: Arc<Mutex<UserJob>>, pool: DbPool) {
-        let mut shutdown_rx = {
+    /// Check once whether enrollment can ever succeed (holds, prerequisites, conflicts)
+    async fn check_eligibility(job: Arc<Mutex<UserJob>>, webreg_permits: Arc<Semaphore>) {
+        let (wrapper, term, courses) = {
             let job_lock = job.lock().await;
-            job_lock.shutdown_tx.subscribe()
+            (Arc::clone(&job_lock.wrapper), job_lock.term.clone(), job_lock.courses.clone())
+        };
+        let mut eligibility = Vec::new();
+        for course in &courses {
+            let sections: Vec<String> = course.sections
+                .iter()
+                .flat_map(|g| g.sections().cloned())
+                .collect();
+            let _permit = webreg_permits.acquire().await;
+            eligibility.extend(
+                check_course_eligibility(&wrapper, &term, &course.department, &course.course_code, &sections).await
+            );
+        }
+        job.lock().await.eligibility = eligibility;
+    }
+
+    /// Add a group for each listed section that matches its course's `section_pattern`
+    /// and isn't monitored yet
+    async fn expand_section_patterns(job: &Mutex<UserJob>, webreg_permits: &Semaphore) {
+        let (job_id, wrapper, term, quota, notifier, latency, throttle, budget, max_sections, patterns) = {
+            let job_lock = job.lock().await;
+            let patterns: Vec<(String, String, String)> = job_lock.courses
+                .iter()
+                .filter_map(|course| Some((course.department.clone(), course.course_code.clone(), course.section_pattern.clone()?)))
+                .collect();
+            (
+                job_lock.job_id,
+                Arc::clone(&job_lock.wrapper),
+                job_lock.term.clone(),
+                Arc::clone(&job_lock.quota),
+                job_lock.notifier.clone(),
+                job_lock.latency.clone(),
+                job_lock.throttle.clone(),
+                RetryBudget::new(job_lock.cycle_retry_budget),
+                job_lock.max_sections_per_course,
+                patterns,
+            )
+        };
+
+        for (department, course_code, pattern) in patterns {
+            // Same retries and throttle handling as a poll's listing fetch
+            let listing = {
+                let _permit = webreg_permits.acquire().await;
+                fetch_course_listing(
+                    &QuotaClient::new(wrapper.as_ref(), &quota),
+                    &term,
+                    &department,
+                    &course_code,
+                    &notifier,
+                    &latency,
+                    &throttle,
+                    RetryPolicy::default(),
+                    &budget,
+                ).await
+            };
+            let listing = match listing {
+                Ok(listing) => listing,
+                Err(e) => {
+                    warn!("Could not expand section pattern {} for {} {} in job {}: {:?}",
+                        pattern, department, course_code, job_id, e);
+                    continue;
+                }
+            };
+
+            let mut job_lock = job.lock().await;
+            let Some(course) = job_lock.courses
+                .iter_mut()
+                .find(|course| course.department == department && course.course_code == course_code)
+            else {
+                continue;
+            };
+            for info in listing.sections().iter().filter(|info| section_matches(&pattern, &info.section_code)) {
+                if course.sections.iter().flat_map(|group| group.sections()).any(|s| *s == info.section_code || *s == info.section_id) {
+                    continue;
+                }
+                let monitored: usize = course.sections.iter().map(|group| 1 + group.discussions.len()).sum();
+                if max_sections > 0 && monitored >= max_sections {
+                    warn!("Job {} hit the {}-section limit expanding {} for {} {}", job_id, max_sections, pattern, department, course_code);
+                    break;
+                }
+                info!("Job {} now monitors {} {} {} (matches {})", job_id, department, course_code, info.section_code, pattern);
+                course.sections.push(SectionGroup {
+                    lecture: info.section_code.clone(),
+                    discussions: Vec::new(),
+                    section_id: None,
+                    enroll_target: None,
+                    atomic: false,
+                    one_discussion: false,
+                    discussion_required: false,
+                });
+            }
+        }
+    }
+
+    /// Monitoring loop for a user job
+    async fn run_monitoring_loop(
+        job: Arc<Mutex<UserJob>>,
+        pool: DbPool,
+        webreg_permits: Arc<Semaphore>,
+        stats_flush_interval: Duration,
+        jobs: Arc<RwLock<HashMap<Uuid, Arc<Mutex<UserJob>>>>>,
+        loops: Arc<Mutex<HashMap<Uuid, MonitorLoop>>>,
+        heartbeat: Heartbeat,
+        staggered: bool,
+    ) {
+        let mut shutdown_rx = {
+            let job_lock = job.lock().await;
+            job_lock.shutdown_tx.subscribe()
         };
 
-        let cookie_refresh_interval = 480; // 8 minutes
-        let mut cookie_refresh_timer = tokio::time::interval(Duration::from_secs(cookie_refresh_interval));
-
+        let (polling_interval, cookie_refresh_interval, cookie_refresh_jitter, poll_immediately, session_check_cycles) = {
+            let job_lock = job.lock().await;
+            (
+                job_lock.polling_interval,
+                job_lock.cookie_refresh_interval,
+                job_lock.cookie_refresh_jitter,
+                job_lock.poll_immediately,
+                job_lock.session_check_cycles,
+            )
+        };
+
+        // Jobs resumed together at startup would otherwise all hit WebReg at once
+        if staggered {
+            sleep(initial_offset(polling_interval)).await;
+        }
+
+        // Sections matched by a pattern are monitored from the first poll
+        Self::expand_section_patterns(&job, &webreg_permits).await;
+
+        if poll_immediately || staggered {
+            // The eligibility check is informational, so it doesn't hold up the first poll
+            tokio::spawn(Self::check_eligibility(Arc::clone(&job), Arc::clone(&webreg_permits)));
+        } else {
+            Self::check_eligibility(Arc::clone(&job), Arc::clone(&webreg_permits)).await;
+            // Stagger the first poll so jobs started together spread out over the interval
+            sleep(initial_offset(polling_interval)).await;
+        }
+
+        // Jobs resumed together would otherwise all check their cookie on the same tick
+        let first_refresh = if cookie_refresh_jitter { initial_offset(cookie_refresh_interval) } else { Duration::ZERO };
+        let mut cookie_refresh_timer = tokio::time::interval_at(
+            tokio::time::Instant::now() + first_refresh,
+            Duration::from_secs(cookie_refresh_interval),
+        );
+        let mut session_cycles = SessionCheckCycles::new(session_check_cycles);
+
+        // In-memory stats are authoritative; the database copy is refreshed at most this often
+        let mut last_stats_flush = Instant::now();
+
         loop {
+            heartbeat.beat();
             tokio::select! {
                 _ = shutdown_rx.recv() => {
                     info!("Received shutdown signal for job");
-                    let mut job_lock = job.lock().await;
-                    job_lock.is_running = false;
                     break;
                 }
                 _ = cookie_refresh_timer.tick() => {
-                    // TODO: Implement cookie refresh logic
-                    info!("Cookie refresh tick");
+                    session_cycles.checked();
+                    let (job_id, wrapper, term) = {
+                        let job_lock = job.lock().await;
+                        (job_lock.job_id, Arc::clone(&job_lock.wrapper), job_lock.term.clone())
+                    };
+                    let status = {
+                        let _permit = webreg_permits.acquire().await;
+                        check_session(&wrapper, &term).await
+                    };
+                    if let SessionStatus::Unreachable(reason) = &status {
+                        // A network blip says nothing about the cookie, so leave the status alone
+                        warn!("Could not reach WebReg to check the session for job {}: {}", job_id, reason);
+                        continue;
+                    }
+                    let is_valid = status.is_valid();
+
+                    let mut job_lock = job.lock().await;
+                    if status == SessionStatus::TermUnavailable {
+                        // The cookie still works, so neither the backup nor a new cookie would help
+                        if job_lock.is_connected {
+                            job_lock.is_connected = false;
+                            error!("Term {} is no longer available for job {}", term, job_id);
+                            let msg = format!(
+                                "⚠️  The {} term is no longer available in WebReg, so your job is paused.\n\
+                                Time: {}",
+                                term,
+                                local_now().format("%Y-%m-%d %H:%M:%S")
+                            );
+                            job_lock.notifier.send_alert(&msg).await;
+                            Self::write_job_status(&pool, job_id, true, false).await;
+                        }
+                        continue;
+                    }
+                    if !is_valid {
+                        if let Some(backup) = job_lock.backup_cookie.take() {
+                            if Self::fail_over_to_backup(&mut job_lock, &backup, &pool, &webreg_permits).await {
+                                continue;
+                            }
+                        }
+                    }
+
+                    if !is_valid && job_lock.is_connected {
+                        // Cookie just expired (transition from connected to disconnected)
+                        job_lock.is_connected = false;
+                        error!("WebReg cookie has expired for job {}", job_id);
+
+                        let msg = format!(
+                            "⚠️  WebReg Cookie has expired for your {} job!\n\
+                            Time: {}\n\
+                            Update the job's cookie to resume monitoring.",
+                            term,
+                            local_now().format("%Y-%m-%d %H:%M:%S")
+                        );
+                        job_lock.notifier.send_alert(&msg).await;
+                        Self::write_job_status(&pool, job_id, true, false).await;
+                    } else if is_valid && !job_lock.is_connected {
+                        job_lock.is_connected = true;
+                        info!("WebReg session for job {} is valid again", job_id);
+                        Self::write_job_status(&pool, job_id, true, true).await;
+
+                        if job_lock.notifier.notifies_on_recovery() {
+                            let msg = format!(
+                                "✅ WebReg connection restored for your {} job, monitoring resumed.\n\
+                                Time: {}",
+                                term,
+                                local_now().format("%Y-%m-%d %H:%M:%S")
+                            );
+                            job_lock.notifier.send_alert(&msg).await;
+                        }
+                    }
+
+                    // A paused job only keeps its session status current
+                    if job_lock.paused {
+                        continue;
+                    }
+
+                    // Held seats are re-checked on the session check's schedule, not every poll
+                    if is_valid && job_lock.is_running && job_lock.watch_enrolled && !job_lock.held_sections.is_empty() {
+                        Self::check_held_sections(&mut job_lock, &pool, &webreg_permits).await;
+                    }
+
+                    // Section patterns are re-expanded on the same schedule to catch newly added sections
+                    if is_valid && job_lock.courses.iter().any(|course| course.section_pattern.is_some()) {
+                        drop(job_lock);
+                        Self::expand_section_patterns(&job, &webreg_permits).await;
+                    }
                 }
                 _ = async {
                     let mut job_lock = job.lock().await;
 
-                    if !job_lock.is_running || !job_lock.is_connected {
+                    if !job_lock.polls() {
+                        let polling_interval = job_lock.polling_interval;
+                        drop(job_lock);
+                        sleep(Duration::from_secs(polling_interval)).await;
+                        return;
+                    }
+
+                    // WebReg is throttling this job; wait out the cooldown instead of polling into it
+                    if let Some(remaining) = job_lock.throttle.cooldown_remaining() {
+                        drop(job_lock);
+                        sleep(remaining).await;
+                        return;
+                    }
+
+                    // Over today's request quota; the count resets at midnight and monitoring resumes
+                    if job_lock.quota.is_exhausted() {
+                        if job_lock.quota.take_exhausted_notice() {
+                            info!("User {} reached the daily WebReg request quota, pausing job {}",
+                                job_lock.user_id, job_lock.job_id);
+                            let msg = format!(
+                                "⏸️ Your jobs reached today's limit of {} WebReg requests and are paused.\n\
+                                Monitoring resumes automatically at midnight.",
+                                job_lock.quota.limit()
+                            );
+                            job_lock.notifier.send_notification(&msg).await;
+                        }
                         let polling_interval = job_lock.polling_interval;
                         drop(job_lock);
                         sleep(Duration::from_secs(polling_interval)).await;
                         return;
                     }
 
-                    // Get necessary data for monitoring (clone to avoid borrow checker issues)
-                    let job_id = job_lock.job_id;
-                    let term = job_lock.term.clone();
-                    let wrapper = Arc::clone(&job_lock.wrapper);
-                    let notifier = job_lock.notifier.clone();
-                    let courses = job_lock.courses.clone();
-                    let polling_interval = job_lock.polling_interval;
-                    let seat_threshold = job_lock.seat_threshold;
-
-                    // Monitor each course
-                    for course in &courses {
-                        for section_group in &course.sections {
-                            // Monitor lecture
-                            if let Ok(Some(section_id)) = monitor_section_with_retry(
-                                &wrapper,
-                                &term,
-                                &section_group.lecture,
+                    if let Some(max_run_duration) = job_lock.max_run_duration {
+                        let elapsed = job_lock.start_time.elapsed().unwrap_or_default();
+                        if elapsed >= max_run_duration {
+                            info!("Job {} reached its maximum run duration, stopping", job_lock.job_id);
+                            let msg = format!(
+                                "⏱️ Your {} monitoring job stopped automatically after running for {}.\n\
+                                Start it again from the dashboard to keep monitoring.",
+                                job_lock.term,
+                                format_duration(elapsed)
+                            );
+                            job_lock.notifier.send_notification(&msg).await;
+                            Self::stop_from_loop(&mut job_lock);
+                            return;
+                        }
+                    }
+
+                    if job_lock.awaiting_enrollment_window && job_lock.enrollment_open() {
+                        job_lock.awaiting_enrollment_window = false;
+                        info!("Enrollment window opened for job {}, enrolling from now on", job_lock.job_id);
+                        let msg = format!(
+                            "🔓 Your {} enrollment window is open; the job now enrolls when a section opens.",
+                            job_lock.term
+                        );
+                        job_lock.notifier.send_notification(&msg).await;
+                    }
+
+                    Self::poll_cycle(&mut job_lock, &pool, &webreg_permits, true).await;
+                    let (job_id, user_id, polling_interval) = (job_lock.job_id, job_lock.user_id, job_lock.polling_interval);
+
+                    // Update stats in database from a copy, so retrying through a database
+                    // outage doesn't hold the job's lock
+                    let flush = (last_stats_flush.elapsed() >= stats_flush_interval)
+                        .then(|| (job_lock.stats.clone(), Arc::clone(&job_lock.quota)));
+                    drop(job_lock);
+
+                    if let Some((stats, quota)) = flush {
+                        let stats_written = Self::flush_stats(&pool, job_id, &stats).await;
+                        let requests_written = Self::flush_request_count(&pool, user_id, &quota).await;
+                        Self::set_db_degraded(&mut *job.lock().await, !(stats_written && requests_written));
+                        // A failed flush is tried again next cycle instead of after a full interval
+                        if stats_written && requests_written {
+                            last_stats_flush = Instant::now();
+                        }
+                    }
+
+                    sleep(jittered_interval(polling_interval)).await;
+                } => {
+                    // Catch a cookie that expires mid-interval without waiting for the next refresh
+                    if session_cycles.cycle_done() {
+                        cookie_refresh_timer.reset_immediately();
+                    }
+                }
+            }
+        }
+
+        let (job_id, user_id, stats, quota) = {
+            let mut job_lock = job.lock().await;
+            job_lock.is_running = false;
+            (job_lock.job_id, job_lock.user_id, job_lock.stats.clone(), Arc::clone(&job_lock.quota))
+        };
+        // Persist anything the flush throttle was still holding
+        Self::flush_stats(&pool, job_id, &stats).await;
+        Self::flush_request_count(&pool, user_id, &quota).await;
+
+        // However the loop ended, the job leaves both maps and is marked inactive so it can be
+        // started again. A newer run of the same job, started while this one wound down, is left alone.
+        let mut jobs_write = jobs.write().await;
+        let replaced = jobs_write.get(&job_id).is_some_and(|entry| !Arc::ptr_eq(entry, &job));
+        if !replaced {
+            jobs_write.remove(&job_id);
+        }
+        drop(jobs_write);
+
+        let mut loops = loops.lock().await;
+        if loops.get(&job_id).is_some_and(|monitor| monitor.heartbeat.is_same(&heartbeat)) {
+            loops.remove(&job_id);
+        }
+        drop(loops);
+
+        if !replaced {
+            Self::persist_job_status(&pool, job_id, false, false).await;
+        }
+    }
+
+    /// Deliver queued notifications, including any left pending by a previous run, and retry
+    /// failed ones. Runs until the server exits.
+    pub async fn run_outbox(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(OUTBOX_CHECK_INTERVAL);
+        let mut last_cleanup: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.outbox_wake.notified() => {}
+            }
+
+            let retention_days = self.settings.notification_retention_days;
+            if retention_days > 0 && last_cleanup.map_or(true, |at| at.elapsed() >= OUTBOX_CLEANUP_INTERVAL) {
+                match crate::db::delete_sent_notifications(&self.pool, retention_days as i32).await {
+                    Ok(0) => {}
+                    Ok(deleted) => debug!("Deleted {} delivered notifications older than {} days", deleted, retention_days),
+                    Err(e) => error!("Could not clean up the notification outbox: {}", e),
+                }
+                // A failed cleanup waits for the next interval too; old rows only cost space
+                last_cleanup = Some(Instant::now());
+            }
+
+            let queued = match crate::db::get_undelivered_notifications(
+                &self.pool,
+                self.settings.notification_max_attempts as i32,
+                self.settings.notification_retry_delay.as_secs_f64(),
+                OUTBOX_BATCH_SIZE,
+            ).await {
+                Ok(queued) => queued,
+                Err(e) => {
+                    error!("Could not read the notification outbox: {}", e);
+                    continue;
+                }
+            };
+
+            // Built once per pass so settings changed since queueing are picked up
+            let mut notifiers: HashMap<Uuid, Result<Notifier, String>> = HashMap::new();
+            for notification in queued {
+                if !notifiers.contains_key(&notification.user_id) {
+                    let notifier = self.user_notifier(notification.user_id).await.map_err(|e| e.to_string());
+                    notifiers.insert(notification.user_id, notifier);
+                }
+                let delivered = match &notifiers[&notification.user_id] {
+                    Ok(notifier) => match serde_json::from_value::<OutboxMessage>(notification.payload.clone()) {
+                        Ok(message) => notifier.deliver(&message).await.map_err(|e| e.to_string()),
+                        Err(e) => Err(format!("unreadable payload: {}", e)),
+                    },
+                    Err(e) => Err(format!("could not load notification settings: {}", e)),
+                };
+
+                let recorded = match &delivered {
+                    Ok(()) => crate::db::mark_notification_sent(&self.pool, notification.id).await,
+                    Err(e) => {
+                        warn!("Notification {} ({}) for user {} failed on attempt {}: {}",
+                            notification.id, notification.channel, notification.user_id, notification.attempts + 1, e);
+                        crate::db::mark_notification_failed(&self.pool, notification.id, e).await
+                    }
+                };
+                if let Err(e) = recorded {
+                    error!("Could not record delivery of notification {}: {}", notification.id, e);
+                }
+            }
+        }
+    }
+
+    /// Count the jobs held in memory. A job mid-cycle holds its lock, so it's counted without being measured.
+    pub async fn job_metrics(&self) -> Result<JobMapMetrics, Box<dyn std::error::Error + Send + Sync>> {
+        let jobs: Vec<Arc<Mutex<UserJob>>> = self.jobs.read().await.values().cloned().collect();
+        let monitor_loops = self.loops.lock().await.len();
+
+        let mut busy_jobs = 0;
+        let mut approx_bytes = 0;
+        for job in &jobs {
+            match job.try_lock() {
+                Ok(job_lock) => approx_bytes += job_lock.approx_size(),
+                Err(_) => {
+                    busy_jobs += 1;
+                    approx_bytes += std::mem::size_of::<UserJob>();
+                }
+            }
+        }
+
+        Ok(JobMapMetrics {
+            resident_jobs: jobs.len(),
+            monitor_loops,
+            active_jobs: crate::db::count_active_jobs(&self.pool).await?,
+            busy_jobs,
+            approx_bytes,
+        })
+    }
+
+    /// Log the job counts at debug level on a timer. Runs until the server exits.
+    pub async fn run_metrics_log(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(METRICS_LOG_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match self.job_metrics().await {
+                Ok(metrics) => debug!(
+                    "Jobs: {} resident ({} mid-cycle), {} monitor loops, {} active in the database, ~{} KiB",
+                    metrics.resident_jobs, metrics.busy_jobs, metrics.monitor_loops,
+                    metrics.active_jobs, metrics.approx_bytes / 1024
+                ),
+                Err(e) => warn!("Could not collect job metrics: {}", e),
+            }
+        }
+    }
+
+    /// Restart jobs whose monitoring loop panicked or stopped going round. Runs until the server exits.
+    pub async fn run_watchdog(self: Arc<Self>) {
+        if self.settings.watchdog_stall_intervals == 0 {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(WATCHDOG_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for (job_id, reason) in self.stalled_jobs().await {
+                self.restart_stalled_job(job_id, &reason).await;
+            }
+        }
+    }
+
+    /// Running jobs whose loop exited or is overdue, with why
+    async fn stalled_jobs(&self) -> Vec<(Uuid, String)> {
+        let running: HashSet<Uuid> = self.jobs.read().await.keys().copied().collect();
+        let mut loops = self.loops.lock().await;
+        // A job that stopped itself leaves the map before its task ends
+        loops.retain(|job_id, monitor| running.contains(job_id) || !monitor.task.is_finished());
+
+        loops
+            .iter()
+            .filter(|(job_id, _)| running.contains(job_id))
+            .filter_map(|(job_id, monitor)| {
+                let elapsed = monitor.heartbeat.elapsed();
+                if monitor.task.is_finished() {
+                    Some((*job_id, "its monitoring task exited".to_string()))
+                } else if elapsed > monitor.stall_after {
+                    Some((*job_id, format!("no cycle for {}", format_duration(elapsed))))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Abort a stalled loop and start the job again from its saved settings
+    async fn restart_stalled_job(&self, job_id: Uuid, reason: &str) {
+        let Some(monitor) = self.loops.lock().await.remove(&job_id) else { return };
+        warn!("Job {} stalled ({}), restarting its monitoring loop", job_id, reason);
+
+        // Save the counts held since the last flush. A loop stuck mid-cycle holds the job,
+        // so those are read once the abort makes it let go.
+        let job = self.jobs.read().await.get(&job_id).cloned();
+        let idle = job.as_ref().and_then(|job| job.try_lock().ok());
+        let flushed = idle.is_some();
+        if let Some(job_lock) = idle {
+            Self::flush_job_counts(&self.pool, job_lock).await;
+        }
+        // Dropping the aborted loop releases its hold on the job
+        monitor.task.abort();
+        if let (false, Some(job)) = (flushed, &job) {
+            match tokio::time::timeout(STALLED_JOB_RELEASE_WAIT, job.lock()).await {
+                Ok(job_lock) => Self::flush_job_counts(&self.pool, job_lock).await,
+                Err(_) => warn!("Job {} still held after its loop was aborted; stats since the last flush are lost", job_id),
+            }
+        }
+        self.jobs.write().await.remove(&job_id);
+
+        if let Err(e) = self.start_job(job_id, monitor.user_id).await {
+            error!("Could not restart stalled job {}: {:?}", job_id, e);
+            Self::persist_job_status(&self.pool, job_id, false, false).await;
+            return;
+        }
+
+        let job = self.jobs.read().await.get(&job_id).cloned();
+        if let Some(job) = job {
+            let job_lock = job.lock().await;
+            let msg = format!(
+                "🔄 Your {} monitoring job stopped responding and was restarted automatically.\n\
+                Time: {}",
+                job_lock.term,
+                local_now().format("%Y-%m-%d %H:%M:%S")
+            );
+            job_lock.notifier.send_alert(&msg).await;
+        }
+    }
+
+    /// One check of every due section, then enrollment in whatever opened. The caller holds
+    /// the job's lock throughout, so a manual poll never overlaps the loop's own cycle.
+    /// Unscheduled (manual) cycles check every section regardless of poll backoff.
+    async fn poll_cycle(
+        job_lock: &mut UserJob,
+        pool: &DbPool,
+        webreg_permits: &Semaphore,
+        scheduled: bool,
+    ) -> Vec<SectionPollResult> {
+        // Get necessary data for monitoring (clone to avoid borrow checker issues)
+        let job_id = job_lock.job_id;
+        let term = job_lock.term.clone();
+        let wrapper = Arc::clone(&job_lock.wrapper);
+        let notifier = job_lock.notifier.clone();
+        let latency = job_lock.latency.clone();
+        let throttle = job_lock.throttle.clone();
+        let openings = job_lock.openings.clone();
+        let poll_backoff = job_lock.poll_backoff.clone();
+        let quota = Arc::clone(&job_lock.quota);
+        let heartbeat = job_lock.heartbeat.clone();
+        let user_id = job_lock.user_id;
+        let courses = job_lock.courses.clone();
+        let polling_interval = job_lock.polling_interval;
+        let seat_threshold = job_lock.effective_seat_threshold();
+        let threshold_direction = job_lock.threshold_direction;
+        let recheck_freshness = job_lock.recheck_freshness;
+        let enroll = job_lock.enroll && job_lock.enrollment_open();
+        let mode = job_lock.mode;
+        let enroll_options = job_lock.enroll_options();
+        let stop_on_first_success = job_lock.stop_on_first_success;
+        let max_concurrent_enrolls = job_lock.max_concurrent_enrolls;
+        let stop_cycle_on_success = job_lock.stop_cycle_on_success || stop_on_first_success;
+        let section_timeout = job_lock.section_timeout;
+        // One retry budget for all of this cycle's checks and adds
+        let budget = RetryBudget::new(job_lock.cycle_retry_budget);
+
+        // Check every section first, then enroll in whatever opened
+        let mut candidates = Vec::new();
+        let mut results = Vec::new();
+        for course in &courses {
+            let backoff_key = |section: &str| format!("{} {} {}", course.department, course.course_code, section);
+            // Sections far past capacity sit out some cycles; the course isn't fetched if none are due
+            let due: HashSet<&String> = course.sections.iter()
+                .flat_map(|group| group.sections())
+                .filter(|section| !scheduled || poll_backoff.is_due(&backoff_key(section)))
+                .collect();
+            if due.is_empty() {
+                continue;
+            }
+
+            // One listing fetch serves every section of the course; without it the course sits the cycle out
+            let listing = {
+                let _permit = webreg_permits.acquire().await;
+                within(section_timeout, fetch_course_listing(
+                    &QuotaClient::new(wrapper.as_ref(), &quota),
+                    &term,
+                    &course.department,
+                    &course.course_code,
+                    &notifier,
+                    &latency,
+                    &throttle,
+                    RetryPolicy::default(),
+                    &budget,
+                )).await
+            };
+            let listing = match listing {
+                Ok(listing) => listing,
+                Err(e) => {
+                    warn!("Skipping {} {} for job {} this cycle: {}", course.department, course.course_code, job_id, e);
+                    if e.is::<TimedOut>() {
+                        job_lock.stats.errors += 1;
+                    }
+                    results.extend(due.iter().map(|section| SectionPollResult {
+                        department: course.department.clone(),
+                        course_code: course.course_code.clone(),
+                        section: (*section).clone(),
+                        opening: false,
+                        error: Some(e.to_string()),
+                        enroll_result: None,
+                    }));
+                    continue;
+                }
+            };
+
+            for section_group in &course.sections {
+                let target = section_group.enroll_target(mode);
+                let mut group_openings = Vec::new();
+                // Open discussions of a one-discussion group, tried in order as one candidate
+                let one_of = (section_group.one_discussion && !section_group.atomic)
+                    .then(|| format!("{} {} {}", course.department, course.course_code, section_group.lecture));
+                let filled = one_of.as_ref().is_some_and(|group| job_lock.filled_groups.contains(group));
+                let mut discussion_openings = Vec::new();
+                // Lecture first, then its discussions
+                for (index, section) in section_group.sections().enumerate() {
+                    if !due.contains(section) {
+                        continue;
+                    }
+                    let role = section_group.opening_role(index);
+                    // A long cycle still shows the watchdog it's getting somewhere
+                    heartbeat.beat();
+                    // One check per WebReg section query, not per cycle
+                    job_lock.stats.total_checks += 1;
+                    let opening = {
+                        let _permit = webreg_permits.acquire().await;
+                        within(section_timeout, monitor_section_with_retry(
+                            &QuotaClient::new(wrapper.as_ref(), &quota),
+                            Some(&listing),
+                            &term,
+                            section,
+                            &course.department,
+                            &course.course_code,
+                            polling_interval,
+                            seat_threshold,
+                            threshold_direction,
+                            mode,
+                            section_group.enroll_target(mode),
+                            recheck_freshness,
+                            enroll && role != OpeningRole::ReportOnly,
+                            &notifier,
+                            &latency,
+                            &throttle,
+                            &openings,
+                            RetryPolicy::default(),
+                            &budget,
+                        )).await
+                    };
+                    if opening.as_ref().is_err_and(|e| e.is::<TimedOut>()) {
+                        job_lock.stats.errors += 1;
+                    }
+                    if let Some(demand) = listing.demand(section) {
+                        poll_backoff.observe(&backoff_key(section), demand);
+                    }
+                    results.push(SectionPollResult {
+                        department: course.department.clone(),
+                        course_code: course.course_code.clone(),
+                        section: section.clone(),
+                        opening: matches!(opening, Ok(Some(_))),
+                        error: opening.as_ref().err().map(|e| e.to_string()),
+                        enroll_result: None,
+                    });
+
+                    if let Ok(Some(Opening { section_id, available_seats, .. })) = opening {
+                        job_lock.stats.openings_found += 1;
+                        // Monitor-only jobs stop at the opening notification
+                        match role {
+                            _ if !enroll => {}
+                            OpeningRole::Atomic => group_openings.push((section, section_id)),
+                            OpeningRole::OneOf if !filled => discussion_openings.push((section, section_id)),
+                            OpeningRole::OneOf => {}
+                            OpeningRole::ReportOnly => {
+                                info!("{} {} lecture {} is open, but it's only taken with a discussion",
+                                    course.department, course.course_code, section);
+                            }
+                            OpeningRole::Alone => {
+                                candidates.push((course, vec![(section, section_id)], target.for_seats(available_seats), None));
+                            }
+                        }
+                    }
+                }
+
+                // An atomic group is only tried once its lecture and a discussion are both open
+                let lecture = section_group.sections().next();
+                let lecture_open = group_openings.first().is_some_and(|(section, _)| Some(*section) == lecture);
+                if lecture_open && (group_openings.len() > 1 || section_group.discussions.is_empty()) {
+                    group_openings.truncate(2);
+                    candidates.push((course, group_openings, target, None));
+                }
+                if !discussion_openings.is_empty() {
+                    candidates.push((course, discussion_openings, target, one_of));
+                }
+            }
+        }
+
+        // Backups wait a cycle while a primary course is being tried, so both can't be taken at once
+        if candidates.iter().any(|(course, ..)| course.role == Some(CourseRole::Primary)) {
+            candidates.retain(|(course, ..)| course.role != Some(CourseRole::Backup));
+        }
+
+        let attempts = run_bounded(
+            candidates,
+            max_concurrent_enrolls,
+            stop_cycle_on_success,
+            |(course, parts, target, one_of)| {
+                let (wrapper, quota, term, notifier, latency, throttle, budget, webreg_permits, heartbeat) =
+                    (&wrapper, &quota, &term, &notifier, &latency, &throttle, &budget, webreg_permits, &heartbeat);
+                async move {
+                    let mut attempt_history = Vec::new();
+                    let mut parts = parts;
+                    let enrolled = {
+                        let _permit = webreg_permits.acquire().await;
+                        let client = QuotaClient::new(wrapper.as_ref(), quota);
+                        // Not time-boxed: cutting an add short could leave a group half enrolled,
+                        // or a seat taken without being recorded
+                        if one_of.is_some() {
+                            let (index, enrolled) = attempt_enroll_one_of(
+                                &client,
+                                term,
+                                &parts,
                                 &course.department,
                                 &course.course_code,
-                                polling_interval,
-                                seat_threshold,
-                                &notifier,
-                            ).await {
-                                job_lock.stats.enrollment_attempts += 1;
-
-                                if let Ok(true) = try_enroll_with_retry(
-                                    &wrapper,
-                                    &term,
-                                    &section_id,
+                                enroll_options.with_target(target),
+                                notifier,
+                                latency,
+                                throttle,
+                                budget,
+                                Some(&mut attempt_history),
+                            ).await;
+                            // Only the discussion that ended the run is reported
+                            parts = vec![parts.swap_remove(index)];
+                            enrolled
+                        } else {
+                            match parts.as_slice() {
+                                [(section, section_id)] => attempt_enroll(
+                                    &client,
+                                    term,
+                                    section_id,
                                     &course.department,
                                     &course.course_code,
-                                    &section_group.lecture,
-                                    &notifier,
-                                    &mut job_lock.stats,
-                                ).await {
-                                    job_lock.stats.successful_enrollments += 1;
-                                }
-                            }
-
-                            // Monitor discussions
-                            for discussion in &section_group.discussions {
-                                if let Ok(Some(section_id)) = monitor_section_with_retry(
-                                    &wrapper,
-                                    &term,
-                                    discussion,
+                                    section,
+                                    enroll_options.with_target(target),
+                                    notifier,
+                                    latency,
+                                    throttle,
+                                    budget,
+                                    Some(&mut attempt_history),
+                                ).await,
+                                _ => attempt_enroll_group(
+                                    &client,
+                                    term,
+                                    &parts,
                                     &course.department,
                                     &course.course_code,
-                                    polling_interval,
-                                    seat_threshold,
-                                    &notifier,
-                                ).await {
-                                    job_lock.stats.enrollment_attempts += 1;
-
-                                    if let Ok(true) = try_enroll_with_retry(
-                                        &wrapper,
-                                        &term,
-                                        &section_id,
-                                        &course.department,
-                                        &course.course_code,
-                                        discussion,
-                                        &notifier,
-                                        &mut job_lock.stats,
-                                    ).await {
-                                        job_lock.stats.successful_enrollments += 1;
-                                    }
-                                }
+                                    enroll_options.with_target(target),
+                                    notifier,
+                                    latency,
+                                    throttle,
+                                    budget,
+                                    Some(&mut attempt_history),
+                                ).await,
                             }
                         }
+                    };
+                    heartbeat.beat();
+                    let succeeded = enrolled.as_ref().is_ok_and(|outcome| outcome.succeeded());
+                    (succeeded, (course, parts, target, one_of, enrolled, attempt_history))
+                }
+            },
+        ).await;
+
+        // Openings skipped after a success in this cycle don't count as attempts
+        job_lock.stats.enrollment_attempts += attempts.len() as u64;
+        for (course, parts, target, one_of, enrolled, attempt_history) in attempts {
+            if let Err(e) = crate::db::retry_write(|| crate::db::record_enrollment_attempts(pool, job_id, &attempt_history)).await {
+                error!("Failed to record enrollment attempts for job {}: {:?}", job_id, e);
+            }
+
+            let enroll_result = match &enrolled {
+                Ok(outcome) => format!("{:?}", outcome).to_lowercase(),
+                Err(e) => e.to_string(),
+            };
+            for result in results.iter_mut().filter(|result| {
+                result.department == course.department
+                    && result.course_code == course.course_code
+                    && parts.iter().any(|(section, _)| *section == &result.section)
+            }) {
+                result.enroll_result = Some(enroll_result.clone());
+            }
+
+            let Ok(outcome) = enrolled else { continue };
+            // An atomic group is reported as one attempt, e.g. "A00 + A01"
+            let section = parts.iter().map(|(section, _)| section.as_str()).collect::<Vec<_>>().join(" + ");
+            report_enroll_result(
+                &term,
+                &course.department,
+                &course.course_code,
+                &section,
+                enroll_options.with_target(target),
+                &notifier,
+                &mut job_lock.stats,
+                outcome,
+            ).await;
+
+            if outcome.succeeded() {
+                job_lock.stats.successful_enrollments += 1;
+                if let Some(group) = one_of {
+                    info!("Job {} took a discussion for {}, leaving its other discussions alone", job_id, group);
+                    job_lock.filled_groups.insert(group);
+                    if let Err(e) = crate::db::retry_write(|| crate::db::set_filled_groups(pool, job_id, &job_lock.filled_groups)).await {
+                        error!("Failed to save filled groups for job {}: {:?}", job_id, e);
+                    }
+                }
+                if course.role == Some(CourseRole::Primary) && job_lock.drop_backup_courses() {
+                    info!("Job {} enrolled in primary course {} {}, no longer monitoring its backups",
+                        job_id, course.department, course.course_code);
+                    if let Err(e) = crate::db::retry_write(|| crate::db::set_primary_enrolled(pool, job_id)).await {
+                        error!("Failed to record the primary enrollment for job {}: {:?}", job_id, e);
                     }
+                }
+                notifier.send_success_webhook(&EnrollmentEvent {
+                    job_id,
+                    department: course.department.clone(),
+                    course_code: course.course_code.clone(),
+                    section: section.clone(),
+                    timestamp: Utc::now(),
+                }).await;
 
-                    job_lock.last_check_time = Local::now().to_string();
-                    job_lock.stats.total_checks += 1;
+                if job_lock.watch_enrolled {
+                    for (section, section_id) in parts {
+                        if !job_lock.held_sections.iter().any(|held| held.section_id == section_id) {
+                            job_lock.held_sections.push(HeldSection {
+                                department: course.department.clone(),
+                                course_code: course.course_code.clone(),
+                                section: section.clone(),
+                                section_id,
+                                waitlisted: outcome == EnrollOutcome::Waitlisted,
+                            });
+                        }
+                    }
+                    Self::persist_held_sections(pool, job_lock).await;
+                }
 
-                    // Update stats in database
-                    let stats_json = serde_json::to_value(&job_lock.stats.section_failures).unwrap_or_default();
-                    let _ = crate::db::update_job_stats(
-                        &pool,
-                        job_id,
-                        job_lock.stats.total_checks as i32,
-                        job_lock.stats.openings_found as i32,
-                        job_lock.stats.enrollment_attempts as i32,
-                        job_lock.stats.successful_enrollments as i32,
-                        job_lock.stats.errors as i32,
-                        stats_json,
-                    ).await;
-
-                    let _ = crate::db::update_job_last_check(&pool, job_id).await;
+                if stop_on_first_success && job_lock.is_running {
+                    info!("Job {} enrolled in {} {} section {}, stopping (stop_on_first_success)",
+                        job_id, course.department, course.course_code, section);
+                    Self::stop_from_loop(job_lock);
+                }
+            }
+        }
 
-                    drop(job_lock);
-                    sleep(Duration::from_secs(polling_interval)).await;
-                } => {}
+        job_lock.last_check_time = local_now().to_string();
+
+        results
+    }
+
+    /// Swap a job onto its backup cookie. Returns false if the backup doesn't work either.
+    async fn fail_over_to_backup(job: &mut UserJob, backup: &str, pool: &DbPool, webreg_permits: &Semaphore) -> bool {
+        let wrapper = match cookie_wrapper(backup, job.proxy_url.as_deref(), job.webreg_timeout) {
+            Some(wrapper) => wrapper,
+            None => return false,
+        };
+        let associated = {
+            let _permit = webreg_permits.acquire().await;
+            wrapper.associate_term(&job.term).await
+        };
+        if let Err(e) = associated {
+            error!("Backup cookie for job {} is not valid either: {:?}", job.job_id, e);
+            return false;
+        }
+
+        info!("Primary cookie for job {} expired, switched to the backup cookie", job.job_id);
+        job.wrapper = Arc::new(wrapper);
+        job.is_connected = true;
+        if let Err(e) = crate::db::promote_backup_cookie(pool, job.job_id).await {
+            error!("Failed to promote backup cookie for job {}: {:?}", job.job_id, e);
+        }
+        Self::write_job_status(pool, job.job_id, true, true).await;
+
+        let msg = format!(
+            "⚠️  The primary WebReg cookie for your {} job expired, switched to the backup cookie.\n\
+            Time: {}\n\
+            Monitoring continues; add a new backup cookie when you can.",
+            job.term,
+            local_now().format("%Y-%m-%d %H:%M:%S")
+        );
+        job.notifier.send_alert(&msg).await;
+        true
+    }
+
+    /// Confirm the job still holds each section it enrolled in. A dropped section
+    /// is re-enrolled right away and the user is told either way.
+    async fn check_held_sections(job: &mut UserJob, pool: &DbPool, webreg_permits: &Semaphore) {
+        let wrapper = Arc::clone(&job.wrapper);
+        let quota = Arc::clone(&job.quota);
+        let client = QuotaClient::new(wrapper.as_ref(), &quota);
+        let (changed, attempt_history) = Self::recheck_held_sections(&client, job, webreg_permits).await;
+
+        if let Err(e) = crate::db::retry_write(|| crate::db::record_enrollment_attempts(pool, job.job_id, &attempt_history)).await {
+            error!("Failed to record enrollment attempts for job {}: {:?}", job.job_id, e);
+        }
+        if changed {
+            Self::persist_held_sections(pool, job).await;
+        }
+    }
+
+    /// The WebReg side of `check_held_sections`. Returns whether a section was lost,
+    /// along with the re-adds that were tried.
+    async fn recheck_held_sections(
+        client: &impl WebRegClient,
+        job: &mut UserJob,
+        webreg_permits: &Semaphore,
+    ) -> (bool, Vec<EnrollAttempt>) {
+        let mut still_held = Vec::new();
+        let mut changed = false;
+        let mut attempt_history = Vec::new();
+        let budget = RetryBudget::new(job.cycle_retry_budget);
+
+        for held in std::mem::take(&mut job.held_sections) {
+            let on_schedule = {
+                let _permit = webreg_permits.acquire().await;
+                client.is_on_schedule(&job.term, &held.section_id, held.waitlisted).await
+            };
+            match on_schedule {
+                Ok(true) => {
+                    still_held.push(held);
+                    continue;
+                }
+                Err(e) => {
+                    // Can't tell, so keep it and look again next time
+                    warn!("Could not check whether job {} still holds {} {} section {}: {:?}",
+                        job.job_id, held.department, held.course_code, held.section, e);
+                    still_held.push(held);
+                    continue;
+                }
+                Ok(false) => {}
             }
+
+            warn!("Job {} was dropped from {} {} section {}, re-enrolling",
+                job.job_id, held.department, held.course_code, held.section);
+            changed = true;
+
+            let target = if held.waitlisted { EnrollTarget::WaitlistOnly } else { EnrollTarget::EnrollOnly };
+            let outcome = {
+                let _permit = webreg_permits.acquire().await;
+                attempt_enroll(
+                    client,
+                    &job.term,
+                    &held.section_id,
+                    &held.department,
+                    &held.course_code,
+                    &held.section,
+                    job.enroll_options().with_target(target),
+                    &job.notifier,
+                    &job.latency,
+                    &job.throttle,
+                    &budget,
+                    Some(&mut attempt_history),
+                ).await
+            };
+            job.stats.enrollment_attempts += 1;
+
+            let regained = outcome.as_ref().is_ok_and(|outcome| outcome.succeeded());
+            let msg = format!(
+                "⚠️ You were dropped from {} {} section {}.\n{}\n\nTime: {}",
+                held.department,
+                held.course_code,
+                held.section,
+                if regained {
+                    "It was re-added automatically; please verify on WebReg."
+                } else {
+                    "Re-enrolling failed. Check WebReg for the reason (e.g. an unmet prerequisite)."
+                },
+                local_now().format("%Y-%m-%d %H:%M:%S")
+            );
+            job.notifier.send_priority_notification(&msg, Priority::High).await;
+
+            if regained {
+                job.stats.successful_enrollments += 1;
+                still_held.push(held);
+            }
+        }
+
+        job.held_sections = still_held;
+        (changed, attempt_history)
+    }
+
+    async fn persist_held_sections(pool: &DbPool, job: &UserJob) {
+        if let Err(e) = crate::db::retry_write(|| crate::db::set_held_sections(pool, job.job_id, &job.held_sections)).await {
+            error!("Failed to save held sections for job {}: {:?}", job.job_id, e);
+        }
+    }
+
+    /// Stop a job from inside its own loop; the loop marks it inactive on the way out
+    fn stop_from_loop(job: &mut UserJob) {
+        job.is_running = false;
+        let _ = job.shutdown_tx.send(());
+    }
+
+    async fn persist_job_status(pool: &DbPool, job_id: Uuid, is_active: bool, is_connected: bool) {
+        if let Err(e) = crate::db::retry_write(|| crate::db::update_job_status(pool, job_id, is_active, is_connected)).await {
+            error!("Failed to update status for job {}: {:?}", job_id, e);
+        }
+    }
+
+    /// `persist_job_status` for callers holding the job's lock: one attempt, so a database
+    /// outage can't stall the job. A missed write is corrected by the next status change
+    /// or when the loop ends.
+    async fn write_job_status(pool: &DbPool, job_id: Uuid, is_active: bool, is_connected: bool) {
+        if let Err(e) = crate::db::update_job_status(pool, job_id, is_active, is_connected).await {
+            error!("Failed to update status for job {}: {:?}", job_id, e);
+        }
+    }
+
+    /// Flush a job's stats and its user's request count, releasing the job before writing
+    async fn flush_job_counts(pool: &DbPool, job_lock: tokio::sync::MutexGuard<'_, UserJob>) {
+        let (job_id, user_id, stats, quota) = (job_lock.job_id, job_lock.user_id, job_lock.stats.clone(), Arc::clone(&job_lock.quota));
+        drop(job_lock);
+        Self::flush_stats(pool, job_id, &stats).await;
+        Self::flush_request_count(pool, user_id, &quota).await;
+    }
+
+    /// Add the user's uncounted WebReg requests to today's stored total.
+    /// On failure they're kept for the next flush rather than dropped.
+    async fn flush_request_count(pool: &DbPool, user_id: Uuid, quota: &RequestQuota) -> bool {
+        let (day, requests) = quota.take_unflushed();
+        if requests == 0 {
+            return true;
+        }
+        match crate::db::retry_write(|| crate::db::add_request_count(pool, user_id, day, requests as i64)).await {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Failed to record request count for user {}: {:?}", user_id, e);
+                quota.restore_unflushed(day, requests);
+                false
+            }
+        }
+    }
+
+    /// Write a job's in-memory stats and last check time to the database
+    async fn flush_stats(pool: &DbPool, job_id: Uuid, stats: &EnrollmentStats) -> bool {
+        let stats_json = serde_json::to_value(&stats.section_failures).unwrap_or_default();
+        let written = crate::db::retry_write(|| crate::db::update_job_stats(
+            pool,
+            job_id,
+            stats.total_checks as i32,
+            stats.openings_found as i32,
+            stats.enrollment_attempts as i32,
+            stats.successful_enrollments as i32,
+            stats.errors as i32,
+            stats_json.clone(),
+        )).await;
+        if let Err(e) = written {
+            error!("Failed to persist stats for job {}: {:?}", job_id, e);
+            return false;
+        }
+
+        if let Err(e) = crate::db::retry_write(|| crate::db::update_job_last_check(pool, job_id)).await {
+            error!("Failed to update last check time for job {}: {:?}", job_id, e);
+            return false;
+        }
+        true
+    }
+
+    /// Track whether the database copy of a job is falling behind, logging each change
+    fn set_db_degraded(job: &mut UserJob, degraded: bool) {
+        if degraded == job.db_degraded {
+            return;
+        }
+        job.db_degraded = degraded;
+        if degraded {
+            error!("Job {} can't reach the database; stats are held in memory until it recovers", job.job_id);
+        } else {
+            info!("Job {} is writing to the database again", job.job_id);
+        }
+    }
+
+    /// Get a WebReg wrapper for a job, reusing the running job's wrapper when possible
+    async fn job_wrapper(&self, job: &Job) -> Result<Arc<WebRegWrapper>, Box<dyn std::error::Error + Send + Sync>> {
+        // Released before waiting on the job, which a poll cycle may hold through an enroll
+        let running = self.jobs.read().await.get(&job.id).cloned();
+        if let Some(running) = running {
+            return Ok(Arc::clone(&running.lock().await.wrapper));
         }
+
+        let cookie = self.encryption_key.decrypt(&job.cookie_encrypted, &job.encryption_nonce)?;
+        let wrapper = cookie_wrapper(&cookie, self.settings.proxy_url.as_deref(), self.settings.webreg_timeout)
+            .ok_or("Failed to create WebRegWrapper")?;
+        wrapper.associate_term(&job.term).await?;
+
+        Ok(Arc::new(wrapper))
+    }
+
+    /// Load a job's courses with their configured section groups
+    async fn load_course_sections(&self, job_id: Uuid) -> Result<Vec<CourseWithSections>, Box<dyn std::error::Error + Send + Sync>> {
+        let courses = crate::db::get_job_courses(&self.pool, job_id).await?;
+        let mut course_sections = Vec::new();
+
+        for course in courses {
+            let sections = crate::db::get_course_sections(&self.pool, course.id).await?;
+            let section_groups: Vec<SectionGroup> = sections
+                .iter()
+                .map(|s| {
+                    let discussions: Vec<String> = serde_json::from_value(s.discussions.clone())
+                        .unwrap_or_default();
+                    SectionGroup {
+                        lecture: s.lecture.clone(),
+                        discussions,
+                        section_id: s.section_id.clone(),
+                        enroll_target: s.enroll_target.as_deref().and_then(|target| target.parse().ok()),
+                        atomic: s.atomic,
+                        one_discussion: s.one_discussion,
+                        discussion_required: s.discussion_required,
+                    }
+                })
+                .collect();
+
+            course_sections.push(CourseWithSections {
+                department: course.department,
+                course_code: course.course_code,
+                sections: section_groups,
+                section_pattern: course.section_pattern,
+                role: course.role.as_deref().and_then(|role| role.parse().ok()),
+            });
+        }
+
+        Ok(course_sections)
+    }
+
+    /// Evaluate every configured section against the job's threshold and mode
+    /// without enrolling, using the same decision as the monitoring loop
+    pub async fn preview_job(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<SectionPreview>, Box<dyn std::error::Error + Send + Sync>> {
+        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
+            .await?
+            .ok_or("Job not found")?;
+
+        let mode: MonitoringMode = job.monitoring_mode.parse()?;
+        let threshold_direction: ThresholdDirection = job.threshold_direction.parse()?;
+        let wrapper = self.job_wrapper(&job).await?;
+        let course_sections = self.load_course_sections(job_id).await?;
+
+        // Previews count against the user's quota and respect a running job's throttle cooldown
+        let quota = self.request_quota(user_id).await?;
+        let running = self.jobs.read().await.get(&job_id).cloned();
+        let (notifier, latency, throttle, cycle_retry_budget) = match running {
+            Some(running) => {
+                let job_lock = running.lock().await;
+                (job_lock.notifier.clone(), job_lock.latency.clone(), job_lock.throttle.clone(), job_lock.cycle_retry_budget)
+            }
+            None => (self.user_notifier(user_id).await?, LatencyTracker::default(), ThrottleBackoff::default(), self.settings.cycle_retry_budget),
+        };
+        if throttle.cooldown_remaining().is_some() {
+            return Err(Box::new(Error::WebRegThrottled));
+        }
+        let budget = RetryBudget::new(cycle_retry_budget);
+
+        let mut previews = Vec::new();
+        for course in &course_sections {
+            if quota.is_exhausted() {
+                return Err(Box::new(Error::LimitReached("Today's WebReg request quota is used up".to_string())));
+            }
+            let listing = {
+                let _permit = self.webreg_permits.acquire().await?;
+                fetch_course_listing(
+                    &QuotaClient::new(wrapper.as_ref(), &quota),
+                    &job.term,
+                    &course.department,
+                    &course.course_code,
+                    &notifier,
+                    &latency,
+                    &throttle,
+                    RetryPolicy::default(),
+                    &budget,
+                ).await?
+            };
+
+            for group in &course.sections {
+                for section in group.sections() {
+                    let found = listing.sections().iter().find(|s| &s.section_code == section || &s.section_id == section);
+
+                    previews.push(SectionPreview {
+                        department: course.department.clone(),
+                        course_code: course.course_code.clone(),
+                        section: section.clone(),
+                        available_seats: found.map(|s| s.available_seats),
+                        would_attempt: found.map_or(false, |s| should_attempt_for_target(
+                            s.available_seats,
+                            s.waitlist_ct,
+                            effective_seat_threshold(job.seat_threshold as i64, job.ignore_threshold),
+                            threshold_direction,
+                            mode,
+                            group.enroll_target(mode),
+                        ).is_attempt()),
+                    });
+                }
+            }
+        }
+
+        Ok(previews)
+    }
+
+    /// Drop a section using the job's WebReg session
+    pub async fn drop_section(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+        request: &DropSectionRequest,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
+            .await?
+            .ok_or("Job not found")?;
+
+        let wrapper = self.job_wrapper(&job).await?;
+        let dropped = try_drop(&wrapper, &job.term, &request.section_id, request.waitlisted).await?;
+
+        info!("Drop of section {} for job {}: {}", request.section_id, job_id, dropped);
+        Ok(dropped)
+    }
+
+    /// The user's live WebReg schedule for the job's term, using the job's session
+    pub async fn job_schedule(
+        &self,
+        job_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<ScheduleEntry>, Error> {
+        let job = crate::db::get_job_by_id(&self.pool, job_id, user_id)
+            .await?
+            .ok_or_else(|| Error::NotFound("Job not found".to_string()))?;
+
+        let wrapper = self.job_wrapper(&job).await?;
+        Ok(fetch_schedule(&wrapper, &job.term).await?)
+    }
+
+    /// Fetch WebReg's unparsed course info using one of the user's jobs for that term.
+    /// Returns `None` when the user has no job for the term.
+    pub async fn debug_course_info(
+        &self,
+        user_id: Uuid,
+        term: &str,
+        department: &str,
+        course_code: &str,
+    ) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
+        let jobs = crate::db::get_user_jobs(&self.pool, user_id, &JobListQuery::default()).await?;
+        let Some(job) = jobs.iter().find(|j| j.term.eq_ignore_ascii_case(term)) else {
+            return Ok(None);
+        };
+
+        let wrapper = self.job_wrapper(job).await?;
+        let raw = wrapper.req(&job.term).raw()
+            .get_course_info(&normalize_course_field(department), &normalize_course_field(course_code))
+            .await?;
+
+        Ok(Some(serde_json::from_str(&raw)?))
     }
 
     /// Get all user jobs (from database, not just running ones)
-    pub async fn get_user_jobs(&self, user_id: Uuid) -> Result<Vec<Job>, Box<dyn std::error::Error + Send + Sync>> {
-        crate::db::get_user_jobs(&self.pool, user_id).await
+    pub async fn get_user_jobs(
+        &self,
+        user_id: Uuid,
+        query: &JobListQuery,
+    ) -> Result<Vec<Job>, Box<dyn std::error::Error + Send + Sync>> {
+        crate::db::get_user_jobs(&self.pool, user_id, query).await
     }
 
     /// Delete a user job
@@ -407,7 +2309,265 @@ impl MultiUserState {
 #[derive(Debug, Clone)]
 pub struct JobStatusInfo {
     pub is_running: bool,
+    pub is_paused: bool,
     pub is_connected: bool,
     pub last_check_time: String,
     pub stats: EnrollmentStats,
+    pub eligibility: Vec<EnrollEligibility>,
+    pub avg_latency_ms: Option<f64>,
+    pub p95_latency_ms: Option<u64>,
+    pub db_degraded: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotificationConfig;
+    use crate::config::BackoffThresholds;
+    use crate::webreg::cookie_wrapper;
+    use crate::monitor::Decision;
+    use crate::webreg::mock::MockWebReg;
+
+    /// A state whose database is never reached; only the in-memory job map is used
+    fn test_state() -> MultiUserState {
+        std::env::set_var("ENCRYPTION_KEY", EncryptionKey::generate());
+        let pool = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/webreg_test").unwrap();
+        MultiUserState::new(pool, EncryptionKey::from_env().unwrap(), ServerConfig::from_env())
+    }
+
+    /// A running, connected job with no courses and no notification channels
+    fn test_job() -> UserJob {
+        let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
+        UserJob {
+            job_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            term: "FA25".to_string(),
+            wrapper: Arc::new(cookie_wrapper("cookie", None, Duration::ZERO).unwrap()),
+            backup_cookie: None,
+            notifier: Notifier::new(&NotificationConfig::default()).unwrap(),
+            stats: EnrollmentStats::default(),
+            courses: Vec::new(),
+            polling_interval: 30,
+            seat_threshold: 0,
+            threshold_direction: ThresholdDirection::AtMost,
+            mode: MonitoringMode::Available,
+            enroll: true,
+            verify_enrollment: false,
+            stop_on_first_success: false,
+            max_concurrent_enrolls: 1,
+            stop_cycle_on_success: false,
+            watch_enrolled: false,
+            held_sections: Vec::new(),
+            filled_groups: HashSet::new(),
+            poll_immediately: true,
+            enrollment_opens_at: None,
+            force_enroll: false,
+            ignore_threshold: false,
+            awaiting_enrollment_window: false,
+            paused: false,
+            is_running: true,
+            is_connected: true,
+            last_check_time: String::new(),
+            start_time: SystemTime::now(),
+            shutdown_tx,
+            eligibility: Vec::new(),
+            latency: LatencyTracker::default(),
+            throttle: ThrottleBackoff::default(),
+            openings: OpeningFilter::default(),
+            poll_backoff: PollBackoff::new(BackoffThresholds::default()),
+            quota: Arc::new(RequestQuota::new(0, local_now().date_naive(), 0)),
+            recheck_freshness: Duration::ZERO,
+            max_run_duration: None,
+            two_phase_enroll: false,
+            plan_unit_count: crate::config::DEFAULT_PLAN_UNIT_COUNT,
+            grace_retry: GraceRetry::default(),
+            cycle_retry_budget: 0,
+            cookie_refresh_interval: 480,
+            cookie_refresh_jitter: false,
+            proxy_url: None,
+            webreg_timeout: Duration::ZERO,
+            section_timeout: Duration::ZERO,
+            session_check_cycles: 0,
+            max_sections_per_course: 0,
+            db_degraded: false,
+            heartbeat: Heartbeat::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pausing_stops_and_resumes_polling() {
+        let state = test_state();
+        let job = Arc::new(Mutex::new(test_job()));
+        let job_id = job.lock().await.job_id;
+        state.jobs.write().await.insert(job_id, Arc::clone(&job));
+        assert!(job.lock().await.polls());
+
+        state.set_paused(job_id, true).await.unwrap();
+        assert!(!job.lock().await.polls());
+
+        state.set_paused(job_id, false).await.unwrap();
+        assert!(job.lock().await.polls());
+
+        // Only a loaded job can be paused
+        assert!(state.set_paused(Uuid::new_v4(), true).await.is_err());
+    }
+
+    fn monitor_loop(task: tokio::task::JoinHandle<()>, quiet_for: Duration) -> MonitorLoop {
+        let heartbeat = Heartbeat::new();
+        *heartbeat.0.lock().unwrap() = Instant::now() - quiet_for;
+        let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
+        MonitorLoop { user_id: Uuid::new_v4(), task, heartbeat, stall_after: Duration::from_secs(60), shutdown_tx }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_jobs_are_quiet_or_exited_loops() {
+        let state = test_state();
+        let (healthy, quiet, exited, stopped) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for job_id in [healthy, quiet, exited] {
+            state.jobs.write().await.insert(job_id, Arc::new(Mutex::new(test_job())));
+        }
+
+        let finished = tokio::spawn(async {});
+        let finished_too = tokio::spawn(async {});
+        while !finished.is_finished() || !finished_too.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        {
+            let mut loops = state.loops.lock().await;
+            loops.insert(healthy, monitor_loop(tokio::spawn(future::pending()), Duration::from_secs(30)));
+            loops.insert(quiet, monitor_loop(tokio::spawn(future::pending()), Duration::from_secs(90)));
+            loops.insert(exited, monitor_loop(finished, Duration::ZERO));
+            // A job that stopped itself is forgotten rather than restarted
+            loops.insert(stopped, monitor_loop(finished_too, Duration::ZERO));
+        }
+
+        let mut stalled = state.stalled_jobs().await;
+        stalled.sort_by_key(|(job_id, _)| *job_id != quiet);
+        assert_eq!(stalled.len(), 2);
+        assert_eq!(stalled[0].0, quiet);
+        assert!(stalled[0].1.starts_with("no cycle for"));
+        assert_eq!(stalled[1], (exited, "its monitoring task exited".to_string()));
+        assert!(!state.loops.lock().await.contains_key(&stopped));
+
+        // A cycle that keeps making progress isn't a stall
+        state.loops.lock().await[&quiet].heartbeat.beat();
+        assert_eq!(state.stalled_jobs().await.len(), 1);
+    }
+
+    fn group(discussions: &[&str]) -> SectionGroup {
+        SectionGroup {
+            lecture: "A00".to_string(),
+            discussions: discussions.iter().map(|d| d.to_string()).collect(),
+            section_id: None,
+            enroll_target: None,
+            atomic: false,
+            one_discussion: false,
+            discussion_required: false,
+        }
+    }
+
+    #[test]
+    fn test_discussion_required_lecture_is_not_enrolled_alone() {
+        let required = SectionGroup { discussion_required: true, ..group(&["A01", "A02"]) };
+        assert_eq!(required.opening_role(0), OpeningRole::ReportOnly);
+        assert_eq!(required.opening_role(1), OpeningRole::Alone);
+        assert_eq!(SectionGroup { one_discussion: true, ..required.clone() }.opening_role(0), OpeningRole::ReportOnly);
+        assert_eq!(SectionGroup { one_discussion: true, ..required.clone() }.opening_role(2), OpeningRole::OneOf);
+        // An atomic group takes the lecture together with a discussion
+        assert_eq!(SectionGroup { atomic: true, ..required }.opening_role(0), OpeningRole::Atomic);
+
+        // With no discussion listed there's nothing to take it with
+        assert_eq!(SectionGroup { discussion_required: true, ..group(&[]) }.opening_role(0), OpeningRole::Alone);
+        assert_eq!(group(&["A01"]).opening_role(0), OpeningRole::Alone);
+    }
+
+    fn course(course_code: &str, role: Option<CourseRole>) -> CourseWithSections {
+        CourseWithSections {
+            department: "CSE".to_string(),
+            course_code: course_code.to_string(),
+            sections: vec![group(&["A01"])],
+            section_pattern: None,
+            role,
+        }
+    }
+
+    #[test]
+    fn test_enrolling_the_primary_stops_the_backups() {
+        let mut job = test_job();
+        job.courses = vec![
+            course("100", Some(CourseRole::Primary)),
+            course("101", Some(CourseRole::Backup)),
+            course("110", None),
+            course("105", Some(CourseRole::Backup)),
+        ];
+
+        assert!(job.drop_backup_courses());
+        let left: Vec<&str> = job.courses.iter().map(|course| course.course_code.as_str()).collect();
+        assert_eq!(left, vec!["100", "110"]);
+        // A second primary enrollment has nothing left to stop
+        assert!(!job.drop_backup_courses());
+    }
+
+    #[test]
+    fn test_threshold_override_attempts_on_any_open_seat() {
+        let mut job = test_job();
+        job.mode = MonitoringMode::LowSeats;
+        job.seat_threshold = 3;
+
+        let decide = |job: &UserJob, seats: i64, direction: ThresholdDirection| should_attempt_for_target(
+            seats, 0, job.effective_seat_threshold(), direction, job.mode, job.mode.default_target(),
+        );
+        assert_eq!(decide(&job, 10, ThresholdDirection::AtMost), Decision::AboveThreshold);
+        assert_eq!(decide(&job, 1, ThresholdDirection::AtLeast), Decision::BelowThreshold);
+
+        job.ignore_threshold = true;
+        assert_eq!(decide(&job, 10, ThresholdDirection::AtMost), Decision::Attempt);
+        assert_eq!(decide(&job, 1, ThresholdDirection::AtLeast), Decision::Attempt);
+        // Still nothing to attempt on a full section
+        assert_eq!(decide(&job, 0, ThresholdDirection::AtMost), Decision::Full);
+    }
+
+    fn held(section: &str, section_id: &str) -> HeldSection {
+        HeldSection {
+            department: "CSE".to_string(),
+            course_code: "100".to_string(),
+            section: section.to_string(),
+            section_id: section_id.to_string(),
+            waitlisted: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lost_seat_is_readded_or_forgotten() {
+        let mock = MockWebReg::default();
+        *mock.off_schedule.lock().unwrap() = true;
+        let permits = Semaphore::new(1);
+        let mut job = test_job();
+
+        // A re-add that takes keeps the section on the watch list
+        job.held_sections = vec![held("A01", "111111")];
+        mock.push_add_result(Ok(true));
+        let (changed, attempts) = MultiUserState::recheck_held_sections(&mock, &mut job, &permits).await;
+        assert!(changed);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(job.held_sections.len(), 1);
+        assert_eq!(job.stats.successful_enrollments, 1);
+
+        // A rejected re-add drops it
+        mock.push_add_result(Ok(false));
+        let (changed, _) = MultiUserState::recheck_held_sections(&mock, &mut job, &permits).await;
+        assert!(changed);
+        assert!(job.held_sections.is_empty());
+        assert_eq!(*mock.add_calls.lock().unwrap(), vec![("111111".to_string(), false), ("111111".to_string(), false)]);
+        assert_eq!(job.stats.enrollment_attempts, 2);
+
+        // A seat still on the schedule isn't touched
+        *mock.off_schedule.lock().unwrap() = false;
+        job.held_sections = vec![held("A02", "222222")];
+        let (changed, attempts) = MultiUserState::recheck_held_sections(&mock, &mut job, &permits).await;
+        assert!(!changed);
+        assert!(attempts.is_empty());
+        assert_eq!(job.held_sections.len(), 1);
+        assert_eq!(mock.add_calls.lock().unwrap().len(), 2);
+    }
 }
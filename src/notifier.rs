@@ -1,15 +1,281 @@
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::error::Error as StdError;
 use lettre::transport::smtp::authentication::Credentials;
+use lettre::message::Mailbox;
 use lettre::{Message, SmtpTransport, Transport};
-use reqwest::Client as HttpClient;
-use log::{info, error};
-use crate::config::NotificationConfig;
+use reqwest::{Client as HttpClient, StatusCode};
+use chrono::{DateTime, Utc};
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::config::{DiscordFormat, NotificationConfig, DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY};
+use crate::utils::get_retry_strategy;
+
+const DISCORD_MAX_ATTEMPTS: u32 = 3;
+const OPENING_SUBJECT: &str = "WebReg Course Opening Alert!";
+const ALERT_SUBJECT: &str = "WebReg Connection Alert";
+const DISCORD_MAX_RETRY_AFTER_SECS: f64 = 30.0;
+const PUSHOVER_URL: &str = "https://api.pushover.net/1/messages.json";
+// Discord rejects embeds past these lengths
+const EMBED_TITLE_MAX_CHARS: usize = 256;
+const EMBED_DESCRIPTION_MAX_CHARS: usize = 4096;
+
+/// How urgently a notification should reach the phone (only Pushover distinguishes these)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    #[default]
+    Normal,
+    /// Bypasses the user's Pushover quiet hours
+    High,
+}
+
+impl Priority {
+    fn pushover_value(self) -> &'static str {
+        match self {
+            Priority::Normal => "0",
+            Priority::High => "1",
+        }
+    }
+}
+
+/// What a notification reports, which sets the sidebar color of a Discord embed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeKind {
+    #[default]
+    Info,
+    Opening,
+    Success,
+    Error,
+}
+
+impl NoticeKind {
+    fn embed_color(self) -> u32 {
+        match self {
+            NoticeKind::Info => 0x3498DB,
+            NoticeKind::Opening => 0xF1C40F,
+            NoticeKind::Success => 0x2ECC71,
+            NoticeKind::Error => 0xE74C3C,
+        }
+    }
+}
+
+/// The section a notification is about, shown as fields of a Discord embed
+#[derive(Debug, Clone, Copy)]
+pub struct CourseNotice<'a> {
+    pub department: &'a str,
+    pub course_code: &'a str,
+    pub section: &'a str,
+    pub seats: Option<(i64, i64)>,  // Available and total, when the notification knows them
+}
+
+/// An owned `CourseNotice`, kept with a queued message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedCourse {
+    pub department: String,
+    pub course_code: String,
+    pub section: String,
+    pub seats: Option<(i64, i64)>,
+}
+
+impl QueuedCourse {
+    fn notice(&self) -> CourseNotice<'_> {
+        CourseNotice {
+            department: &self.department,
+            course_code: &self.course_code,
+            section: &self.section,
+            seats: self.seats,
+        }
+    }
+}
+
+impl From<&CourseNotice<'_>> for QueuedCourse {
+    fn from(course: &CourseNotice<'_>) -> Self {
+        Self {
+            department: course.department.to_string(),
+            course_code: course.course_code.to_string(),
+            section: course.section.to_string(),
+            seats: course.seats,
+        }
+    }
+}
+
+/// Where one delivery of a notification goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Email,
+    Discord,
+    Pushover,
+    AlertEmail,
+    AlertDiscord,
+    SuccessWebhook,
+}
+
+impl Channel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Channel::Email => "email",
+            Channel::Discord => "discord",
+            Channel::Pushover => "pushover",
+            Channel::AlertEmail => "alert_email",
+            Channel::AlertDiscord => "alert_discord",
+            Channel::SuccessWebhook => "success_webhook",
+        }
+    }
+}
+
+/// One channel's share of a notification, after its rate limit has been applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    pub channel: Channel,
+    pub message: String,  // The success webhook's JSON body for `Channel::SuccessWebhook`
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub kind: NoticeKind,
+    #[serde(default)]
+    pub course: Option<QueuedCourse>,
+}
+
+/// Durable storage a notifier hands messages to instead of sending them itself.
+/// Whatever drains it delivers each message with `Notifier::deliver`.
+#[async_trait]
+pub trait Outbox: Send + Sync {
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<(), Box<dyn StdError + Send + Sync>>;
+}
+
+/// Payload POSTed to the success webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollmentEvent {
+    pub job_id: Uuid,
+    pub department: String,
+    pub course_code: String,
+    pub section: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Minimum spacing between sends on one channel. A message inside the window is
+/// held and delivered with the next one the channel lets through, or on its own
+/// once the window closes.
+struct ChannelThrottle {
+    min_interval: Duration,
+    state: Mutex<ChannelThrottleState>,
+}
+
+#[derive(Default)]
+struct ChannelThrottleState {
+    last_sent: Option<Instant>,
+    held: Vec<String>,
+    flush_scheduled: bool,
+}
+
+/// What a channel's throttle does with a message
+#[derive(Debug, PartialEq, Eq)]
+enum Admission {
+    /// Send this text now; any held messages are folded in
+    Send(String),
+    /// Held back. The first message held in a window asks for a flush after the given delay.
+    Held(Option<Duration>),
+}
+
+/// What a scheduled flush of held messages should do
+#[derive(Debug, PartialEq, Eq)]
+enum Flush {
+    Send(String),
+    /// A later send restarted the window; look again after this long
+    Wait(Duration),
+    /// A later send already carried the held messages
+    Done,
+}
+
+impl ChannelThrottle {
+    fn new(min_interval_secs: u64) -> Self {
+        Self {
+            min_interval: Duration::from_secs(min_interval_secs),
+            state: Mutex::new(ChannelThrottleState::default()),
+        }
+    }
+
+    /// Let `message` through or hold it. An urgent message skips the window and leaves
+    /// it, and anything held, as it was.
+    fn admit(&self, message: &str, urgent: bool) -> Admission {
+        if self.min_interval.is_zero() || urgent {
+            return Admission::Send(message.to_string());
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(remaining) = state.last_sent
+            .map(|last| self.min_interval.saturating_sub(last.elapsed()))
+            .filter(|remaining| !remaining.is_zero())
+        {
+            state.held.push(message.to_string());
+            let flush_after = (!state.flush_scheduled).then_some(remaining);
+            state.flush_scheduled = true;
+            return Admission::Held(flush_after);
+        }
+
+        state.last_sent = Some(Instant::now());
+        if state.held.is_empty() {
+            return Admission::Send(message.to_string());
+        }
+        let held = std::mem::take(&mut state.held);
+        Admission::Send(format!(
+            "{}\n\n--- {} earlier notification(s) held back by the rate limit ---\n\n{}",
+            message,
+            held.len(),
+            held.join("\n\n")
+        ))
+    }
+
+    /// The messages still held, as one text, once the window has closed
+    fn take_held(&self) -> Flush {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.held.is_empty() {
+            state.flush_scheduled = false;
+            return Flush::Done;
+        }
+        if let Some(remaining) = state.last_sent
+            .map(|last| self.min_interval.saturating_sub(last.elapsed()))
+            .filter(|remaining| !remaining.is_zero())
+        {
+            return Flush::Wait(remaining);
+        }
+
+        state.flush_scheduled = false;
+        state.last_sent = Some(Instant::now());
+        let held = std::mem::take(&mut state.held);
+        Flush::Send(format!("{} notification(s) held back by the rate limit:\n\n{}", held.len(), held.join("\n\n")))
+    }
+}
+
+/// Per-channel throttles, shared by every clone of a notifier
+struct ChannelThrottles {
+    email: ChannelThrottle,
+    discord: ChannelThrottle,
+    pushover: ChannelThrottle,
+}
+
+impl ChannelThrottles {
+    fn get(&self, channel: Channel) -> Option<&ChannelThrottle> {
+        match channel {
+            Channel::Email => Some(&self.email),
+            Channel::Discord => Some(&self.discord),
+            Channel::Pushover => Some(&self.pushover),
+            _ => None,
+        }
+    }
+}
 
 pub struct Notifier {
     smtp_transport: SmtpTransport,
     http_client: HttpClient,
     config: NotificationConfig,
+    throttles: Arc<ChannelThrottles>,
+    outbox: Option<Arc<dyn Outbox>>,
 }
 
 impl Clone for Notifier {
@@ -18,12 +284,19 @@ impl Clone for Notifier {
             smtp_transport: self.smtp_transport.clone(),
             http_client: self.http_client.clone(),
             config: self.config.clone(),
+            throttles: Arc::clone(&self.throttles),
+            outbox: self.outbox.clone(),
         }
     }
 }
 
 impl Notifier {
     pub fn new(config: &NotificationConfig) -> Result<Self, Box<dyn StdError + Send + Sync>> {
+        Self::with_proxy(config, None)
+    }
+
+    /// Like `new`, but Discord and Pushover requests go through `proxy`. Email still uses SMTP directly.
+    pub fn with_proxy(config: &NotificationConfig, proxy: Option<&str>) -> Result<Self, Box<dyn StdError + Send + Sync>> {
         let creds = Credentials::new(
             config.gmail_address.clone(),
             config.gmail_app_password.clone(),
@@ -34,73 +307,488 @@ impl Notifier {
             .credentials(creds)
             .build();
 
-        let http_client = HttpClient::builder()
-            .timeout(Duration::from_secs(10))
-            .build()?;
+        let mut http_client = HttpClient::builder().timeout(Duration::from_secs(10));
+        if let Some(proxy) = proxy {
+            http_client = http_client.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        let http_client = http_client.build()?;
 
         Ok(Self {
             smtp_transport,
             http_client,
             config: config.clone(),
+            throttles: Arc::new(ChannelThrottles {
+                email: ChannelThrottle::new(config.email_min_interval_secs),
+                discord: ChannelThrottle::new(config.discord_min_interval_secs),
+                pushover: ChannelThrottle::new(config.pushover_min_interval_secs),
+            }),
+            outbox: None,
         })
     }
 
+    /// Queue every send in `outbox` rather than delivering it. Rate limits still apply before queueing.
+    pub fn with_outbox(mut self, outbox: Arc<dyn Outbox>) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    pub fn notifies_on_close(&self) -> bool {
+        self.config.notify_on_close
+    }
+
+    pub fn notifies_on_recovery(&self) -> bool {
+        self.config.notify_on_recovery
+    }
+
     pub async fn send_notification(&self, message: &str) {
-        self.send_email(message).await;
-        self.send_discord(message).await;
-        info!("Notification sent: {}", message);
+        self.send_priority_notification(message, Priority::Normal).await;
     }
 
-    async fn send_email(&self, content: &str) {
-        for recipient in &self.config.email_recipients {
-            let from_address = match format!("WebReg Monitor <{}>", self.config.gmail_address).parse() {
-                Ok(addr) => addr,
-                Err(e) => {
-                    error!("Invalid from address '{}': {:?}", self.config.gmail_address, e);
-                    continue;
+    pub async fn send_priority_notification(&self, message: &str, priority: Priority) {
+        self.notify(message, priority, NoticeKind::Info, None).await;
+    }
+
+    /// A notification about one section; Discord embeds show its kind and details
+    pub async fn send_course_notification(&self, message: &str, priority: Priority, kind: NoticeKind, course: &CourseNotice<'_>) {
+        self.notify(message, priority, kind, Some(course)).await;
+    }
+
+    /// Send on every channel, subject to each channel's minimum interval. High priority
+    /// messages skip the intervals, as do dedicated alert channels, since both need prompt action.
+    async fn notify(&self, message: &str, priority: Priority, kind: NoticeKind, course: Option<&CourseNotice<'_>>) {
+        let course = course.map(QueuedCourse::from);
+        let channels = [
+            (Channel::Email, &self.throttles.email, !self.config.email_recipients.is_empty()),
+            (Channel::Discord, &self.throttles.discord, !self.config.discord_webhook_url.is_empty()),
+            (Channel::Pushover, &self.throttles.pushover, self.has_pushover()),
+        ];
+
+        for (channel, throttle, configured) in channels {
+            if !configured {
+                continue;
+            }
+            match throttle.admit(message, priority == Priority::High) {
+                Admission::Send(text) => self.dispatch(OutboxMessage {
+                    channel,
+                    message: text,
+                    priority,
+                    kind,
+                    course: course.clone(),
+                }).await,
+                Admission::Held(flush_after) => {
+                    info!("{} notification held back by the rate limit", channel.as_str());
+                    if let Some(delay) = flush_after {
+                        self.flush_held_after(channel, delay);
+                    }
                 }
-            };
+            }
+        }
+        info!("Notification sent: {}", message);
+    }
 
-            let to_address = match recipient.parse() {
-                Ok(addr) => addr,
-                Err(e) => {
-                    error!("Invalid recipient address '{}': {:?}", recipient, e);
-                    continue;
+    /// Send whatever `channel` is still holding once its window closes, so a burst
+    /// isn't stuck waiting for a later notification to carry it
+    fn flush_held_after(&self, channel: Channel, delay: Duration) {
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            let Some(throttle) = notifier.throttles.get(channel) else { return };
+            let mut delay = delay;
+            let text = loop {
+                tokio::time::sleep(delay).await;
+                match throttle.take_held() {
+                    Flush::Send(text) => break text,
+                    Flush::Wait(remaining) => delay = remaining,
+                    Flush::Done => return,
                 }
             };
+            notifier.dispatch(OutboxMessage {
+                channel,
+                message: text,
+                priority: Priority::Normal,
+                kind: NoticeKind::Info,
+                course: None,
+            }).await;
+        });
+    }
 
-            let email = match Message::builder()
-                .from(from_address)
-                .to(to_address)
-                .subject("WebReg Course Opening Alert!")
-                .body(content.to_string()) {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        error!("Failed to build email message: {:?}", e);
-                        continue;
-                    }
+    /// Connectivity problems that need manual action (e.g. an expired cookie).
+    /// Goes to the dedicated alert channel when one is configured, otherwise to the usual channels.
+    pub async fn send_alert(&self, message: &str) {
+        let has_alert_channel = !self.config.alert_email_recipients.is_empty()
+            || !self.config.alert_webhook_url.is_empty();
+        if !has_alert_channel {
+            self.notify(message, Priority::Normal, NoticeKind::Error, None).await;
+            return;
+        }
+
+        let alert = |channel| OutboxMessage {
+            channel,
+            message: message.to_string(),
+            priority: Priority::Normal,
+            kind: NoticeKind::Error,
+            course: None,
+        };
+        if !self.config.alert_email_recipients.is_empty() {
+            self.dispatch(alert(Channel::AlertEmail)).await;
+        }
+        if !self.config.alert_webhook_url.is_empty() {
+            self.dispatch(alert(Channel::AlertDiscord)).await;
+        }
+        info!("Alert sent: {}", message);
+    }
+
+    /// POST an enrollment event to the success webhook, retrying with backoff
+    pub async fn send_success_webhook(&self, event: &EnrollmentEvent) {
+        if self.config.success_webhook_url.is_empty() {
+            return;
+        }
+
+        let body = match serde_json::to_string(event) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Could not serialize success webhook payload: {}", e);
+                return;
+            }
+        };
+        info!("Success webhook for {} {} section {}", event.department, event.course_code, event.section);
+        self.dispatch(OutboxMessage {
+            channel: Channel::SuccessWebhook,
+            message: body,
+            priority: Priority::Normal,
+            kind: NoticeKind::Success,
+            course: None,
+        }).await;
+    }
+
+    /// Queue the message when there's an outbox, falling back to sending it now if queueing fails
+    async fn dispatch(&self, message: OutboxMessage) {
+        if let Some(outbox) = &self.outbox {
+            match outbox.enqueue(&message).await {
+                Ok(()) => return,
+                Err(e) => warn!("Could not queue {} notification, sending it directly: {}", message.channel.as_str(), e),
+            }
+        }
+
+        if let Err(e) = self.deliver(&message).await {
+            error!("Could not send {} notification: {}", message.channel.as_str(), e);
+        }
+    }
+
+    /// Send one queued message on its channel, to wherever this notifier's config points that channel.
+    /// A channel that's no longer configured counts as delivered.
+    pub async fn deliver(&self, message: &OutboxMessage) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let course = message.course.as_ref().map(QueuedCourse::notice);
+        match message.channel {
+            Channel::Email => self.send_email(&self.config.email_recipients, OPENING_SUBJECT, &message.message)?,
+            Channel::AlertEmail => self.send_email(&self.config.alert_email_recipients, ALERT_SUBJECT, &message.message)?,
+            Channel::Discord | Channel::AlertDiscord => {
+                let webhook_url = match message.channel {
+                    Channel::Discord => &self.config.discord_webhook_url,
+                    _ => &self.config.alert_webhook_url,
                 };
+                if webhook_url.is_empty() {
+                    return Ok(());
+                }
+                self.post_discord(webhook_url, &message.message, message.kind, course.as_ref()).await?;
+                info!("Discord webhook message sent");
+            }
+            Channel::Pushover => {
+                if !self.has_pushover() {
+                    return Ok(());
+                }
+                self.post_pushover(&message.message, message.priority).await?;
+                info!("Pushover notification sent");
+            }
+            Channel::SuccessWebhook => {
+                if self.config.success_webhook_url.is_empty() {
+                    return Ok(());
+                }
+                tokio_retry::Retry::spawn(get_retry_strategy(DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY), || async {
+                    self.http_client.post(&self.config.success_webhook_url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(message.message.clone())
+                        .send()
+                        .await?
+                        .error_for_status()
+                }).await?;
+                info!("Success webhook delivered");
+            }
+        }
+        Ok(())
+    }
+
+    /// Send to every configured channel, returning the first failure instead of only logging it
+    pub async fn send_checked(&self, message: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        for recipient in &self.config.email_recipients {
+            let email = self.build_email(recipient, OPENING_SUBJECT, message)?;
+            self.smtp_transport.send(&email)?;
+        }
+
+        if !self.config.discord_webhook_url.is_empty() {
+            self.post_discord(&self.config.discord_webhook_url, message, NoticeKind::Info, None).await?;
+        }
 
-            match self.smtp_transport.send(&email) {
-                Ok(_) => info!("📧 Email sent to {}", recipient),
-                Err(e) => error!("Could not send email to {}: {:?}", recipient, e),
+        if self.has_pushover() {
+            self.post_pushover(message, Priority::Normal).await?;
+        }
+
+        Ok(())
+    }
+
+    fn build_email(&self, recipient: &str, subject: &str, content: &str) -> Result<Message, Box<dyn StdError + Send + Sync>> {
+        let from_address: Mailbox = format!("WebReg Monitor <{}>", self.config.gmail_address)
+            .parse()
+            .map_err(|e| format!("Invalid from address '{}': {:?}", self.config.gmail_address, e))?;
+
+        let to_address: Mailbox = recipient
+            .parse()
+            .map_err(|e| format!("Invalid recipient address '{}': {:?}", recipient, e))?;
+
+        let email = Message::builder()
+            .from(from_address)
+            .to(to_address)
+            .subject(subject)
+            .body(content.to_string())?;
+
+        Ok(email)
+    }
+
+    /// Email every recipient, failing once all have been tried if any could not be sent to
+    fn send_email(&self, recipients: &[String], subject: &str, content: &str) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let mut failed = Vec::new();
+        for recipient in recipients {
+            let sent = self.build_email(recipient, subject, content)
+                .and_then(|email| self.smtp_transport.send(&email).map(|_| ()).map_err(Into::into));
+            match sent {
+                Ok(()) => info!("📧 Email sent to {}", recipient),
+                Err(e) => {
+                    error!("Could not send email to {}: {}", recipient, e);
+                    failed.push(recipient.as_str());
+                }
             }
         }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("email to {} failed", failed.join(", ")).into())
+        }
     }
 
-    async fn send_discord(&self, content: &str) {
-        let payload = serde_json::json!({
-            "content": content,
-            "username": "WebReg Monitor",
-            "avatar_url": "https://ucsd.edu/favicon.ico"
-        });
+    fn has_pushover(&self) -> bool {
+        !self.config.pushover_user_key.is_empty() && !self.config.pushover_api_token.is_empty()
+    }
+
+    async fn post_pushover(&self, message: &str, priority: Priority) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let form = [
+            ("token", self.config.pushover_api_token.as_str()),
+            ("user", self.config.pushover_user_key.as_str()),
+            ("title", OPENING_SUBJECT),
+            ("message", message),
+            ("priority", priority.pushover_value()),
+        ];
 
-        match self.http_client.post(&self.config.discord_webhook_url)
-            .json(&payload)
+        let response = self.http_client.post(PUSHOVER_URL)
+            .form(&form)
             .send()
-            .await {
-                Ok(_) => info!("Discord webhook message sent"),
-                Err(e) => error!("Could not send Discord webhook: {:?}", e),
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Pushover returned {}", status).into());
+        }
+        Ok(())
+    }
+
+    /// POST to the webhook, waiting out 429 rate limits a bounded number of times
+    async fn post_discord(
+        &self,
+        webhook_url: &str,
+        content: &str,
+        kind: NoticeKind,
+        course: Option<&CourseNotice<'_>>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let payload = match self.config.discord_format {
+            DiscordFormat::Plain => discord_payload(content),
+            DiscordFormat::Embed => discord_embed_payload(content, kind, course),
+        };
+
+        for attempt in 1..=DISCORD_MAX_ATTEMPTS {
+            let response = self.http_client.post(webhook_url)
+                .json(&payload)
+                .send()
+                .await?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+
+            if status == StatusCode::TOO_MANY_REQUESTS && attempt < DISCORD_MAX_ATTEMPTS {
+                let wait = retry_after_secs(response).await;
+                warn!("Discord webhook rate limited, retrying in {:.1}s (attempt {}/{})",
+                    wait, attempt, DISCORD_MAX_ATTEMPTS);
+                tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+                continue;
             }
+
+            return Err(format!("Discord webhook returned {}", status).into());
+        }
+
+        Err("Discord webhook still rate limited after retries".into())
+    }
+}
+
+/// Seconds Discord asked us to wait, from the Retry-After header or the JSON body
+async fn retry_after_secs(response: reqwest::Response) -> f64 {
+    let header = response.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<f64>().ok());
+
+    let secs = match header {
+        Some(secs) => secs,
+        None => response.json::<serde_json::Value>().await
+            .ok()
+            .and_then(|body| body.get("retry_after").and_then(|v| v.as_f64()))
+            .unwrap_or(1.0),
+    };
+
+    secs.clamp(0.0, DISCORD_MAX_RETRY_AFTER_SECS)
+}
+
+fn discord_payload(content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "content": content,
+        "username": "WebReg Monitor",
+        "avatar_url": "https://ucsd.edu/favicon.ico"
+    })
+}
+
+/// The message as an embed: its first line is the title, the rest the description
+fn discord_embed_payload(content: &str, kind: NoticeKind, course: Option<&CourseNotice<'_>>) -> serde_json::Value {
+    let (title, description) = content.split_once('\n').unwrap_or((content, ""));
+    let mut fields = Vec::new();
+    if let Some(course) = course {
+        fields.push(serde_json::json!({
+            "name": "Course",
+            "value": format!("{} {}", course.department, course.course_code),
+            "inline": true
+        }));
+        fields.push(serde_json::json!({ "name": "Section", "value": course.section, "inline": true }));
+        if let Some((available, total)) = course.seats {
+            fields.push(serde_json::json!({
+                "name": "Seats",
+                "value": format!("{}/{}", available, total),
+                "inline": true
+            }));
+        }
+    }
+
+    serde_json::json!({
+        "username": "WebReg Monitor",
+        "avatar_url": "https://ucsd.edu/favicon.ico",
+        "embeds": [{
+            "title": title.trim().chars().take(EMBED_TITLE_MAX_CHARS).collect::<String>(),
+            "description": description.trim().chars().take(EMBED_DESCRIPTION_MAX_CHARS).collect::<String>(),
+            "color": kind.embed_color(),
+            "fields": fields,
+            "timestamp": Utc::now().to_rfc3339()
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_throttle_holds_burst_until_next_send() {
+        let unlimited = ChannelThrottle::new(0);
+        assert!((0..3).all(|i| matches!(unlimited.admit(&format!("opening {}", i), false), Admission::Send(_))));
+
+        let email = ChannelThrottle { min_interval: Duration::from_millis(50), ..ChannelThrottle::new(0) };
+        assert_eq!(email.admit("opening 1", false), Admission::Send("opening 1".to_string()));
+        // Only the first held message asks for a flush
+        assert!(matches!(email.admit("opening 2", false), Admission::Held(Some(delay)) if delay <= Duration::from_millis(50)));
+        assert_eq!(email.admit("opening 3", false), Admission::Held(None));
+
+        // Once the window has passed, the held messages ride along with the next send
+        std::thread::sleep(Duration::from_millis(60));
+        let Admission::Send(text) = email.admit("opening 4", false) else { panic!("opening 4 was held") };
+        assert!(text.starts_with("opening 4") && text.contains("2 earlier") && text.contains("opening 3"));
+        // ...which leaves the scheduled flush nothing to send
+        assert_eq!(email.take_held(), Flush::Done);
+    }
+
+    #[test]
+    fn test_channel_throttle_flushes_held_and_lets_urgent_through() {
+        let email = ChannelThrottle { min_interval: Duration::from_millis(50), ..ChannelThrottle::new(0) };
+        assert!(matches!(email.admit("opening 1", false), Admission::Send(_)));
+        assert!(matches!(email.admit("opening 2", false), Admission::Held(Some(_))));
+
+        // A high priority message goes straight out on its own
+        assert_eq!(email.admit("dropped from A01", true), Admission::Send("dropped from A01".to_string()));
+        assert_eq!(email.admit("opening 3", false), Admission::Held(None));
+        assert!(matches!(email.take_held(), Flush::Wait(_)));
+
+        // With no later message, the flush sends the held ones on their own once the window closes
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(
+            email.take_held(),
+            Flush::Send("2 notification(s) held back by the rate limit:\n\nopening 2\n\nopening 3".to_string()),
+        );
+        assert_eq!(email.take_held(), Flush::Done);
+    }
+
+    #[test]
+    fn test_discord_embed_carries_color_and_section_fields() {
+        let course = CourseNotice { department: "CSE", course_code: "100", section: "A01", seats: Some((3, 40)) };
+        let payload = discord_embed_payload("Found opening in CSE 100 section A01!\n\nAttempting enrollment...", NoticeKind::Opening, Some(&course));
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "Found opening in CSE 100 section A01!");
+        assert_eq!(embed["description"], "Attempting enrollment...");
+        assert_eq!(embed["color"], 0xF1C40F);
+        assert_eq!(embed["fields"][0]["value"], "CSE 100");
+        assert_eq!(embed["fields"][2]["value"], "3/40");
+        assert!(payload.get("content").is_none());
+    }
+
+    #[derive(Default)]
+    struct RecordingOutbox(Mutex<Vec<OutboxMessage>>);
+
+    #[async_trait]
+    impl Outbox for RecordingOutbox {
+        async fn enqueue(&self, message: &OutboxMessage) -> Result<(), Box<dyn StdError + Send + Sync>> {
+            self.0.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbox_queues_one_message_per_rate_limited_channel() {
+        let outbox = Arc::new(RecordingOutbox::default());
+        let notifier = Notifier::new(&NotificationConfig {
+            gmail_address: "monitor@example.com".to_string(),
+            gmail_app_password: String::new(),
+            email_recipients: vec!["student@example.com".to_string()],
+            discord_webhook_url: "https://discord.example/webhook".to_string(),
+            email_min_interval_secs: 3600,
+            ..Default::default()
+        }).unwrap().with_outbox(outbox.clone());
+
+        let course = CourseNotice { department: "CSE", course_code: "100", section: "A01", seats: Some((1, 40)) };
+        notifier.send_course_notification("opening 1", Priority::High, NoticeKind::Opening, &course).await;
+        notifier.send_notification("opening 2").await;
+
+        // The email interval holds the second message back; nothing is sent directly
+        let queued = outbox.0.lock().unwrap().clone();
+        let channels: Vec<Channel> = queued.iter().map(|message| message.channel).collect();
+        assert_eq!(channels, vec![Channel::Email, Channel::Discord, Channel::Discord]);
+        assert_eq!(queued[0].course.as_ref().map(|course| course.section.as_str()), Some("A01"));
+
+        let stored = serde_json::to_value(&queued[0]).unwrap();
+        assert_eq!(stored["channel"], "email");
+        assert_eq!(serde_json::from_value::<OutboxMessage>(stored).unwrap(), queued[0]);
     }
 }
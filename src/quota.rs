@@ -0,0 +1,185 @@
+use std::error::Error as StdError;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use crate::utils::local_now;
+use crate::webreg::{SectionAvailability, WebRegClient};
+
+#[derive(Debug)]
+struct QuotaState {
+    day: NaiveDate,
+    count: u64,
+    unflushed: u64,     // Counted but not yet written to the database
+    notified: bool,     // The user has been told today's quota ran out
+}
+
+/// Daily cap on one user's WebReg requests, shared by all of their jobs.
+/// The count resets at midnight in the configured timezone.
+#[derive(Debug)]
+pub struct RequestQuota {
+    limit: u64,  // 0 means unlimited
+    state: Mutex<QuotaState>,
+}
+
+impl RequestQuota {
+    /// `count` is what the database already holds for `day`
+    pub fn new(limit: u64, day: NaiveDate, count: u64) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(QuotaState { day, count, unflushed: 0, notified: false }),
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn record(&self) {
+        self.record_on(local_now().date_naive());
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.is_exhausted_on(local_now().date_naive())
+    }
+
+    /// True the first time the quota is found exhausted each day, so the user is notified once
+    pub fn take_exhausted_notice(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        !std::mem::replace(&mut state.notified, true)
+    }
+
+    /// Requests counted since the last call, and the day they belong to
+    pub fn take_unflushed(&self) -> (NaiveDate, u64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        (state.day, std::mem::take(&mut state.unflushed))
+    }
+
+    /// Hand back requests that `take_unflushed` returned but couldn't be written
+    pub fn restore_unflushed(&self, day: NaiveDate, requests: u64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.day == day {
+            state.unflushed += requests;
+        }
+    }
+
+    fn record_on(&self, day: NaiveDate) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        roll_over(&mut state, day);
+        state.count += 1;
+        state.unflushed += 1;
+    }
+
+    fn is_exhausted_on(&self, day: NaiveDate) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        roll_over(&mut state, day);
+        self.limit > 0 && state.count >= self.limit
+    }
+}
+
+// Yesterday's unflushed requests are dropped with it; only today's count gates anything
+fn roll_over(state: &mut QuotaState, day: NaiveDate) {
+    if state.day != day {
+        *state = QuotaState { day, count: 0, unflushed: 0, notified: false };
+    }
+}
+
+/// Counts each `get_course_info` and `add_section` against the user's quota
+pub struct QuotaClient<'a, C: WebRegClient> {
+    inner: &'a C,
+    quota: &'a RequestQuota,
+}
+
+impl<'a, C: WebRegClient> QuotaClient<'a, C> {
+    pub fn new(inner: &'a C, quota: &'a RequestQuota) -> Self {
+        Self { inner, quota }
+    }
+}
+
+#[async_trait]
+impl<C: WebRegClient> WebRegClient for QuotaClient<'_, C> {
+    async fn get_course_info(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+    ) -> Result<Vec<SectionAvailability>, Box<dyn StdError + Send + Sync>> {
+        self.quota.record();
+        self.inner.get_course_info(term, department, course_code).await
+    }
+
+    async fn add_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        self.quota.record();
+        self.inner.add_section(term, section_id, waitlist).await
+    }
+
+    async fn is_on_schedule(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        self.inner.is_on_schedule(term, section_id, waitlist).await
+    }
+
+    async fn add_to_plan(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+        section_id: &str,
+        section_code: &str,
+        unit_count: u8,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        self.quota.record();
+        self.inner.add_to_plan(term, department, course_code, section_id, section_code, unit_count).await
+    }
+
+    async fn drop_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        self.quota.record();
+        self.inner.drop_section(term, section_id, waitlist).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, d).unwrap()
+    }
+
+    #[test]
+    fn test_quota_exhausts_and_resets_next_day() {
+        let quota = RequestQuota::new(3, day(1), 1);
+        quota.record_on(day(1));
+        assert!(!quota.is_exhausted_on(day(1)));
+        quota.record_on(day(1));
+        assert!(quota.is_exhausted_on(day(1)));
+        assert!(quota.take_exhausted_notice());
+        assert!(!quota.take_exhausted_notice());
+
+        assert!(!quota.is_exhausted_on(day(2)));
+        assert!(quota.take_exhausted_notice());
+    }
+
+    #[test]
+    fn test_unflushed_count_is_taken_once() {
+        let quota = RequestQuota::new(0, day(1), 10);
+        quota.record_on(day(1));
+        quota.record_on(day(1));
+        assert_eq!(quota.take_unflushed(), (day(1), 2));
+        assert_eq!(quota.take_unflushed(), (day(1), 0));
+        // Zero is unlimited
+        assert!(!quota.is_exhausted_on(day(1)));
+    }
+}
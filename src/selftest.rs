@@ -0,0 +1,100 @@
+// Deployment self-test for the multi-user server (`webreg-web-multiuser --selftest`)
+use std::error::Error as StdError;
+
+use crate::config::{NotificationConfig, ServerConfig, ENV_DISCORD_WEBHOOK_URL, ENV_GMAIL_APP_PASSWORD};
+use crate::db;
+use crate::encryption::EncryptionKey;
+use crate::notifier::Notifier;
+
+enum Outcome {
+    Pass(String),
+    Skipped(String),
+}
+
+type CheckResult = Result<Outcome, Box<dyn StdError + Send + Sync>>;
+
+/// Run every check, print a checklist, and return whether all of them passed
+pub async fn run() -> bool {
+    println!("WebReg Auto-Enroller self-test\n");
+
+    let results = [
+        ("ENCRYPTION_KEY decodes to 32 bytes and round-trips", check_encryption()),
+        ("DATABASE_URL connects and migrations are applied", check_database().await),
+        ("Test notification sends", check_notifications().await),
+    ];
+
+    let mut all_passed = true;
+    for (name, result) in results {
+        match result {
+            Ok(Outcome::Pass(detail)) => println!("[PASS] {} - {}", name, detail),
+            Ok(Outcome::Skipped(reason)) => println!("[SKIP] {} - {}", name, reason),
+            Err(e) => {
+                all_passed = false;
+                println!("[FAIL] {} - {}", name, e);
+            }
+        }
+    }
+
+    println!();
+    if all_passed {
+        println!("All checks passed");
+    } else {
+        println!("Some checks failed");
+    }
+
+    all_passed
+}
+
+fn check_encryption() -> CheckResult {
+    // from_env already rejects bad base64 and keys that aren't 32 bytes
+    let key = EncryptionKey::from_env()?;
+
+    let sample = "webreg-selftest";
+    let (ciphertext, nonce) = key.encrypt(sample)?;
+    if key.decrypt(&ciphertext, &nonce)? != sample {
+        return Err("Decrypted value did not match the original".into());
+    }
+
+    Ok(Outcome::Pass("encrypt/decrypt round-trip succeeded".to_string()))
+}
+
+async fn check_database() -> CheckResult {
+    let database_url = std::env::var("DATABASE_URL")
+        .map_err(|_| "DATABASE_URL environment variable not set")?;
+
+    let pool = db::connect_pool(&database_url, &ServerConfig::from_env()).await?;
+    let pending = db::pending_migrations(&pool).await?;
+    if !pending.is_empty() {
+        return Err(format!("{} migration(s) not applied: {:?} (run `sqlx migrate run`)", pending.len(), pending).into());
+    }
+
+    Ok(Outcome::Pass("connected and all migrations applied".to_string()))
+}
+
+async fn check_notifications() -> CheckResult {
+    let gmail_address = std::env::var("GMAIL_ADDRESS").unwrap_or_default();
+    let gmail_app_password = std::env::var(ENV_GMAIL_APP_PASSWORD).unwrap_or_default();
+    let discord_webhook_url = std::env::var(ENV_DISCORD_WEBHOOK_URL).unwrap_or_default();
+
+    let email_configured = !gmail_address.is_empty() && !gmail_app_password.is_empty();
+    if !email_configured && discord_webhook_url.is_empty() {
+        return Ok(Outcome::Skipped(format!(
+            "set GMAIL_ADDRESS and {}, or {}, to send a test notification",
+            ENV_GMAIL_APP_PASSWORD, ENV_DISCORD_WEBHOOK_URL
+        )));
+    }
+
+    // Email goes to the sending address itself
+    let email_recipients = if email_configured { vec![gmail_address.clone()] } else { Vec::new() };
+    let notifier = Notifier::new(&NotificationConfig {
+        gmail_address,
+        gmail_app_password,
+        email_recipients,
+        discord_webhook_url,
+        ..Default::default()
+    })?;
+
+    notifier.send_checked("WebReg Auto-Enroller self-test notification").await?;
+
+    Ok(Outcome::Pass("test notification delivered".to_string()))
+}
@@ -1,76 +1,60 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use std::error::Error as StdError;
 use std::path::Path;
 use std::fs;
 use std::collections::HashMap;
+use std::sync::Arc;
 use webweg::wrapper::WebRegWrapper;
-use chrono::Local;
-use log::{info, error};
-use crate::config::{AppConfig, CONFIG_PATH};
-use crate::stats::{EnrollmentStats, HealthStatus};
+use log::{info, warn, error};
+use crate::config::{non_empty_env, with_promoted_cookie, AppConfig, WebRegConfig, CONFIG_PATH, ENV_WEBREG_COOKIE};
+use crate::stats::{EnrollmentStats, HealthStatus, LatencyTracker};
 use crate::notifier::Notifier;
-use crate::webreg::{initialize_webreg, is_connection_valid};
-use crate::monitor::monitor_section_with_retry;
-use crate::utils::format_duration;
+use crate::webreg::{associate_terms, build_wrapper, initialize_webreg, check_session, SessionStatus, ThrottleBackoff};
+use crate::monitor::{monitor_section_with_retry, OpeningFilter};
+use crate::utils::{data_path, format_duration, local_now, set_timezone, RetryBudget};
 
 pub struct AppState {
     pub stats: EnrollmentStats,
     pub config: AppConfig,
     pub notifier: Notifier,
-    pub wrapper: WebRegWrapper,
+    pub wrapper: Arc<WebRegWrapper>,
+    wrapper_session: WebRegConfig,  // Settings the cached wrapper was built from
     pub start_time: SystemTime,
     pub last_check_time: String,
     pub is_connected: bool,
     pub term: String,
+    pub latency: LatencyTracker,
+    pub throttle: ThrottleBackoff,
+    pub openings: OpeningFilter,
 }
 
 impl AppState {
-    pub async fn new() -> Result<Self, Box<dyn StdError + Send + Sync>> {
+    /// State for a config from `load_config`, loaded by the caller so logging can start under its data_dir
+    pub async fn new(config: AppConfig) -> Result<Self, Box<dyn StdError + Send + Sync>> {
         println!("Starting AppState::new()");
 
-        if !Path::new(CONFIG_PATH).exists() {
-            println!("Config file not found!");
-            return Err("config.toml not found in current directory".into());
-        }
-
-        println!("Reading config file...");
-        let config_content = fs::read_to_string(CONFIG_PATH)
-            .map_err(|e| {
-                println!("Error reading config: {:?}", e);
-                format!("Failed to read config.toml: {}", e)
-            })?;
-
-        println!("Parsing config content...");
-        println!("Config content: {}", config_content);
-
-        let config: AppConfig = toml::from_str(&config_content)
-            .map_err(|e| {
-                println!("Error parsing TOML: {:?}", e);
-                format!("Failed to parse config.toml: {}", e)
-            })?;
+        set_timezone(config.monitoring.timezone);
 
-        println!("Successfully parsed config");
-
-        // Initialize stats with default values
+        // Carry the totals over from the last run; start fresh without a readable stats file
         println!("Initializing stats...");
-        let stats = EnrollmentStats {
-            start_time: Local::now().to_string(),
-            last_updated: Local::now().to_string(),
+        let stats = EnrollmentStats::load(&data_path(&config.monitoring.stats_file)).unwrap_or_else(|| EnrollmentStats {
+            start_time: local_now().to_string(),
+            last_updated: local_now().to_string(),
             total_checks: 0,
             openings_found: 0,
             enrollment_attempts: 0,
             successful_enrollments: 0,
             errors: 0,
             section_failures: HashMap::new(),
-        };
+        });
 
         println!("Creating WebReg wrapper and notifier...");
         let term = config.webreg.term.clone();
-        let notifier = Notifier::new(&config.notifications)?;
+        let notifier = Notifier::with_proxy(&config.notifications, config.webreg.proxy.as_deref())?;
 
         // Try to initialize WebReg, but don't fail if it doesn't work
         // (cookie might be expired, user can update it via web UI)
-        let (wrapper, is_connected) = match initialize_webreg(&config.webreg).await {
+        let (wrapper, is_connected) = match initialize_webreg(&config.webreg, &config.terms()).await {
             Ok(w) => {
                 println!("WebReg connection successful");
                 (w, true)
@@ -78,36 +62,104 @@ impl AppState {
             Err(e) => {
                 println!("WebReg connection failed (this is OK for web mode): {:?}", e);
                 // Create a basic wrapper even if connection failed
-                let wrapper = WebRegWrapper::builder()
-                    .with_cookies(&config.webreg.cookie)
-                    .try_build_wrapper()
-                    .ok_or("Failed to create WebReg wrapper")?;
-                (wrapper, false)
+                (build_wrapper(&config.webreg)?, false)
             }
         };
 
+        // What the wrapper was built from, taken before config moves into the state
+        let wrapper_session = config.webreg.clone();
+
         println!("AppState::new() completed successfully");
         Ok(Self {
             stats,
             config,
             notifier,
-            wrapper,
+            wrapper: Arc::new(wrapper),
+            wrapper_session,
             start_time: SystemTime::now(),
-            last_check_time: Local::now().to_string(),
+            last_check_time: local_now().to_string(),
             is_connected,
             term,
+            latency: LatencyTracker::default(),
+            throttle: ThrottleBackoff::default(),
+            openings: OpeningFilter::default(),
         })
     }
 
-    pub fn clone_wrapper(&self) -> Result<WebRegWrapper, Box<dyn StdError + Send + Sync>> {
-        WebRegWrapper::builder()
-            .with_cookies(&self.config.webreg.cookie)
-            .try_build_wrapper()
-            .ok_or_else(|| "Failed to clone WebRegWrapper - invalid cookie".into())
+    /// Re-read config.toml and apply the differences to the running state.
+    /// Stats are kept; the wrapper is only rebuilt when the cookie or term changed.
+    /// Returns the names of the sections that changed.
+    pub async fn reload_config(&mut self) -> Result<Vec<String>, Box<dyn StdError + Send + Sync>> {
+        let new_config = load_config()?;
+        let mut changes = Vec::new();
+
+        if !new_config.webreg.same_session(&self.config.webreg) {
+            changes.push("webreg session".to_string());
+            match initialize_webreg(&new_config.webreg, &new_config.terms()).await {
+                Ok(wrapper) => {
+                    self.wrapper = Arc::new(wrapper);
+                    if !self.is_connected {
+                        announce_recovery(&self.notifier).await;
+                    }
+                    self.is_connected = true;
+                }
+                Err(e) => {
+                    error!("Reloaded cookie could not connect to WebReg: {:?}", e);
+                    self.wrapper = Arc::new(build_wrapper(&new_config.webreg)?);
+                    self.is_connected = false;
+                }
+            }
+            self.wrapper_session = new_config.webreg.clone();
+            self.term = new_config.webreg.term.clone();
+        } else if new_config.terms() != self.config.terms() {
+            // Same session, but a course moved to a term it hasn't been associated with
+            if let Err(e) = associate_terms(&self.wrapper, &new_config.terms()).await {
+                error!("Could not associate the reloaded terms: {:?}", e);
+            }
+        }
+
+        if new_config.webreg.polling_interval != self.config.webreg.polling_interval {
+            changes.push("polling interval".to_string());
+        }
+
+        if new_config.notifications != self.config.notifications || new_config.webreg.proxy != self.config.webreg.proxy {
+            self.notifier = Notifier::with_proxy(&new_config.notifications, new_config.webreg.proxy.as_deref())?;
+            changes.push("notifications".to_string());
+        }
+
+        if new_config.courses != self.config.courses {
+            changes.push("courses".to_string());
+        }
+
+        if new_config.monitoring != self.config.monitoring {
+            set_timezone(new_config.monitoring.timezone);
+            changes.push("monitoring".to_string());
+        }
+
+        self.config = new_config;
+        Ok(changes)
+    }
+
+    /// The cached wrapper, rebuilt only when the cookie, term or headers changed since it was built
+    /// (e.g. a new job submitted through the web API)
+    pub async fn current_wrapper(&mut self) -> Result<Arc<WebRegWrapper>, Box<dyn StdError + Send + Sync>> {
+        if !self.wrapper_session.same_session(&self.config.webreg) {
+            let wrapper = build_wrapper(&self.config.webreg)
+                .map_err(|e| format!("Failed to build WebRegWrapper - {}", e))?;
+            if let Err(e) = associate_terms(&wrapper, &self.config.terms()).await {
+                warn!("Could not associate terms with the rebuilt wrapper: {:?}", e);
+            }
+
+            self.wrapper = Arc::new(wrapper);
+            self.wrapper_session = self.config.webreg.clone();
+            self.term = self.config.webreg.term.clone();
+        }
+
+        Ok(Arc::clone(&self.wrapper))
     }
 
     pub fn update_stats(&mut self) {
-        self.stats.last_updated = Local::now().to_string();
+        self.stats.last_updated = local_now().to_string();
         let stats_json = match serde_json::to_string_pretty(&self.stats) {
             Ok(json) => json,
             Err(e) => {
@@ -115,7 +167,7 @@ impl AppState {
                 return;
             }
         };
-        if let Err(e) = fs::write(&self.config.monitoring.stats_file, stats_json) {
+        if let Err(e) = fs::write(data_path(&self.config.monitoring.stats_file), stats_json) {
             error!("Failed to write stats file: {:?}", e);
         }
     }
@@ -138,6 +190,8 @@ impl AppState {
             error_count: self.stats.errors,
             success_rate,
             total_checks: self.stats.total_checks,
+            avg_latency_ms: self.latency.average_ms(),
+            p95_latency_ms: self.latency.p95_ms(),
         }
     }
 
@@ -150,14 +204,25 @@ impl AppState {
         self.stats.total_checks += 1;
         let result = monitor_section_with_retry(
             &self.wrapper,
+            None,
             &self.term,
             section,
             department,
             course_code,
             self.config.webreg.polling_interval,
             self.config.monitoring.seat_threshold,
+            self.config.monitoring.threshold_direction,
+            self.config.monitoring.effective_mode(),
+            self.config.monitoring.effective_mode().default_target(),
+            Duration::from_millis(self.config.monitoring.recheck_freshness_ms),
+            true,
             &self.notifier,
-        ).await;
+            &self.latency,
+            &self.throttle,
+            &self.openings.with_required(self.config.monitoring.consecutive_polls),
+            self.config.monitoring.retry_policy(),
+            &RetryBudget::new(self.config.monitoring.cycle_retry_budget),
+        ).await.map(|opening| opening.map(|opening| opening.section_id));
 
         match &result {
             Ok(Some(_)) => self.stats.openings_found += 1,
@@ -170,10 +235,81 @@ impl AppState {
     }
 }
 
+/// Read and parse config.toml, applying environment overrides
+pub fn load_config() -> Result<AppConfig, Box<dyn StdError + Send + Sync>> {
+    if !Path::new(CONFIG_PATH).exists() {
+        error!("Config file not found!");
+        return Err("config.toml not found in current directory".into());
+    }
+
+    info!("Reading config file...");
+    let config_content = fs::read_to_string(CONFIG_PATH)
+        .map_err(|e| {
+            error!("Error reading config: {:?}", e);
+            format!("Failed to read config.toml: {}", e)
+        })?;
+
+    info!("Parsing config content...");
+
+    let mut config: AppConfig = toml::from_str(&config_content)
+        .map_err(|e| {
+            error!("Error parsing TOML: {:?}", e);
+            format!("Failed to parse config.toml: {}", e)
+        })?;
+
+    // Environment variables take precedence over secrets in the file
+    config.apply_env_overrides();
+    config.normalize_courses();
+    for repeat in config.dedupe_sections() {
+        warn!("Section {} is listed more than once in config.toml; monitoring it once", repeat);
+    }
+    if let Some(note) = config.monitoring.effective_mode().ignored_direction(config.monitoring.threshold_direction) {
+        warn!("{}", note);
+    }
+
+    Ok(config)
+}
+
 pub async fn refresh_cookie(state: &mut AppState) -> Result<(), Box<dyn StdError + Send + Sync>> {
     info!("Checking WebReg session status...");
 
-    let is_valid = is_connection_valid(&state.wrapper, &state.term).await;
+    let status = check_session(&state.wrapper, &state.term).await;
+    match &status {
+        SessionStatus::Unreachable(reason) => {
+            // Says nothing about the cookie, so keep the current state and check again next time
+            warn!("Could not reach WebReg to check the session: {}", reason);
+            return Ok(());
+        }
+        SessionStatus::TermUnavailable => {
+            error!("Term {} is not available to this WebReg account", state.term);
+            return Err(format!("Term {} is not available in WebReg", state.term).into());
+        }
+        SessionStatus::Valid | SessionStatus::Expired => {}
+    }
+    let is_valid = status.is_valid();
+
+    if !is_valid {
+        // Fail over to the backup cookie before declaring the session dead
+        if let Some(backup) = state.config.webreg.backup_cookie.take() {
+            warn!("Primary WebReg cookie failed, switching to the backup cookie");
+            state.config.webreg.cookie = backup;
+            let wrapper = state.current_wrapper().await?;
+
+            if check_session(&wrapper, &state.term).await.is_valid() {
+                state.is_connected = true;
+                persist_promoted_cookie(&state.config.webreg.cookie);
+                let msg = format!(
+                    "⚠️  Primary WebReg cookie expired, switched to the backup cookie.\n\
+                    Time: {}\n\
+                    Monitoring continues; add a new backup cookie to config.toml.",
+                    local_now().format("%Y-%m-%d %H:%M:%S")
+                );
+                state.notifier.send_alert(&msg).await;
+                return Ok(());
+            }
+            error!("Backup WebReg cookie is not valid either");
+        }
+    }
 
     if !is_valid && state.is_connected {
         // Cookie just expired (transition from connected to disconnected)
@@ -183,18 +319,52 @@ pub async fn refresh_cookie(state: &mut AppState) -> Result<(), Box<dyn StdError
             "⚠️  WebReg Cookie has expired!\n\
             Time: {}\n\
             Please update the cookie in config.toml to resume monitoring.",
-            Local::now().format("%Y-%m-%d %H:%M:%S")
+            local_now().format("%Y-%m-%d %H:%M:%S")
         );
 
-        state.notifier.send_notification(&msg).await;
+        state.notifier.send_alert(&msg).await;
         error!("WebReg cookie has expired!");
         return Err("Cookie expired".into());
     }
 
     if is_valid {
+        if !state.is_connected {
+            announce_recovery(&state.notifier).await;
+        }
         state.is_connected = true;
         info!("WebReg session is valid");
     }
 
     Ok(())
 }
+
+/// Write a promoted backup cookie to config.toml so a reload or restart doesn't go back
+/// to the dead one
+fn persist_promoted_cookie(cookie: &str) {
+    if non_empty_env(ENV_WEBREG_COOKIE).is_some() {
+        warn!("{} overrides config.toml; set it to the backup cookie before the next restart", ENV_WEBREG_COOKIE);
+        return;
+    }
+
+    let promoted = fs::read_to_string(CONFIG_PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|content| with_promoted_cookie(&content, cookie).ok_or_else(|| "no [webreg] cookie line".to_string()))
+        .and_then(|content| fs::write(CONFIG_PATH, content).map_err(|e| e.to_string()));
+    match promoted {
+        Ok(()) => info!("Saved the backup cookie as the primary cookie in {}", CONFIG_PATH),
+        Err(e) => error!("Could not save the backup cookie to {}: {}", CONFIG_PATH, e),
+    }
+}
+
+/// Tell the user monitoring is back after an expiry, when they want to hear it
+async fn announce_recovery(notifier: &Notifier) {
+    info!("WebReg connection restored");
+    if notifier.notifies_on_recovery() {
+        let msg = format!(
+            "✅ WebReg connection restored, monitoring resumed.\n\
+            Time: {}",
+            local_now().format("%Y-%m-%d %H:%M:%S")
+        );
+        notifier.send_alert(&msg).await;
+    }
+}
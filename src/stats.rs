@@ -1,11 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use log::warn;
+use crate::utils::timezone;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectionFailures {
     pub count: u64,
-    pub last_failure: DateTime<Local>,
+    pub last_failure: DateTime<Utc>,  // Stored in UTC; days are compared in the configured timezone
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -21,13 +29,38 @@ pub struct EnrollmentStats {
 }
 
 impl EnrollmentStats {
-    pub fn should_notify_for_section(&mut self, section_id: &str) -> bool {
-        let now = Local::now();
-        let today = now.date_naive();
+    /// Stats saved by an earlier run, or None when the file is missing or unreadable
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Could not read stats file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                warn!("Stats file {} is corrupt, starting fresh: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Record a failure and report whether it's still under the daily cap (0 = unlimited)
+    pub fn should_notify_for_section(&mut self, section_id: &str, daily_cap: u64) -> bool {
+        self.should_notify_for_section_at(section_id, daily_cap, Utc::now(), timezone())
+    }
+
+    /// `should_notify_for_section` with an explicit clock. Days roll over at midnight in `tz`.
+    pub fn should_notify_for_section_at(&mut self, section_id: &str, daily_cap: u64, now: DateTime<Utc>, tz: Tz) -> bool {
+        let today = now.with_timezone(&tz).date_naive();
 
         if let Some(failures) = self.section_failures.get(section_id) {
             // Check if the last failure was from a previous day
-            if failures.last_failure.date_naive() < today {
+            if failures.last_failure.with_timezone(&tz).date_naive() < today {
                 // Reset counter if it's a new day
                 self.section_failures.insert(section_id.to_string(), SectionFailures {
                     count: 1,
@@ -37,7 +70,7 @@ impl EnrollmentStats {
             }
 
             // If we're still in the same day, check failure count
-            if failures.count >= 3 {
+            if daily_cap > 0 && failures.count >= daily_cap {
                 return false;
             }
 
@@ -66,4 +99,141 @@ pub struct HealthStatus {
     pub error_count: u64,
     pub success_rate: f64,
     pub total_checks: u64,
+    pub avg_latency_ms: Option<f64>,
+    pub p95_latency_ms: Option<u64>,
+}
+
+const LATENCY_WINDOW: usize = 100;
+
+/// Rolling window of WebReg response times. Cloning shares the same window,
+/// so the monitoring loop can record while status handlers read.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyTracker {
+    samples: Arc<Mutex<VecDeque<u64>>>,
+}
+
+impl LatencyTracker {
+    pub fn record(&self, elapsed: Duration) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() == LATENCY_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed.as_millis() as u64);
+    }
+
+    pub fn average_ms(&self) -> Option<f64> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().sum::<u64>() as f64 / samples.len() as f64)
+    }
+
+    pub fn p95_ms(&self) -> Option<u64> {
+        let mut sorted: Vec<u64> = self.samples.lock().unwrap_or_else(|e| e.into_inner()).iter().copied().collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_unstable();
+        let index = ((sorted.len() as f64 * 0.95).ceil() as usize).saturating_sub(1);
+        Some(sorted[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const SECTION: &str = "CSE_100_A01_WI25";
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cap_suppresses_within_day() {
+        let mut stats = EnrollmentStats::default();
+        assert!(stats.should_notify_for_section_at(SECTION, 1, at(10, 12, 0), Tz::UTC));
+        assert!(!stats.should_notify_for_section_at(SECTION, 1, at(10, 13, 0), Tz::UTC));
+        assert!(!stats.should_notify_for_section_at(SECTION, 1, at(10, 23, 59), Tz::UTC));
+    }
+
+    #[test]
+    fn test_cap_resets_at_utc_midnight() {
+        let mut stats = EnrollmentStats::default();
+        assert!(stats.should_notify_for_section_at(SECTION, 1, at(10, 23, 58), Tz::UTC));
+        assert!(!stats.should_notify_for_section_at(SECTION, 1, at(10, 23, 59), Tz::UTC));
+        assert!(stats.should_notify_for_section_at(SECTION, 1, at(11, 0, 1), Tz::UTC));
+        assert_eq!(stats.section_failures[SECTION].count, 1);
+    }
+
+    #[test]
+    fn test_cap_survives_restart_across_midnight() {
+        let mut stats = EnrollmentStats::default();
+        assert!(stats.should_notify_for_section_at(SECTION, 1, at(10, 23, 58), Tz::UTC));
+
+        // Reloading from the persisted JSON must not change the outcome
+        let mut reloaded: EnrollmentStats =
+            serde_json::from_str(&serde_json::to_string(&stats).unwrap()).unwrap();
+        assert!(!reloaded.should_notify_for_section_at(SECTION, 1, at(10, 23, 59), Tz::UTC));
+        assert!(reloaded.should_notify_for_section_at(SECTION, 1, at(11, 0, 1), Tz::UTC));
+    }
+
+    #[test]
+    fn test_cap_resets_at_configured_midnight() {
+        let pacific = chrono_tz::America::Los_Angeles;
+        let mut stats = EnrollmentStats::default();
+        // 23:58 and 00:01 UTC are both Jan 10 afternoon in Pacific time
+        assert!(stats.should_notify_for_section_at(SECTION, 1, at(10, 23, 58), pacific));
+        assert!(!stats.should_notify_for_section_at(SECTION, 1, at(11, 0, 1), pacific));
+        // 08:01 UTC is 00:01 PST on Jan 11
+        assert!(stats.should_notify_for_section_at(SECTION, 1, at(11, 8, 1), pacific));
+    }
+
+    #[test]
+    fn test_zero_cap_is_unlimited() {
+        let mut stats = EnrollmentStats::default();
+        for minute in 0..10 {
+            assert!(stats.should_notify_for_section_at(SECTION, 0, at(10, 12, minute), Tz::UTC));
+        }
+    }
+
+    #[test]
+    fn test_load_keeps_saved_totals_and_skips_corrupt_files() {
+        let path = std::env::temp_dir().join(format!("webreg_stats_test_{}.json", std::process::id()));
+        assert!(EnrollmentStats::load(&path).is_none());
+
+        let saved = EnrollmentStats { total_checks: 42, successful_enrollments: 2, ..Default::default() };
+        fs::write(&path, serde_json::to_string(&saved).unwrap()).unwrap();
+        let loaded = EnrollmentStats::load(&path).unwrap();
+        assert_eq!((loaded.total_checks, loaded.successful_enrollments), (42, 2));
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(EnrollmentStats::load(&path).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_latency_average_and_p95() {
+        let latency = LatencyTracker::default();
+        assert_eq!((latency.average_ms(), latency.p95_ms()), (None, None));
+
+        for ms in 1..=20 {
+            latency.record(Duration::from_millis(ms));
+        }
+        assert_eq!(latency.average_ms(), Some(10.5));
+        assert_eq!(latency.p95_ms(), Some(19));
+    }
+
+    #[test]
+    fn test_latency_keeps_only_the_recent_window() {
+        let latency = LatencyTracker::default();
+        latency.record(Duration::from_secs(60));
+        for _ in 0..LATENCY_WINDOW {
+            latency.record(Duration::from_millis(10));
+        }
+        assert_eq!(latency.average_ms(), Some(10.0));
+        assert_eq!(latency.p95_ms(), Some(10));
+    }
 }
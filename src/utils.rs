@@ -1,10 +1,54 @@
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
 use std::error::Error as StdError;
-use std::fs::OpenOptions;
-use log::LevelFilter;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use log::{warn, LevelFilter};
 use env_logger::Builder;
 use tokio_retry::strategy::{ExponentialBackoff, jitter};
-use crate::config::{DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use axum::http::HeaderValue;
+use crate::config::{DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY, DEFAULT_TIMEZONE, LOG_FILE};
+
+// Process-wide display/scheduling timezone, set from config at startup
+static TIMEZONE: RwLock<Tz> = RwLock::new(DEFAULT_TIMEZONE);
+
+pub fn set_timezone(tz: Tz) {
+    *TIMEZONE.write().unwrap_or_else(|e| e.into_inner()) = tz;
+}
+
+pub fn timezone() -> Tz {
+    *TIMEZONE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Current time in the configured timezone, regardless of the server's own
+pub fn local_now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&timezone())
+}
+
+// Directory generated files (logs, stats) go under; unset means the working directory
+static DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Root every generated file under `dir`, creating it if missing.
+/// Call before `setup_logging` so the log lands there too.
+pub fn set_data_dir(dir: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    *DATA_DIR.write().unwrap_or_else(|e| e.into_inner()) = Some(PathBuf::from(dir));
+    Ok(())
+}
+
+/// Where a generated file is written: under the data directory when one is set.
+/// Absolute paths are used as given.
+pub fn data_path(name: impl AsRef<Path>) -> PathBuf {
+    match &*DATA_DIR.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(dir) => dir.join(name),
+        None => name.as_ref().to_path_buf(),
+    }
+}
 
 pub fn setup_logging() -> Result<(), Box<dyn StdError + Send + Sync>> {
     let mut builder = Builder::from_default_env();
@@ -13,7 +57,7 @@ pub fn setup_logging() -> Result<(), Box<dyn StdError + Send + Sync>> {
     let log_file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open("webreg_monitor.log")?;
+        .open(data_path(LOG_FILE))?;
 
     builder.target(env_logger::Target::Pipe(Box::new(log_file)));
     builder.init();
@@ -21,12 +65,190 @@ pub fn setup_logging() -> Result<(), Box<dyn StdError + Send + Sync>> {
     Ok(())
 }
 
-pub fn get_retry_strategy() -> impl Iterator<Item = Duration> {
-    ExponentialBackoff::from_millis(DEFAULT_RETRY_DELAY)
+/// Last `count` lines of a file, read backwards so a large log isn't loaded whole
+pub fn tail_lines(path: &Path, count: usize) -> io::Result<Vec<String>> {
+    const CHUNK: u64 = 64 * 1024;
+
+    let mut file = File::open(path)?;
+    let mut pos = file.seek(SeekFrom::End(0))?;
+    let mut buf = Vec::new();
+
+    // One newline more than requested, so a partial first line is never returned
+    while pos > 0 && buf.iter().filter(|&&b| b == b'\n').count() <= count {
+        let read = CHUNK.min(pos);
+        pos -= read;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut chunk = vec![0; read as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buf);
+        buf = chunk;
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(count);
+    Ok(lines[start..].iter().map(|line| line.to_string()).collect())
+}
+
+/// Retry count and base delay (milliseconds) for WebReg calls
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub retry_delay: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_RETRY_ATTEMPTS,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+/// Quick single adds after a failed enroll, while the opening is still fresh.
+/// The default makes none.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraceRetry {
+    pub retries: u32,
+    pub delay_ms: u64,
+}
+
+/// Jittered delays: up to `2 * retry_delay` ms before the first retry, then `retry_delay`
+/// raised to the retry number and doubled, capped at 60 s (reached by the second retry
+/// for any delay over ~175 ms)
+pub fn get_retry_strategy(max_retries: u32, retry_delay: u64) -> impl Iterator<Item = Duration> {
+    ExponentialBackoff::from_millis(retry_delay)
         .factor(2)
         .max_delay(Duration::from_secs(60))
         .map(jitter)
-        .take(DEFAULT_RETRY_ATTEMPTS as usize)
+        .take(max_retries as usize)
+}
+
+/// Retries left for one poll cycle, shared by every section's checks and adds so
+/// failures across many sections can't compound past the interval. A limit of 0
+/// leaves each call's own policy as the only bound.
+#[derive(Debug, Default)]
+pub struct RetryBudget {
+    limit: u32,
+    used: AtomicU32,
+}
+
+impl RetryBudget {
+    pub fn new(limit: u32) -> Self {
+        Self { limit, used: AtomicU32::new(0) }
+    }
+
+    /// Take one retry, or false once the cycle has spent them all
+    pub fn try_spend(&self) -> bool {
+        self.limit == 0
+            || self.used
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| (used < self.limit).then_some(used + 1))
+                .is_ok()
+    }
+}
+
+/// `get_retry_strategy` that also stops when the cycle's budget runs out
+pub fn budgeted_retry_strategy(policy: RetryPolicy, budget: &RetryBudget) -> impl Iterator<Item = Duration> + '_ {
+    get_retry_strategy(policy.max_retries, policy.retry_delay).take_while(move |_| budget.try_spend())
+}
+
+/// A section's check that was abandoned after running too long
+#[derive(Debug)]
+pub struct TimedOut(pub Duration);
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gave up after {}s", self.0.as_secs())
+    }
+}
+
+impl StdError for TimedOut {}
+
+/// Run one section's WebReg work, failing with `TimedOut` once it passes `limit`
+/// so a hung call can't hold the rest of the cycle (or its lock). Zero waits forever.
+pub async fn within<T>(
+    limit: Duration,
+    work: impl std::future::Future<Output = Result<T, Box<dyn StdError + Send + Sync>>>,
+) -> Result<T, Box<dyn StdError + Send + Sync>> {
+    if limit.is_zero() {
+        return work.await;
+    }
+    match tokio::time::timeout(limit, work).await {
+        Ok(result) => result,
+        Err(_) => {
+            warn!("WebReg work abandoned after {}s", limit.as_secs());
+            Err(Box::new(TimedOut(limit)))
+        }
+    }
+}
+
+/// Counts poll cycles since the last cookie check, so a long refresh interval can be
+/// cut short every `every` cycles. An `every` of 0 leaves the refresh timer alone.
+#[derive(Debug, Default)]
+pub struct SessionCheckCycles {
+    every: u32,
+    since_check: u32,
+}
+
+impl SessionCheckCycles {
+    pub fn new(every: u32) -> Self {
+        Self { every, since_check: 0 }
+    }
+
+    /// Record a finished poll cycle; true when the cookie is due for an early check
+    pub fn cycle_done(&mut self) -> bool {
+        self.since_check += 1;
+        self.every > 0 && self.since_check >= self.every
+    }
+
+    pub fn checked(&mut self) {
+        self.since_check = 0;
+    }
+}
+
+/// Random offset in `[0, interval)` so jobs started together don't poll or refresh in lockstep
+pub fn initial_offset(interval_secs: u64) -> Duration {
+    jitter(Duration::from_secs(interval_secs))
+}
+
+/// Polling interval with up to 10% random jitter added on each cycle
+pub fn jittered_interval(interval_secs: u64) -> Duration {
+    let base = Duration::from_secs(interval_secs);
+    base + jitter(base / 10)
+}
+
+/// CORS for the web servers. Origins come from the comma-separated `ALLOWED_ORIGINS`;
+/// any origin is only allowed when `DEV_MODE=true`, otherwise cross-origin requests are refused.
+pub fn cors_layer() -> CorsLayer {
+    let layer = CorsLayer::new().allow_methods(Any).allow_headers(Any);
+
+    let origins: Vec<HeaderValue> = std::env::var("ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("Ignoring invalid origin in ALLOWED_ORIGINS: {}", origin);
+                None
+            }
+        })
+        .collect();
+
+    if !origins.is_empty() {
+        return layer.allow_origin(AllowOrigin::list(origins));
+    }
+
+    let dev_mode = std::env::var("DEV_MODE").map(|v| v == "true" || v == "1").unwrap_or(false);
+    if dev_mode {
+        warn!("DEV_MODE is set and ALLOWED_ORIGINS is empty; allowing requests from any origin");
+        layer.allow_origin(Any)
+    } else {
+        // Same-origin requests (the bundled frontend) don't need CORS headers
+        layer
+    }
 }
 
 pub fn format_duration(duration: Duration) -> String {
@@ -36,3 +258,48 @@ pub fn format_duration(duration: Duration) -> String {
     let seconds = seconds % 60;
     format!("{}h {}m {}s", hours, minutes, seconds)
 }
+
+/// Resolves on each SIGHUP, used to trigger a config reload.
+/// On platforms without SIGHUP it never resolves.
+pub struct ReloadSignal {
+    #[cfg(unix)]
+    inner: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            inner: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+        })
+    }
+
+    pub async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.inner.recv().await;
+
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_lines_short_files_and_trailing_newline() {
+        let path = std::env::temp_dir().join(format!("webreg_tail_test_{}.log", std::process::id()));
+
+        // Fewer lines than asked for returns them all
+        std::fs::write(&path, "one\ntwo").unwrap();
+        assert_eq!(tail_lines(&path, 5).unwrap(), vec!["one", "two"]);
+
+        // A trailing newline doesn't count as an empty last line
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(tail_lines(&path, 2).unwrap(), vec!["two", "three"]);
+        assert_eq!(tail_lines(&path, 3).unwrap(), vec!["one", "two", "three"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
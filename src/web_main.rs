@@ -15,20 +15,25 @@ use tokio::sync::Mutex;
 use std::error::Error as StdError;
 use log::info;
 
-use state::AppState;
-use utils::setup_logging;
+use state::{AppState, load_config};
+use utils::{set_data_dir, setup_logging};
 use web_server::start_web_server;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     println!("Starting WebReg Auto-Enroller Web Server...");
 
+    let config = load_config()?;
+    if let Some(dir) = &config.monitoring.data_dir {
+        set_data_dir(dir)?;
+    }
+
     // Setup logging
     setup_logging()?;
     info!("Starting WebReg Auto-Enroller Web Server...");
 
     // Initialize application state
-    let state = Arc::new(Mutex::new(AppState::new().await?));
+    let state = Arc::new(Mutex::new(AppState::new(config).await?));
 
     // Start web server
     let port = 3000;
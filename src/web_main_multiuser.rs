@@ -12,12 +12,16 @@ mod encryption;
 mod auth;
 mod multi_user_state;
 mod multi_user_api;
+mod selftest;
+mod quota;
+mod error;
 
 use std::sync::Arc;
 use std::error::Error as StdError;
 use log::info;
 use dotenv::dotenv;
 
+use config::ServerConfig;
 use multi_user_state::MultiUserState;
 use multi_user_api::{create_router, MultiUserApiState};
 use encryption::EncryptionKey;
@@ -30,17 +34,35 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     // Load environment variables
     dotenv().ok();
 
+    let settings = ServerConfig::from_env();
+    utils::set_timezone(settings.timezone);
+    if let Some(dir) = &settings.data_dir {
+        utils::set_data_dir(dir)?;
+    }
+    if let Some(proxy) = &settings.proxy_url {
+        reqwest::Proxy::all(proxy.as_str()).map_err(|e| format!("Invalid WEBREG_PROXY '{}': {}", proxy, e))?;
+    }
+
     // Setup logging
     setup_logging()?;
     info!("Starting WebReg Auto-Enroller Multi-User Web Server...");
 
+    // Verify the deployment's environment and exit instead of serving
+    if std::env::args().any(|arg| arg == "--selftest") {
+        let passed = selftest::run().await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     // Get database URL from environment
     let database_url = std::env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set in .env file");
 
     // Initialize database connection pool
-    info!("Connecting to database...");
-    let pool = db::init_pool(&database_url).await?;
+    info!(
+        "Connecting to database (max {} connections, {}s acquire timeout)...",
+        settings.db_max_connections, settings.db_acquire_timeout
+    );
+    let pool = db::init_pool(&database_url, &settings).await?;
     info!("Database connected successfully");
 
     // Initialize encryption key
@@ -49,7 +71,20 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     info!("Encryption initialized");
 
     // Create multi-user state
-    let state = Arc::new(MultiUserState::new(pool, encryption_key));
+    info!("Allowing up to {} concurrent WebReg requests", settings.max_concurrent_requests);
+    let state = Arc::new(MultiUserState::new(pool, encryption_key, settings));
+
+    // Pick up the jobs that were running when the server last stopped
+    tokio::spawn(Arc::clone(&state).resume_active_jobs());
+
+    // Restart job loops that crash or hang
+    tokio::spawn(Arc::clone(&state).run_watchdog());
+
+    // Deliver queued notifications, including any a previous run didn't get to
+    tokio::spawn(Arc::clone(&state).run_outbox());
+
+    // Resident job counts, for spotting jobs left in memory after they stop
+    tokio::spawn(Arc::clone(&state).run_metrics_log());
 
     // Create API state
     let api_state = Arc::new(MultiUserApiState { state });
@@ -58,12 +93,7 @@ async fn main() -> Result<(), Box<dyn StdError + Send + Sync>> {
     let app = create_router(api_state);
 
     // Add CORS middleware
-    let app = app.layer(
-        tower_http::cors::CorsLayer::new()
-            .allow_origin(tower_http::cors::Any)
-            .allow_methods(tower_http::cors::Any)
-            .allow_headers(tower_http::cors::Any)
-    );
+    let app = app.layer(utils::cors_layer());
 
     // Serve static files
     let app = app.nest_service(
@@ -2,11 +2,11 @@ use axum::Router;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tower_http::services::ServeDir;
-use tower_http::cors::{CorsLayer, Any};
 
 use crate::api::{create_router, ApiState};
 use crate::state::AppState;
 use crate::job_manager::JobManager;
+use crate::utils::cors_layer;
 
 pub async fn start_web_server(
     app_state: Arc<Mutex<AppState>>,
@@ -22,10 +22,7 @@ pub async fn start_web_server(
     let api_router = create_router(api_state);
 
     // Create CORS layer
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let cors = cors_layer();
 
     // Serve static files from the "static" directory
     let serve_dir = ServeDir::new("static");
@@ -1,34 +1,580 @@
 use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use webweg::types::{EnrollmentStatus, WrapperError};
 use webweg::wrapper::WebRegWrapper;
-use log::info;
+use webweg::wrapper::input_types::{AddType, EnrollWaitAdd, ExplicitAddType, GradeOption, PlanAdd};
+use log::{info, warn};
+use serde::Serialize;
 use crate::config::WebRegConfig;
+use crate::notifier::Notifier;
+use crate::utils::local_now;
 
-pub async fn initialize_webreg(config: &WebRegConfig) -> Result<WebRegWrapper, Box<dyn StdError + Send + Sync>> {
+/// The seat counts monitoring needs from one row of WebReg's course listing
+#[derive(Debug, Clone)]
+pub struct SectionAvailability {
+    pub section_id: String,
+    pub section_code: String,
+    pub available_seats: i64,
+    pub total_seats: i64,
+    pub enrolled_ct: i64,
+    pub waitlist_ct: i64,
+    pub raw: String,  // Debug dump of WebReg's full response for the details log
+}
+
+/// One section on the user's WebReg schedule
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleEntry {
+    pub department: String,
+    pub course_code: String,
+    pub course_title: String,
+    pub section_code: String,
+    pub section_id: String,
+    pub status: &'static str,  // "enrolled", "waitlisted" or "planned"
+}
+
+/// WebReg answered with its rate-limit response rather than an ordinary failure
+#[derive(Debug)]
+pub struct Throttled {
+    pub status: u16,
+}
+
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WebReg is throttling requests (HTTP {})", self.status)
+    }
+}
+
+impl StdError for Throttled {}
+
+// Status WebReg sends once a session is making too many requests
+const THROTTLE_STATUS: u16 = 429;
+
+/// Turn webweg's throttle response into `Throttled` so callers can tell it from network errors
+fn classify_error(e: WrapperError) -> Box<dyn StdError + Send + Sync> {
+    match e {
+        WrapperError::BadStatusCode(status, ..) if status == THROTTLE_STATUS => Box::new(Throttled { status }),
+        e => Box::new(e),
+    }
+}
+
+pub fn is_throttled(e: &(dyn StdError + Send + Sync + 'static)) -> bool {
+    e.downcast_ref::<Throttled>().is_some()
+}
+
+// Cooldown after the first throttle response, doubled on each one after until the cap
+const THROTTLE_BASE_COOLDOWN: Duration = Duration::from_secs(2 * 60);
+const THROTTLE_MAX_COOLDOWN: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Default)]
+struct ThrottleState {
+    strikes: u32,
+    until: Option<Instant>,
+}
+
+/// Job-wide cooldown once WebReg starts throttling. Cloning shares the same state,
+/// so every section in a job backs off together.
+#[derive(Debug, Clone, Default)]
+pub struct ThrottleBackoff {
+    state: Arc<Mutex<ThrottleState>>,
+}
+
+impl ThrottleBackoff {
+    /// Time left before WebReg should be contacted again, if cooling down
+    pub fn cooldown_remaining(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Start (or lengthen) the cooldown. Returns true for the first throttle of an episode,
+    /// so the caller notifies once rather than on every strike.
+    pub fn record_throttle(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let cooldown = THROTTLE_BASE_COOLDOWN
+            .saturating_mul(2u32.saturating_pow(state.strikes))
+            .min(THROTTLE_MAX_COOLDOWN);
+        state.strikes += 1;
+        state.until = Some(Instant::now() + cooldown);
+        warn!("WebReg is throttling requests, backing off for {}s", cooldown.as_secs());
+        state.strikes == 1
+    }
+
+    /// A request went through, so the throttle episode is over
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.strikes > 0 {
+            info!("WebReg accepted requests again after throttling");
+        }
+        *state = ThrottleState::default();
+    }
+}
+
+/// Start the job-wide cooldown for a throttle response, notifying once per episode
+pub async fn back_off_if_throttled(
+    e: &(dyn StdError + Send + Sync + 'static),
+    throttle: &ThrottleBackoff,
+    notifier: &Notifier,
+) {
+    if is_throttled(e) && throttle.record_throttle() {
+        let msg = format!(
+            "⚠️  WebReg is throttling us, backing off.\n\
+            Time: {}\n\
+            Monitoring pauses for a few minutes and resumes on its own.",
+            local_now().format("%Y-%m-%d %H:%M:%S")
+        );
+        notifier.send_alert(&msg).await;
+    }
+}
+
+/// The WebReg calls monitoring and enrollment depend on, so they can run against a mock
+#[async_trait]
+pub trait WebRegClient: Send + Sync {
+    async fn get_course_info(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+    ) -> Result<Vec<SectionAvailability>, Box<dyn StdError + Send + Sync>>;
+
+    /// Enroll in (or waitlist) a section, returning WebReg's success flag
+    async fn add_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>>;
+
+    /// Whether the user's schedule holds the section: enrolled, or waitlisted when `waitlist`.
+    /// `add_section` can report success even when the seat was lost in a race.
+    async fn is_on_schedule(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>>;
+
+    /// Put a section on the user's plan without enrolling
+    async fn add_to_plan(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+        section_id: &str,
+        section_code: &str,
+        unit_count: u8,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>>;
+
+    /// Drop an enrolled section, or leave its waitlist when `waitlist`
+    async fn drop_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>>;
+}
+
+#[async_trait]
+impl WebRegClient for WebRegWrapper {
+    async fn get_course_info(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+    ) -> Result<Vec<SectionAvailability>, Box<dyn StdError + Send + Sync>> {
+        let sections = self.req(term).parsed().get_course_info(department, course_code).await
+            .map_err(classify_error)?;
+
+        Ok(sections.into_iter().map(|s| SectionAvailability {
+            raw: format!("{:#?}", s),
+            section_id: s.section_id,
+            section_code: s.section_code,
+            available_seats: s.available_seats,
+            total_seats: s.total_seats,
+            enrolled_ct: s.enrolled_ct,
+            waitlist_ct: s.waitlist_ct,
+        }).collect())
+    }
+
+    async fn add_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        let add_type = if waitlist { AddType::Waitlist } else { AddType::Enroll };
+
+        let enroll_request = EnrollWaitAdd::builder()
+            .with_section_id(section_id)
+            .with_grading_option(GradeOption::L)
+            .try_build()
+            .ok_or("Failed to build enrollment request")?;
+
+        self.req(term).parsed().add_section(add_type, enroll_request, true).await
+            .map_err(classify_error)
+    }
+
+    async fn is_on_schedule(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        let schedule = self.req(term).parsed().get_schedule(None).await
+            .map_err(classify_error)?;
+
+        Ok(schedule.iter().any(|s| {
+            s.section_id == section_id && match s.enrolled_status {
+                EnrollmentStatus::Enrolled => true,
+                EnrollmentStatus::Planned => false,
+                // Anything else on the schedule is a waitlist position
+                _ => waitlist,
+            }
+        }))
+    }
+
+    async fn add_to_plan(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+        section_id: &str,
+        section_code: &str,
+        unit_count: u8,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        let plan_request = PlanAdd::builder()
+            .with_subject_code(department)
+            .with_course_code(course_code)
+            .with_section_id(section_id)
+            .with_section_code(section_code)
+            .with_unit_count(unit_count)
+            .try_build()
+            .ok_or("Failed to build plan request")?;
+
+        self.req(term).parsed().add_to_plan(plan_request, false).await
+            .map_err(classify_error)
+    }
+
+    async fn drop_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        let drop_type = if waitlist { ExplicitAddType::Waitlist } else { ExplicitAddType::Enroll };
+        self.req(term).parsed().drop_section(drop_type, section_id).await
+            .map_err(classify_error)
+    }
+}
+
+// Lets the loops pass their shared wrapper straight through
+#[async_trait]
+impl<T: WebRegClient + ?Sized> WebRegClient for Arc<T> {
+    async fn get_course_info(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+    ) -> Result<Vec<SectionAvailability>, Box<dyn StdError + Send + Sync>> {
+        (**self).get_course_info(term, department, course_code).await
+    }
+
+    async fn add_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        (**self).add_section(term, section_id, waitlist).await
+    }
+
+    async fn is_on_schedule(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        (**self).is_on_schedule(term, section_id, waitlist).await
+    }
+
+    async fn add_to_plan(
+        &self,
+        term: &str,
+        department: &str,
+        course_code: &str,
+        section_id: &str,
+        section_code: &str,
+        unit_count: u8,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        (**self).add_to_plan(term, department, course_code, section_id, section_code, unit_count).await
+    }
+
+    async fn drop_section(
+        &self,
+        term: &str,
+        section_id: &str,
+        waitlist: bool,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        (**self).drop_section(term, section_id, waitlist).await
+    }
+}
+
+/// Build a wrapper from config without contacting WebReg.
+/// The User-Agent and extra headers are only applied when configured.
+pub fn build_wrapper(config: &WebRegConfig) -> Result<WebRegWrapper, Box<dyn StdError + Send + Sync>> {
+    let mut builder = WebRegWrapper::builder().with_cookies(&config.cookie);
+
+    if let Some(user_agent) = &config.user_agent {
+        builder = builder.with_user_agent(user_agent);
+    }
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &config.headers {
+        headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+    }
+    let timeout = Duration::from_secs(config.request_timeout_secs);
+    builder = builder.with_client(http_client(headers, config.proxy.as_deref(), timeout)?);
+
+    Ok(builder.try_build_wrapper().ok_or("Failed to build WebReg wrapper")?)
+}
+
+/// Build a wrapper for `cookie`, routed through `proxy` when one is set.
+/// The proxy URL is checked at startup, so a bad one only shows up here as `None`.
+pub fn cookie_wrapper(cookie: &str, proxy: Option<&str>, timeout: Duration) -> Option<WebRegWrapper> {
+    let client = http_client(HeaderMap::new(), proxy, timeout).ok()?;
+    WebRegWrapper::builder().with_cookies(cookie).with_client(client).try_build_wrapper()
+}
+
+/// The HTTP client a wrapper sends through. A request running past `timeout` fails like any
+/// other WebReg error instead of holding up the poll cycle; zero leaves requests unbounded.
+fn http_client(headers: HeaderMap, proxy: Option<&str>, timeout: Duration) -> Result<reqwest::Client, Box<dyn StdError + Send + Sync>> {
+    let mut client = reqwest::Client::builder().default_headers(headers);
+    if let Some(proxy) = proxy {
+        client = client.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    if !timeout.is_zero() {
+        client = client.timeout(timeout);
+    }
+    Ok(client.build()?)
+}
+
+/// Build a wrapper and associate it with every term in `terms`
+pub async fn initialize_webreg(config: &WebRegConfig, terms: &[String]) -> Result<WebRegWrapper, Box<dyn StdError + Send + Sync>> {
     println!("Starting initialize_webreg");
     println!("Cookie length: {}", config.cookie.len());
 
-    let wrapper = WebRegWrapper::builder()
-        .with_cookies(&config.cookie)
-        .try_build_wrapper()
-        .ok_or("Failed to build WebReg wrapper")?;
+    let wrapper = build_wrapper(config)?;
 
     println!("Successfully built wrapper, attempting to associate term");
 
-    let result = wrapper.associate_term(&config.term).await;
+    let result = associate_terms(&wrapper, terms).await;
     match &result {
         Ok(_) => println!("Successfully associated term"),
         Err(e) => println!("Error associating term: {:?}", e),
     }
 
     result?;
-    info!("Successfully initialized WebReg connection for term {}", config.term);
+    info!("Successfully initialized WebReg connection for terms {}", terms.join(", "));
 
     Ok(wrapper)
 }
 
-pub async fn is_connection_valid(wrapper: &WebRegWrapper, term: &str) -> bool {
-    match wrapper.associate_term(term).await {
-        Ok(_) => true,
-        Err(_) => false
+/// Associate each term with the session once. Requests then pick their term with
+/// `wrapper.req(term)`, so polling several terms never re-associates.
+pub async fn associate_terms(wrapper: &WebRegWrapper, terms: &[String]) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    for term in terms {
+        wrapper.associate_term(term).await
+            .map_err(|e| format!("Failed to associate term {}: {}", term, e))?;
+    }
+    Ok(())
+}
+
+/// The user's schedule for `term` as WebReg reports it right now
+pub async fn fetch_schedule(wrapper: &WebRegWrapper, term: &str) -> Result<Vec<ScheduleEntry>, Box<dyn StdError + Send + Sync>> {
+    let schedule = wrapper.req(term).parsed().get_schedule(None).await
+        .map_err(classify_error)?;
+
+    Ok(schedule.into_iter().map(|s| ScheduleEntry {
+        status: match s.enrolled_status {
+            EnrollmentStatus::Enrolled => "enrolled",
+            EnrollmentStatus::Planned => "planned",
+            _ => "waitlisted",
+        },
+        department: s.subject_code,
+        course_code: s.course_code,
+        course_title: s.course_title,
+        section_code: s.section_code,
+        section_id: s.section_id,
+    }).collect())
+}
+
+/// Terms the cookie's account can access, by term code (e.g. "FA25")
+pub async fn accessible_terms(wrapper: &WebRegWrapper) -> Result<Vec<String>, Box<dyn StdError + Send + Sync>> {
+    let terms = wrapper.get_all_terms().await?;
+    Ok(terms.into_iter().map(|term| term.term_code).collect())
+}
+
+/// What a session check found; only `Expired` means the cookie itself is bad
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStatus {
+    Valid,
+    Expired,
+    /// The cookie works but the term isn't one the account can access
+    TermUnavailable,
+    /// WebReg couldn't be reached or answered with a server error
+    Unreachable(String),
+}
+
+impl SessionStatus {
+    pub fn is_valid(&self) -> bool {
+        *self == SessionStatus::Valid
+    }
+}
+
+/// Associate the term and classify any failure, so a network blip isn't reported as an expired cookie
+pub async fn check_session(wrapper: &WebRegWrapper, term: &str) -> SessionStatus {
+    let e = match wrapper.associate_term(term).await {
+        Ok(_) => return SessionStatus::Valid,
+        Err(e) => e,
+    };
+    if let Some(reason) = unreachable_reason(&e) {
+        return SessionStatus::Unreachable(reason);
+    }
+
+    // The term list only loads with a working cookie, which tells a bad term apart from a bad session
+    match accessible_terms(wrapper).await {
+        Ok(terms) if !terms.iter().any(|t| t == term) => SessionStatus::TermUnavailable,
+        _ => SessionStatus::Expired,
+    }
+}
+
+/// Failures that say nothing about the cookie: no response, throttling, or a server error
+fn unreachable_reason(e: &WrapperError) -> Option<String> {
+    match e {
+        WrapperError::RequestError(e) => Some(e.to_string()),
+        WrapperError::BadStatusCode(status, ..) if *status == THROTTLE_STATUS || *status >= 500 => {
+            Some(format!("WebReg responded with HTTP {}", status))
+        }
+        _ => None,
+    }
+}
+
+/// Scripted stand-in for WebReg, for tests of the monitor and enroll flows
+#[cfg(test)]
+pub mod mock {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use super::*;
+
+    #[derive(Default)]
+    pub struct MockWebReg {
+        course_info: Mutex<VecDeque<Result<Vec<SectionAvailability>, Throttled>>>,
+        add_results: Mutex<VecDeque<Result<bool, String>>>,
+        pub add_calls: Mutex<Vec<(String, bool)>>,
+        pub drop_calls: Mutex<Vec<String>>,
+        pub plan_calls: Mutex<Vec<String>>,
+        /// Make `is_on_schedule` report the seat as missing
+        pub off_schedule: Mutex<bool>,
+    }
+
+    impl MockWebReg {
+        /// Queue the listing returned by the next `get_course_info` call
+        pub fn push_course_info(&self, sections: Vec<SectionAvailability>) -> &Self {
+            self.course_info.lock().unwrap().push_back(Ok(sections));
+            self
+        }
+
+        /// Make the next `get_course_info` call hit WebReg's throttle response
+        pub fn push_throttle(&self) -> &Self {
+            self.course_info.lock().unwrap().push_back(Err(Throttled { status: THROTTLE_STATUS }));
+            self
+        }
+
+        /// Queue the outcome of the next `add_section` call
+        pub fn push_add_result(&self, result: Result<bool, String>) -> &Self {
+            self.add_results.lock().unwrap().push_back(result);
+            self
+        }
+
+        pub fn remaining_course_info(&self) -> usize {
+            self.course_info.lock().unwrap().len()
+        }
+    }
+
+    pub fn section(section_code: &str, section_id: &str, available_seats: i64, waitlist_ct: i64) -> SectionAvailability {
+        SectionAvailability {
+            section_id: section_id.to_string(),
+            section_code: section_code.to_string(),
+            available_seats,
+            total_seats: 100,
+            enrolled_ct: 100 - available_seats.max(0),
+            waitlist_ct,
+            raw: String::new(),
+        }
+    }
+
+    #[async_trait]
+    impl WebRegClient for MockWebReg {
+        async fn get_course_info(
+            &self,
+            _term: &str,
+            _department: &str,
+            _course_code: &str,
+        ) -> Result<Vec<SectionAvailability>, Box<dyn StdError + Send + Sync>> {
+            match self.course_info.lock().unwrap().pop_front() {
+                Some(Ok(sections)) => Ok(sections),
+                Some(Err(throttled)) => Err(Box::new(throttled)),
+                None => Err("No scripted course info left".into()),
+            }
+        }
+
+        async fn add_section(
+            &self,
+            _term: &str,
+            section_id: &str,
+            waitlist: bool,
+        ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+            self.add_calls.lock().unwrap().push((section_id.to_string(), waitlist));
+            self.add_results.lock().unwrap()
+                .pop_front()
+                .unwrap_or_else(|| Err("No scripted add result left".to_string()))
+                .map_err(|e| e.into())
+        }
+
+        async fn is_on_schedule(
+            &self,
+            _term: &str,
+            _section_id: &str,
+            _waitlist: bool,
+        ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+            Ok(!*self.off_schedule.lock().unwrap())
+        }
+
+        async fn add_to_plan(
+            &self,
+            _term: &str,
+            _department: &str,
+            _course_code: &str,
+            section_id: &str,
+            _section_code: &str,
+            _unit_count: u8,
+        ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+            self.plan_calls.lock().unwrap().push(section_id.to_string());
+            Ok(true)
+        }
+
+        async fn drop_section(
+            &self,
+            _term: &str,
+            section_id: &str,
+            _waitlist: bool,
+        ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+            self.drop_calls.lock().unwrap().push(section_id.to_string());
+            Ok(true)
+        }
     }
 }
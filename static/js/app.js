@@ -19,7 +19,7 @@ function setupModeToggle() {
     const thresholdGroup = document.getElementById('threshold-group');
 
     modeSelect.addEventListener('change', (e) => {
-        if (e.target.value === 'include') {
+        if (e.target.value === 'available') {
             thresholdGroup.style.display = 'none';
         } else {
             thresholdGroup.style.display = 'block';
@@ -27,7 +27,7 @@ function setupModeToggle() {
     });
 
     // Initial setup
-    if (modeSelect.value === 'include') {
+    if (modeSelect.value === 'available') {
         thresholdGroup.style.display = 'none';
     }
 }
@@ -50,8 +50,9 @@ async function loadConfiguration() {
         // Populate form fields
         document.getElementById('term').value = config.term || '';
         document.getElementById('polling-interval').value = config.polling_interval || 30;
-        document.getElementById('monitoring-mode').value = config.monitoring_mode || 'include';
+        document.getElementById('monitoring-mode').value = config.monitoring_mode || 'available';
         document.getElementById('seat-threshold').value = config.seat_threshold || 3;
+        document.getElementById('threshold-direction').value = config.threshold_direction || 'at_most';
 
         // Trigger mode change event
         document.getElementById('monitoring-mode').dispatchEvent(new Event('change'));
@@ -252,6 +253,7 @@ async function saveConfiguration() {
         const pollingInterval = parseInt(document.getElementById('polling-interval').value);
         const monitoringMode = document.getElementById('monitoring-mode').value;
         const seatThreshold = parseInt(document.getElementById('seat-threshold').value);
+        const thresholdDirection = document.getElementById('threshold-direction').value;
 
         if (!term || !cookie) {
             showMessage('Please fill in all required fields (Term and Cookie)', 'error');
@@ -281,7 +283,8 @@ async function saveConfiguration() {
                 sections: c.sections || []
             })),
             seat_threshold: seatThreshold,
-            monitoring_mode: monitoringMode
+            monitoring_mode: monitoringMode,
+            threshold_direction: thresholdDirection
         };
 
         const response = await fetch(`${API_BASE}/jobs`, {
@@ -384,6 +384,7 @@ async function handleJobSubmit(e) {
         polling_interval: parseInt(document.getElementById('job-polling').value),
         seat_threshold: parseInt(document.getElementById('job-threshold').value),
         monitoring_mode: document.getElementById('job-mode').value,
+        threshold_direction: document.getElementById('job-direction').value,
         courses: []
     };
 
@@ -439,6 +440,9 @@ async function loadNotifications() {
             const settings = response.data;
             document.getElementById('gmail-address').value = settings.gmail_address || '';
             document.getElementById('discord-webhook').value = settings.discord_webhook_url || '';
+            document.getElementById('gmail-password-status').textContent = settings.has_gmail_password
+                ? 'A password is saved. Leave this blank to keep it.'
+                : 'No password saved.';
 
             const recipients = settings.email_recipients || [];
             document.getElementById('email-recipients').value = recipients.join('\n');
@@ -471,6 +475,7 @@ async function handleNotificationsSubmit(e) {
 
         showSuccess('Notifications updated successfully');
         document.getElementById('gmail-password').value = ''; // Clear password field
+        await loadNotifications();
     } catch (error) {
         console.error('Error updating notifications:', error);
         showError('Failed to update notifications');